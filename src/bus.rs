@@ -0,0 +1,227 @@
+use super::interrupt::*;
+use super::mapper::*;
+use super::ppu::*;
+
+pub const MEMORY_SIZE: usize = 0x10000;
+
+// everything the CPU can reach through its address and data pins
+pub trait Bus {
+    // a real bus access: reading a register may clear flags, advance buffers, ...
+    fn read(&mut self, address: u16) -> u8;
+    fn write(&mut self, address: u16, data: u8);
+    // what a read would return, without any side effects (debuggers, tracing)
+    fn peek(&self, address: u16) -> u8;
+    // called after every access so devices can drive the CPU's NMI and IRQ inputs
+    fn poll_interrupts(&mut self, _lines: &mut InterruptLines) {}
+    // the CPU reports the cycles of each instruction once it has finished
+    fn tick(&mut self, _cycles: u8) {}
+    // the host's hand on the cartridge slot, for battery saves and disk swaps
+    fn cartridge(&self) -> Option<&dyn Mapper> {
+        None
+    }
+    fn cartridge_mut(&mut self) -> Option<&mut dyn Mapper> {
+        None
+    }
+}
+
+// 64 KiB of plain RAM without any devices, used by the CPU unit tests
+pub struct FlatRamBus {
+    memory: Vec<u8>,
+}
+
+impl FlatRamBus {
+    pub fn new() -> Self {
+        FlatRamBus {
+            memory: vec![0; MEMORY_SIZE],
+        }
+    }
+}
+
+impl Default for FlatRamBus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Bus for FlatRamBus {
+    fn read(&mut self, address: u16) -> u8 {
+        self.memory[address as usize]
+    }
+    fn write(&mut self, address: u16, data: u8) {
+        self.memory[address as usize] = data;
+    }
+    fn peek(&self, address: u16) -> u8 {
+        self.memory[address as usize]
+    }
+}
+
+const RAM_SIZE: usize = 0x0800;
+const RAM_MIRRORS_END: u16 = 0x1fff;
+const PPU_REGISTERS_COUNT: usize = 8;
+const PPU_REGISTERS_MIRRORS_END: u16 = 0x3fff;
+const APU_IO_REGISTERS_START: u16 = 0x4000;
+const APU_IO_REGISTERS_END: u16 = 0x4017;
+const APU_IO_REGISTERS_COUNT: usize = 0x18;
+const TEST_MODE_END: u16 = 0x401f;
+const CARTRIDGE_SPACE_START: u16 = 0x4020;
+
+enum NesAddress {
+    Ram(usize),
+    PpuRegister(usize),
+    ApuIoRegister(usize),
+    // only enabled on test hardware, a retail console leaves the data bus floating
+    TestMode,
+    Cartridge,
+}
+
+impl NesAddress {
+    fn decode(address: u16) -> NesAddress {
+        match address {
+            0x0000..=RAM_MIRRORS_END => NesAddress::Ram(address as usize % RAM_SIZE),
+            0x2000..=PPU_REGISTERS_MIRRORS_END => {
+                NesAddress::PpuRegister(address as usize % PPU_REGISTERS_COUNT)
+            }
+            APU_IO_REGISTERS_START..=APU_IO_REGISTERS_END => {
+                NesAddress::ApuIoRegister((address - APU_IO_REGISTERS_START) as usize)
+            }
+            0x4018..=TEST_MODE_END => NesAddress::TestMode,
+            CARTRIDGE_SPACE_START..=0xffff => NesAddress::Cartridge,
+        }
+    }
+}
+
+// the CPU side of the NES: internal RAM, register windows and the cartridge slot
+pub struct NesBus {
+    ram: [u8; RAM_SIZE],
+    ppu: Ppu,
+    // plain latches until the APU is attached
+    apu_io_registers: [u8; APU_IO_REGISTERS_COUNT],
+    cartridge: Option<Box<dyn Mapper>>,
+    // last value driven on the data bus, returned by reads nothing answers
+    open_bus: u8,
+    // the cartridge's audio, summed once per CPU cycle until the mixer takes it
+    expansion_audio_sum: f32,
+    expansion_audio_cycles: u32,
+}
+
+impl NesBus {
+    pub fn new() -> Self {
+        NesBus {
+            ram: [0; RAM_SIZE],
+            ppu: Ppu::new(),
+            apu_io_registers: [0; APU_IO_REGISTERS_COUNT],
+            cartridge: None,
+            open_bus: 0,
+            expansion_audio_sum: 0.0,
+            expansion_audio_cycles: 0,
+        }
+    }
+    pub fn insert_cartridge(&mut self, mapper: Box<dyn Mapper>) {
+        self.cartridge = Some(mapper);
+    }
+    pub fn remove_cartridge(&mut self) -> Option<Box<dyn Mapper>> {
+        self.cartridge.take()
+    }
+    pub fn ppu(&self) -> &Ppu {
+        &self.ppu
+    }
+    pub fn ppu_mut(&mut self) -> &mut Ppu {
+        &mut self.ppu
+    }
+    // average of the per-cycle expansion audio since the last call, one mixer output sample
+    pub fn take_expansion_audio(&mut self) -> f32 {
+        let sample = if self.expansion_audio_cycles == 0 {
+            0.0
+        } else {
+            self.expansion_audio_sum / self.expansion_audio_cycles as f32
+        };
+        self.expansion_audio_sum = 0.0;
+        self.expansion_audio_cycles = 0;
+        sample
+    }
+}
+
+impl Default for NesBus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Bus for NesBus {
+    fn read(&mut self, address: u16) -> u8 {
+        let data = match NesAddress::decode(address) {
+            NesAddress::PpuRegister(index) => {
+                Some(self.ppu.read_register(index, self.cartridge.as_mut()))
+            }
+            NesAddress::Cartridge => self
+                .cartridge
+                .as_mut()
+                .and_then(|mapper| mapper.cpu_read(address)),
+            _ => Some(self.peek(address)),
+        };
+        self.open_bus = data.unwrap_or(self.open_bus);
+        self.open_bus
+    }
+    fn write(&mut self, address: u16, data: u8) {
+        self.open_bus = data;
+        let decoded = NesAddress::decode(address);
+        if !matches!(decoded, NesAddress::Cartridge) {
+            if let Some(mapper) = self.cartridge.as_mut() {
+                mapper.snoop_cpu_write(address, data);
+            }
+        }
+        match decoded {
+            NesAddress::Ram(index) => self.ram[index] = data,
+            NesAddress::PpuRegister(index) => {
+                self.ppu
+                    .write_register(index, data, self.cartridge.as_mut())
+            }
+            NesAddress::ApuIoRegister(index) => self.apu_io_registers[index] = data,
+            NesAddress::TestMode => {}
+            NesAddress::Cartridge => {
+                if let Some(mapper) = self.cartridge.as_mut() {
+                    mapper.cpu_write(address, data);
+                }
+            }
+        }
+    }
+    fn peek(&self, address: u16) -> u8 {
+        match NesAddress::decode(address) {
+            NesAddress::Ram(index) => self.ram[index],
+            NesAddress::PpuRegister(index) => self.ppu.peek_register(index),
+            NesAddress::ApuIoRegister(index) => self.apu_io_registers[index],
+            NesAddress::TestMode => self.open_bus,
+            NesAddress::Cartridge => self
+                .cartridge
+                .as_ref()
+                .and_then(|mapper| mapper.cpu_peek(address))
+                .unwrap_or(self.open_bus),
+        }
+    }
+    fn tick(&mut self, cycles: u8) {
+        self.ppu.tick(cycles);
+        if let Some(mapper) = self.cartridge.as_mut() {
+            for _ in 0..cycles {
+                mapper.cpu_clock();
+                self.expansion_audio_sum += mapper.audio_output();
+                self.expansion_audio_cycles += 1;
+            }
+        }
+    }
+    fn poll_interrupts(&mut self, lines: &mut InterruptLines) {
+        lines.set_nmi_line(self.ppu.nmi_asserted());
+        match &self.cartridge {
+            Some(mapper) if mapper.irq_asserted() => lines.assert_irq(IrqSource::Cartridge),
+            _ => lines.release_irq(IrqSource::Cartridge),
+        }
+    }
+    fn cartridge(&self) -> Option<&dyn Mapper> {
+        self.cartridge.as_deref()
+    }
+    fn cartridge_mut(&mut self) -> Option<&mut dyn Mapper> {
+        match self.cartridge.as_mut() {
+            Some(mapper) => Some(mapper.as_mut()),
+            None => None,
+        }
+    }
+}
@@ -0,0 +1,302 @@
+use super::timing::*;
+use std::fmt;
+
+const HEADER_SIZE: usize = 16;
+const NES_SIGNATURE: [u8; 4] = [b'N', b'E', b'S', 0x1a];
+const TRAINER_SIZE: usize = 512;
+const PRG_ROM_BANK_SIZE: usize = 0x4000;
+const CHR_ROM_BANK_SIZE: usize = 0x2000;
+
+const VERTICAL_MIRRORING_FLAG: u8 = 0b0000_0001;
+const BATTERY_FLAG: u8 = 0b0000_0010;
+const TRAINER_FLAG: u8 = 0b0000_0100;
+const FOUR_SCREEN_FLAG: u8 = 0b0000_1000;
+const NES_2_0_IDENTIFIER_MASK: u8 = 0b0000_1100;
+const NES_2_0_IDENTIFIER: u8 = 0b0000_1000;
+const CONSOLE_TYPE_MASK: u8 = 0b0000_0011;
+const INES_PAL_FLAG: u8 = 0b0000_0001;
+const INES_PRG_RAM_BANK_SIZE: usize = 0x2000;
+const CHR_RAM_SIZE: usize = 0x2000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mirroring {
+    Horizontal,
+    Vertical,
+    // every nametable address goes to the first (lower) or second (upper) VRAM page
+    SingleScreenLower,
+    SingleScreenUpper,
+    // the cartridge brings its own VRAM for all four nametables
+    FourScreen,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HeaderFormat {
+    INes,
+    Nes20,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConsoleType {
+    NesFamicom,
+    VsSystem,
+    Playchoice10,
+    // NES 2.0 extended console type from byte 13
+    Extended(u8),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RomSection {
+    Trainer,
+    PrgRom,
+    ChrRom,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CartridgeError {
+    MissingHeader {
+        length: usize,
+    },
+    InvalidSignature {
+        found: [u8; 4],
+    },
+    MissingPrgRom,
+    // NES 2.0 exponent-multiplier sizes can describe more than fits in memory
+    SizeOverflow {
+        section: RomSection,
+    },
+    UnsupportedMapper {
+        mapper: u16,
+        submapper: u8,
+    },
+    Truncated {
+        section: RomSection,
+        expected: usize,
+        actual: usize,
+    },
+}
+
+impl fmt::Display for CartridgeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CartridgeError::MissingHeader { length } => write!(
+                f,
+                "file is {} bytes long, too short for a {} byte iNES header",
+                length, HEADER_SIZE
+            ),
+            CartridgeError::InvalidSignature { found } => write!(
+                f,
+                "not an iNES file: expected \"NES\\x1A\", found {:02X?}",
+                found
+            ),
+            CartridgeError::MissingPrgRom => write!(f, "header declares no PRG-ROM"),
+            CartridgeError::SizeOverflow { section } => {
+                write!(f, "{:?} size in the header is too large", section)
+            }
+            CartridgeError::UnsupportedMapper { mapper, submapper } => write!(
+                f,
+                "mapper {} (submapper {}) is not supported",
+                mapper, submapper
+            ),
+            CartridgeError::Truncated {
+                section,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "{:?} is truncated: expected {} bytes, found {}",
+                section, expected, actual
+            ),
+        }
+    }
+}
+
+impl std::error::Error for CartridgeError {}
+
+#[derive(Debug, Clone)]
+pub struct Cartridge {
+    pub format: HeaderFormat,
+    pub prg_rom: Vec<u8>,
+    // empty when the board uses CHR-RAM instead
+    pub chr_rom: Vec<u8>,
+    pub trainer: Option<Vec<u8>>,
+    pub mapper: u16,
+    pub submapper: u8,
+    pub mirroring: Mirroring,
+    pub has_battery: bool,
+    // RAM sizes in bytes, the NVRAM ones are battery backed
+    pub prg_ram_size: usize,
+    pub prg_nvram_size: usize,
+    pub chr_ram_size: usize,
+    pub chr_nvram_size: usize,
+    pub region: Region,
+    pub console_type: ConsoleType,
+    // NES 2.0 byte 15, 0 when unspecified (standard controllers)
+    pub default_expansion_device: u8,
+}
+
+impl Cartridge {
+    pub fn from_ines(bytes: &[u8]) -> Result<Cartridge, CartridgeError> {
+        if bytes.len() < HEADER_SIZE {
+            return Err(CartridgeError::MissingHeader {
+                length: bytes.len(),
+            });
+        }
+        let header = &bytes[..HEADER_SIZE];
+        if header[0..4] != NES_SIGNATURE {
+            return Err(CartridgeError::InvalidSignature {
+                found: [header[0], header[1], header[2], header[3]],
+            });
+        }
+        let flags_6 = header[6];
+        let flags_7 = header[7];
+        let format = if flags_7 & NES_2_0_IDENTIFIER_MASK == NES_2_0_IDENTIFIER {
+            HeaderFormat::Nes20
+        } else {
+            HeaderFormat::INes
+        };
+        let mut cartridge = Cartridge {
+            format,
+            prg_rom: Vec::new(),
+            chr_rom: Vec::new(),
+            trainer: None,
+            mapper: (flags_6 >> 4) as u16,
+            submapper: 0,
+            mirroring: if flags_6 & FOUR_SCREEN_FLAG == FOUR_SCREEN_FLAG {
+                Mirroring::FourScreen
+            } else if flags_6 & VERTICAL_MIRRORING_FLAG == VERTICAL_MIRRORING_FLAG {
+                Mirroring::Vertical
+            } else {
+                Mirroring::Horizontal
+            },
+            has_battery: flags_6 & BATTERY_FLAG == BATTERY_FLAG,
+            prg_ram_size: 0,
+            prg_nvram_size: 0,
+            chr_ram_size: 0,
+            chr_nvram_size: 0,
+            region: Region::Ntsc,
+            console_type: match flags_7 & CONSOLE_TYPE_MASK {
+                0 => ConsoleType::NesFamicom,
+                1 => ConsoleType::VsSystem,
+                2 => ConsoleType::Playchoice10,
+                _ => ConsoleType::Extended(header[13] & 0x0f),
+            },
+            default_expansion_device: 0,
+        };
+        let (prg_rom_size, chr_rom_size) = match format {
+            HeaderFormat::INes => cartridge.read_ines_header(header),
+            HeaderFormat::Nes20 => cartridge.read_nes_2_0_header(header)?,
+        };
+        if prg_rom_size == 0 {
+            return Err(CartridgeError::MissingPrgRom);
+        }
+
+        let mut data = &bytes[HEADER_SIZE..];
+        if flags_6 & TRAINER_FLAG == TRAINER_FLAG {
+            cartridge.trainer = Some(take_section(&mut data, RomSection::Trainer, TRAINER_SIZE)?);
+        }
+        cartridge.prg_rom = take_section(&mut data, RomSection::PrgRom, prg_rom_size)?;
+        cartridge.chr_rom = take_section(&mut data, RomSection::ChrRom, chr_rom_size)?;
+        Ok(cartridge)
+    }
+
+    pub fn timing_config(&self) -> TimingConfig {
+        TimingConfig::for_region(self.region)
+    }
+
+    // returns the PRG-ROM and CHR-ROM sizes
+    fn read_ines_header(&mut self, header: &[u8]) -> (usize, usize) {
+        let chr_rom_size = header[5] as usize * CHR_ROM_BANK_SIZE;
+        // old dumping tools wrote a signature ("DiskDude!") over bytes 7-15, so
+        // the upper mapper nibble and bytes 8-9 are only trusted when the padding is clean
+        if header[12..16].iter().all(|byte| *byte == 0) {
+            self.mapper |= (header[7] & 0xf0) as u16;
+            if header[9] & INES_PAL_FLAG == INES_PAL_FLAG {
+                self.region = Region::Pal;
+            }
+        }
+        // iNES cannot tell whether PRG-RAM exists, so assume the usual 8 KiB
+        let prg_ram_size = (header[8].max(1)) as usize * INES_PRG_RAM_BANK_SIZE;
+        if self.has_battery {
+            self.prg_nvram_size = prg_ram_size;
+        } else {
+            self.prg_ram_size = prg_ram_size;
+        }
+        if chr_rom_size == 0 {
+            self.chr_ram_size = CHR_RAM_SIZE;
+        }
+        (header[4] as usize * PRG_ROM_BANK_SIZE, chr_rom_size)
+    }
+
+    fn read_nes_2_0_header(&mut self, header: &[u8]) -> Result<(usize, usize), CartridgeError> {
+        self.mapper |= (header[7] & 0xf0) as u16 | ((header[8] & 0x0f) as u16) << 8;
+        self.submapper = header[8] >> 4;
+        let prg_rom_size = nes_2_0_rom_size(
+            header[4],
+            header[9] & 0x0f,
+            PRG_ROM_BANK_SIZE,
+            RomSection::PrgRom,
+        )?;
+        let chr_rom_size = nes_2_0_rom_size(
+            header[5],
+            header[9] >> 4,
+            CHR_ROM_BANK_SIZE,
+            RomSection::ChrRom,
+        )?;
+        self.prg_ram_size = nes_2_0_ram_size(header[10] & 0x0f);
+        self.prg_nvram_size = nes_2_0_ram_size(header[10] >> 4);
+        self.chr_ram_size = nes_2_0_ram_size(header[11] & 0x0f);
+        self.chr_nvram_size = nes_2_0_ram_size(header[11] >> 4);
+        self.region = match header[12] & 0b0000_0011 {
+            0 => Region::Ntsc,
+            1 => Region::Pal,
+            2 => Region::MultiRegion,
+            _ => Region::Dendy,
+        };
+        self.default_expansion_device = header[15] & 0b0011_1111;
+        Ok((prg_rom_size, chr_rom_size))
+    }
+}
+
+// an MSB nibble of $F switches the LSB byte to EEEEEEMM: 2^E * (MM * 2 + 1) bytes
+fn nes_2_0_rom_size(
+    lsb: u8,
+    msb: u8,
+    bank_size: usize,
+    section: RomSection,
+) -> Result<usize, CartridgeError> {
+    let size = if msb == 0x0f {
+        let multiplier = ((lsb & 0b0000_0011) as usize) * 2 + 1;
+        1usize
+            .checked_shl((lsb >> 2) as u32)
+            .and_then(|power| power.checked_mul(multiplier))
+    } else {
+        (((msb as usize) << 8) | lsb as usize).checked_mul(bank_size)
+    };
+    size.ok_or(CartridgeError::SizeOverflow { section })
+}
+
+// RAM sizes are stored as a shift count: 64 << shift bytes, 0 means none
+fn nes_2_0_ram_size(shift: u8) -> usize {
+    if shift == 0 {
+        0
+    } else {
+        64 << shift
+    }
+}
+
+fn take_section(
+    data: &mut &[u8],
+    section: RomSection,
+    size: usize,
+) -> Result<Vec<u8>, CartridgeError> {
+    if data.len() < size {
+        return Err(CartridgeError::Truncated {
+            section,
+            expected: size,
+            actual: data.len(),
+        });
+    }
+    let (taken, rest) = data.split_at(size);
+    *data = rest;
+    Ok(taken.to_vec())
+}
@@ -1,22 +1,30 @@
+use super::bus::*;
+use super::interrupt::*;
 use super::opcode::*;
+use std::fmt;
 
 const ZERO_RESULT: u8 = 0b0000_0000;
-const NEGATIVE_RESULT: u8 = 0b1000_0000;
 const STACK_START: u16 = 0x0100;
-const STACK_END: u16 = 0x01ff;
-const STACK_POINTER_START: u8 = 0xff;
+const STACK_POINTER_START: u8 = 0xfd;
+const INTERRUPT_CYCLES: u8 = 7;
+const NMI_VECTOR_MEMORY_ADDRESS: u16 = 0xfffa;
+const RESET_VECTOR_MEMORY_ADDRESS: u16 = 0xfffc;
 const INTEERRUPT_VECTOR_MEMEROY_ADDRESS: u16 = 0xfffe;
+// KIL/JAM: these lock up the real chip until reset
+const JAM_OPCODES: [u8; 12] = [
+    0x02, 0x12, 0x22, 0x32, 0x42, 0x52, 0x62, 0x72, 0x92, 0xb2, 0xd2, 0xf2,
+];
 
 pub const CARRY_FLAG: u8 = 0b0000_0001;
 pub const ZERO_FLAG: u8 = 0b0000_0010;
 pub const INTERRUPT_DISABLE_FLAG: u8 = 0b0000_0100;
-pub const DECIMAL_FLAG: u8 = 0b0000_1000; //not used in NES
+pub const DECIMAL_FLAG: u8 = 0b0000_1000; //ignored by the NES 2A03, see CpuVariant
 pub const BREAK_FLAG: u8 = 0b0001_0000;
 pub const ALWAYS_1_FLAG: u8 = 0b0010_0000; //always 1
 pub const OVERFLOW_FLAG: u8 = 0b0100_0000;
 pub const NEGATIVE_FLAG: u8 = 0b1000_0000;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum AddressingMode {
     Immediate,
     ZeroPage,
@@ -34,84 +42,286 @@ pub enum AddressingMode {
     NoneAddressing,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CpuVariant {
+    // NES CPU: the decimal flag can be set but ADC/SBC always work in binary
+    Ricoh2A03,
+    // generic NMOS 6502 (Apple II, C64, ...) with BCD arithmetic
+    Nmos6502,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CpuError {
+    IllegalOpcode {
+        code: u8,
+        program_counter: u16,
+    },
+    InvalidAddressingMode {
+        mode: AddressingMode,
+        program_counter: u16,
+    },
+    StackWrap {
+        stack_pointer: u8,
+    },
+    Jam {
+        code: u8,
+        program_counter: u16,
+    },
+}
+
+impl fmt::Display for CpuError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CpuError::IllegalOpcode {
+                code,
+                program_counter,
+            } => write!(f, "illegal opcode {:02X} at {:04X}", code, program_counter),
+            CpuError::InvalidAddressingMode {
+                mode,
+                program_counter,
+            } => write!(
+                f,
+                "addressing mode {:?} is not supported at {:04X}",
+                mode, program_counter
+            ),
+            CpuError::StackWrap { stack_pointer } => {
+                write!(f, "stack pointer wrapped at {:02X}", stack_pointer)
+            }
+            CpuError::Jam {
+                code,
+                program_counter,
+            } => write!(
+                f,
+                "CPU jammed by opcode {:02X} at {:04X}",
+                code, program_counter
+            ),
+        }
+    }
+}
+
+impl std::error::Error for CpuError {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IllegalOpcodePolicy {
+    Halt,
+    TreatAsNop,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StackWrapPolicy {
+    Halt,
+    // wrap inside page $01 like the real chip
+    Wrap,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FaultPolicy {
+    pub illegal_opcode: IllegalOpcodePolicy,
+    pub stack_wrap: StackWrapPolicy,
+}
+
+impl Default for FaultPolicy {
+    fn default() -> Self {
+        FaultPolicy {
+            illegal_opcode: IllegalOpcodePolicy::Halt,
+            stack_wrap: StackWrapPolicy::Wrap,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct StepResult {
+    pub opcode: OpCode,
+    // address the opcode was fetched from
+    pub program_counter: u16,
+    pub operand_bytes: [u8; 2],
+    // includes the 7 cycles of an interrupt serviced before the instruction
+    pub cycles: u8,
+    pub interrupt: Option<Interrupt>,
+}
+
+impl StepResult {
+    pub fn addressing_mode(&self) -> &AddressingMode {
+        self.opcode.get_addressing_mode()
+    }
+    pub fn operands(&self) -> &[u8] {
+        &self.operand_bytes[..(self.opcode.get_bytes() - 1) as usize]
+    }
+}
+
 pub struct CPU {
+    pub variant: CpuVariant,
     pub accumulator: u8,
     pub register_x: u8,
     pub register_y: u8,
     pub status: u8,
     pub program_counter: u16,
     pub stack_pointer: u8,
-    pub memory: [u8; 0xffff],
+    pub bus: Box<dyn Bus>,
+    pub interrupt_lines: InterruptLines,
+    pub cycles: u64,
+    pub fault_policy: FaultPolicy,
+    page_crossed: bool,
 }
 
 impl CPU {
     pub fn new() -> CPU {
+        CPU::with_variant(CpuVariant::Ricoh2A03)
+    }
+    pub fn with_variant(variant: CpuVariant) -> CPU {
+        let mut cpu = CPU::with_bus(Box::new(FlatRamBus::new()));
+        cpu.variant = variant;
+        cpu
+    }
+    pub fn with_bus(bus: Box<dyn Bus>) -> CPU {
         CPU {
+            variant: CpuVariant::Ricoh2A03,
             accumulator: 0x00,
             register_x: 0x00,
             register_y: 0x00,
             status: 0b0000_0000,
             program_counter: 0x0000,
             stack_pointer: 0x0000,
-            memory: [0; 0xffff],
+            bus,
+            interrupt_lines: InterruptLines::new(),
+            cycles: 0,
+            fault_policy: FaultPolicy::default(),
+            page_crossed: false,
         }
     }
-    pub fn memory_read_2_byte(&self, address: u16) -> u16 {
+    pub fn memory_read_2_byte(&mut self, address: u16) -> u16 {
         let low = self.memory_read_byte(address) as u16;
-        let hi = self.memory_read_byte(address + 1) as u16;
+        let hi = self.memory_read_byte(address.wrapping_add(1)) as u16;
         (hi << 8) | low
     }
-    pub fn memory_read_byte(&self, address: u16) -> u8 {
-        self.memory[address as usize]
+    pub fn memory_read_byte(&mut self, address: u16) -> u8 {
+        let data = self.bus.read(address);
+        self.bus.poll_interrupts(&mut self.interrupt_lines);
+        data
+    }
+    pub fn memory_peek_byte(&self, address: u16) -> u8 {
+        self.bus.peek(address)
     }
     pub fn reset(&mut self) {
         self.accumulator = 0;
         self.register_x = 0;
         self.register_y = 0;
-        self.status = 0;
+        self.status = INTERRUPT_DISABLE_FLAG;
         self.stack_pointer = STACK_POINTER_START;
-        self.program_counter = self.memory_read_2_byte(0xfffc);
+        self.interrupt_lines.acknowledge_nmi();
+        self.program_counter = self.memory_read_2_byte(RESET_VECTOR_MEMORY_ADDRESS);
+        self.cycles = INTERRUPT_CYCLES as u64;
     }
+    // writes a raw program to $8000 on a RAM-only bus, cartridges are mapped by NesBus instead
     pub fn load(&mut self, program: Vec<u8>) {
-        self.memory[0x8000..0x8000 + program.len()].copy_from_slice(&program[..]);
-        self.memory_write_2_byte(0xfffc, 0x8000);
+        for (offset, data) in program.iter().enumerate() {
+            self.memory_write_byte(0x8000u16.wrapping_add(offset as u16), *data);
+        }
+        self.memory_write_2_byte(RESET_VECTOR_MEMORY_ADDRESS, 0x8000);
+    }
+    fn memory_read_zero_page_pointer(&mut self, pointer: u8) -> u16 {
+        let lo = self.memory_read_byte(pointer as u16);
+        let hi = self.memory_read_byte(pointer.wrapping_add(1) as u16);
+        ((hi as u16) << 8) | (lo as u16)
     }
     pub(crate) fn memory_write_2_byte(&mut self, address: u16, data: u16) {
         let hi = (data >> 8) as u8;
         let low = (data & 0xff) as u8;
         self.memory_write_byte(address, low);
-        self.memory_write_byte(address + 1, hi);
+        self.memory_write_byte(address.wrapping_add(1), hi);
     }
-    pub(crate) fn memory_write_byte(&mut self, address: u16, data: u8) {
-        self.memory[address as usize] = data;
+    // read-modify-write instructions write the unmodified value back one cycle before the result
+    fn read_modify_write_dummy_write(&mut self, address: u16, data: u8) {
+        self.memory_write_byte(address, data);
     }
-    fn push_byte_to_stack(&mut self, data: u8) {
+    pub(crate) fn memory_write_byte(&mut self, address: u16, data: u8) {
+        self.bus.write(address, data);
+        self.bus.poll_interrupts(&mut self.interrupt_lines);
+    }
+    fn push_byte_to_stack(&mut self, data: u8) -> Result<(), CpuError> {
+        if self.stack_pointer == 0x00 && self.fault_policy.stack_wrap == StackWrapPolicy::Halt {
+            return Err(CpuError::StackWrap {
+                stack_pointer: self.stack_pointer,
+            });
+        }
         self.memory_write_byte(STACK_START + self.stack_pointer as u16, data);
-        self.stack_pointer -= 1;
+        self.stack_pointer = self.stack_pointer.wrapping_sub(1);
+        Ok(())
     }
-    fn push_2_byte_to_stack(&mut self, data: u16) {
+    fn push_2_byte_to_stack(&mut self, data: u16) -> Result<(), CpuError> {
         let hi = (data >> 8) as u8;
         let low = (data & 0xff) as u8;
-        self.push_byte_to_stack(hi);
-        self.push_byte_to_stack(low);
+        self.push_byte_to_stack(hi)?;
+        self.push_byte_to_stack(low)
+    }
+    fn pop_byte_from_stack(&mut self) -> Result<u8, CpuError> {
+        if self.stack_pointer == 0xff && self.fault_policy.stack_wrap == StackWrapPolicy::Halt {
+            return Err(CpuError::StackWrap {
+                stack_pointer: self.stack_pointer,
+            });
+        }
+        self.stack_pointer = self.stack_pointer.wrapping_add(1);
+        Ok(self.memory_read_byte(STACK_START + self.stack_pointer as u16))
     }
-    fn pop_byte_from_stack(&mut self) -> u8 {
-        self.stack_pointer += 1;
-        self.memory_read_byte(STACK_START + self.stack_pointer as u16)
+    // B and bit 5 only exist in the pushed copy of the status register
+    fn pop_status_from_stack(&mut self) -> Result<u8, CpuError> {
+        Ok(self.pop_byte_from_stack()? & !(BREAK_FLAG | ALWAYS_1_FLAG))
     }
-    fn pop_2_byte_from_stack(&mut self) -> u16 {
-        let low = self.pop_byte_from_stack();
-        let hi = self.pop_byte_from_stack();
-        ((hi as u16) << 8) | (low as u16)
+    fn pop_2_byte_from_stack(&mut self) -> Result<u16, CpuError> {
+        let low = self.pop_byte_from_stack()?;
+        let hi = self.pop_byte_from_stack()?;
+        Ok(((hi as u16) << 8) | (low as u16))
     }
-    pub fn load_and_run(&mut self, program: Vec<u8>) {
+    pub fn load_and_run(&mut self, program: Vec<u8>) -> Result<(), CpuError> {
         self.load(program);
         self.reset();
-        self.run();
+        self.run()
+    }
+
+    // indexed reads take one more cycle when the index carries into the high byte
+    fn indexed_address(&mut self, base: u16, index: u8) -> u16 {
+        let address = base.wrapping_add(index as u16);
+        self.page_crossed = (base & 0xff00) != (address & 0xff00);
+        address
+    }
+
+    fn service_pending_interrupt(&mut self) -> Result<Option<Interrupt>, CpuError> {
+        let interrupt = if self.interrupt_lines.is_nmi_pending() {
+            Interrupt::Nmi
+        } else if self.interrupt_lines.is_irq_asserted() && !self.is_interrupt_disable_flag_set() {
+            Interrupt::Irq
+        } else {
+            return Ok(None);
+        };
+        self.interrupt(interrupt)?;
+        Ok(Some(interrupt))
+    }
+    // 7 cycles: opcode fetch, padding read, push PCH, push PCL, push P, vector low, vector high
+    fn interrupt(&mut self, interrupt: Interrupt) -> Result<(), CpuError> {
+        let mut pushed_status = self.status | ALWAYS_1_FLAG;
+        if interrupt == Interrupt::Brk {
+            // BRK skips its padding byte and is the only one pushing B
+            self.program_counter = self.program_counter.wrapping_add(1);
+            pushed_status |= BREAK_FLAG;
+        } else {
+            pushed_status &= !BREAK_FLAG;
+        }
+        self.push_2_byte_to_stack(self.program_counter)?;
+        // an NMI that arrives before the vector is fetched hijacks BRK and IRQ
+        let vector_address = if self.interrupt_lines.is_nmi_pending() {
+            self.interrupt_lines.acknowledge_nmi();
+            NMI_VECTOR_MEMORY_ADDRESS
+        } else {
+            INTEERRUPT_VECTOR_MEMEROY_ADDRESS
+        };
+        self.push_byte_to_stack(pushed_status)?;
+        self.set_interrupt_disable_flag_to(1);
+        self.program_counter = self.memory_read_2_byte(vector_address);
+        Ok(())
     }
 
-    fn get_address_from(&self, mode: &AddressingMode) -> u16 {
-        match mode {
+    fn get_address_from(&mut self, mode: &AddressingMode) -> Result<u16, CpuError> {
+        let address = match mode {
             AddressingMode::Immediate => self.program_counter,
 
             AddressingMode::ZeroPage => self.memory_read_byte(self.program_counter) as u16,
@@ -132,299 +342,389 @@ impl CPU {
             AddressingMode::AbsoluteX => {
                 let base = self.memory_read_2_byte(self.program_counter);
 
-                base.wrapping_add(self.register_x as u16)
+                self.indexed_address(base, self.register_x)
             }
             AddressingMode::AbsoluteY => {
                 let base = self.memory_read_2_byte(self.program_counter);
 
-                base.wrapping_add(self.register_y as u16)
+                self.indexed_address(base, self.register_y)
             }
 
             AddressingMode::IndirectX => {
                 let base = self.memory_read_byte(self.program_counter);
 
                 let ptr: u8 = base.wrapping_add(self.register_x);
-                let lo = self.memory_read_byte(ptr as u16);
-                let hi = self.memory_read_byte(ptr.wrapping_add(1) as u16);
-                ((hi as u16) << 8) | (lo as u16)
+                self.memory_read_zero_page_pointer(ptr)
             }
             AddressingMode::IndirectY => {
                 let base = self.memory_read_byte(self.program_counter);
 
-                let lo = self.memory_read_byte(base as u16);
-                let hi = self.memory_read_byte(base.wrapping_add(1) as u16);
-                let deref_base = ((hi as u16) << 8) | (lo as u16);
-                deref_base.wrapping_add(self.register_y as u16)
+                let deref_base = self.memory_read_zero_page_pointer(base);
+                self.indexed_address(deref_base, self.register_y)
             }
 
             AddressingMode::Relative => {
-                let offset = self.memory_read_byte(self.program_counter);
-                if self.is_negativ_bit_present(offset) {
-                    self.program_counter - (offset & 0b0111_1111) as u16
-                } else {
-                    self.program_counter + (offset & 0b0111_1111) as u16
-                }
+                let offset = self.memory_read_byte(self.program_counter) as i8;
+                let next_instruction = self.program_counter.wrapping_add(1);
+
+                next_instruction.wrapping_add(offset as u16)
             }
 
-            AddressingMode::Indirect => self.memory_read_2_byte(self.program_counter),
+            AddressingMode::Indirect => {
+                let pointer = self.memory_read_2_byte(self.program_counter);
+
+                // the 6502 never carries into the high byte: JMP ($xxff) reads $xx00 as high byte
+                let lo = self.memory_read_byte(pointer);
+                let hi =
+                    self.memory_read_byte((pointer & 0xff00) | (pointer.wrapping_add(1) & 0x00ff));
+                ((hi as u16) << 8) | (lo as u16)
+            }
             _ => {
-                panic!("mode {:?} is not supported", mode);
+                return Err(CpuError::InvalidAddressingMode {
+                    mode: mode.clone(),
+                    program_counter: self.program_counter,
+                });
             }
-        }
-    }
-    pub fn run(&mut self) {
-        let mut opcode: OpCode;
+        };
+        Ok(address)
+    }
+    // runs until the program executes BRK, which is serviced before returning
+    pub fn run(&mut self) -> Result<(), CpuError> {
+        self.run_until(|_, step| matches!(step.opcode.get_instruction(), Instruction::BRK))?;
+        Ok(())
+    }
+    // steps until the predicate holds after an instruction, returns the cycles spent
+    pub fn run_until<F>(&mut self, mut predicate: F) -> Result<u64, CpuError>
+    where
+        F: FnMut(&CPU, &StepResult) -> bool,
+    {
+        let start_cycles = self.cycles;
         loop {
-            match get_opcode_with_code(self.memory_read_byte(self.program_counter)) {
-                Some(vale) => {
-                    opcode = vale;
-                }
-                None => panic!(
-                    "opcode with code {:X} not found",
-                    self.memory_read_byte(self.program_counter)
-                ),
+            let step = self.step()?;
+            if predicate(self, &step) {
+                return Ok(self.cycles - start_cycles);
             }
-            self.program_counter += 1;
+        }
+    }
+    // instructions are never split, so this can overshoot; the overshoot is in the returned cycles
+    pub fn run_for_cycles(&mut self, cycles: u64) -> Result<u64, CpuError> {
+        let start_cycles = self.cycles;
+        while self.cycles - start_cycles < cycles {
+            self.step()?;
+        }
+        Ok(self.cycles - start_cycles)
+    }
+    // executes one instruction, after servicing a pending NMI or IRQ
+    pub fn step(&mut self) -> Result<StepResult, CpuError> {
+        let interrupt = self.service_pending_interrupt()?;
+        // the interrupt sequence has run even if the instruction after it faults
+        let interrupt_cycles = if interrupt.is_some() {
+            self.cycles += INTERRUPT_CYCLES as u64;
+            self.bus.tick(INTERRUPT_CYCLES);
+            INTERRUPT_CYCLES
+        } else {
+            0
+        };
+        let program_counter = self.program_counter;
+        let code = self.memory_read_byte(program_counter);
+        let opcode = match get_opcode_with_code(code) {
+            Some(vale) => vale,
+            None => self.unknown_opcode(code, program_counter)?,
+        };
+        let operand_bytes = [
+            self.memory_peek_byte(program_counter.wrapping_add(1)),
+            self.memory_peek_byte(program_counter.wrapping_add(2)),
+        ];
+        self.program_counter = self.program_counter.wrapping_add(1);
+        self.page_crossed = false;
+        let mut extra_cycles = 0;
 
-            match opcode.get_code() {
-                //ADC
-                0x69 | 0x65 | 0x75 | 0x6d | 0x7d | 0x79 | 0x61 | 0x71 => {
-                    self.adc(opcode.get_addressing_mode());
-                }
-                //AND
-                0x29 | 0x25 | 0x35 | 0x2d | 0x3d | 0x39 | 0x21 | 0x31 => {
-                    self.and(opcode.get_addressing_mode());
-                }
-                //ASL
-                0x0a | 0x06 | 0x16 | 0x0e | 0x1e => {
-                    self.asl(opcode.get_addressing_mode());
-                }
-                //BCC
-                0x90 => {
-                    self.bcc(opcode.get_addressing_mode());
-                }
-                //BCS
-                0xb0 => {
-                    self.bcs(opcode.get_addressing_mode());
-                }
-                //BEQ
-                0xf0 => {
-                    self.beq(opcode.get_addressing_mode());
-                }
-                //BIT
-                0x24 | 0x2c => self.bit(opcode.get_addressing_mode()),
-                //BMI
-                0x30 => self.bmi(opcode.get_addressing_mode()),
-                //BNE
-                0xd0 => {
-                    self.bne(opcode.get_addressing_mode());
-                }
-                //BPL
-                0x10 => {
-                    self.bpl(opcode.get_addressing_mode());
-                }
-                //BRK
-                0x00 => {
-                    //FIXME: self.brk(opcode.get_addressing_mode());
-                    break;
-                }
-                //BVC
-                0x50 => {
-                    self.bvc(opcode.get_addressing_mode());
-                }
-                //BVS
-                0x70 => {
-                    self.bvs(opcode.get_addressing_mode());
-                }
-                //CLC
-                0x18 => {
-                    self.clc(opcode.get_addressing_mode());
-                }
-                //CLD
-                0xd8 => {
-                    self.cld(opcode.get_addressing_mode());
-                }
-                //CLI
-                0x58 => {
-                    self.cli(opcode.get_addressing_mode());
-                }
-                //CLV
-                0xb8 => {
-                    self.clv(opcode.get_addressing_mode());
-                }
-                //CMP
-                0xc9 | 0xc5 | 0xd5 | 0xcd | 0xdd | 0xd9 | 0xc1 | 0xd1 => {
-                    self.cmp(opcode.get_addressing_mode());
-                }
-                //CPX
-                0xe0 | 0xe4 | 0xec => self.cpx(opcode.get_addressing_mode()),
-                //CPY
-                0xc0 | 0xc4 | 0xcc => self.cpy(opcode.get_addressing_mode()),
-                //DEC
-                0xc6 | 0xd6 | 0xce | 0xde => {
-                    self.dec(opcode.get_addressing_mode());
-                }
-                //DEX
-                0xca => {
-                    self.dex(opcode.get_addressing_mode());
-                }
-                //DEY
-                0x88 => {
-                    self.dey(opcode.get_addressing_mode());
-                }
-                //EOR
-                0x49 | 0x45 | 0x55 | 0x4d | 0x5d | 0x59 | 0x41 | 0x51 => {
-                    self.eor(opcode.get_addressing_mode());
-                }
-                //INC
-                0xe6 | 0xf6 | 0xee | 0xfe => {
-                    self.inc(opcode.get_addressing_mode());
-                }
-                //INX
-                0xe8 => {
-                    self.inx();
-                }
-                //INY
-                0xc8 => {
-                    self.iny();
-                }
-                //JMP
-                0x4c | 0x6c => {
-                    self.jmp(opcode.get_addressing_mode());
-                }
-                //JSR
-                0x20 => {
-                    self.jsr(opcode.get_addressing_mode());
-                }
-                //LDA
-                0xa9 | 0xa5 | 0xb5 | 0xad | 0xbd | 0xb9 | 0xa1 | 0xb1 => {
-                    self.lda(opcode.get_addressing_mode());
-                }
-                //LDX
-                0xa2 | 0xa6 | 0xb6 | 0xae | 0xbe => {
-                    self.ldx(opcode.get_addressing_mode());
-                }
-                //LDY
-                0xa0 | 0xa4 | 0xb4 | 0xac | 0xbc => {
-                    self.ldy(opcode.get_addressing_mode());
-                }
-                //LSR
-                0x4a | 0x46 | 0x56 | 0x4e | 0x5e => {
-                    self.lsr(opcode.get_addressing_mode());
-                }
-                //NOP
-                0xea => {
-                    self.nop();
-                }
-                //ORA
-                0x09 | 0x05 | 0x15 | 0x0d | 0x1d | 0x19 | 0x01 | 0x11 => {
-                    self.ora(opcode.get_addressing_mode());
-                }
-                //PHA
-                0x48 => {
-                    self.pha();
-                }
-                //PHP
-                0x08 => {
-                    self.php();
-                }
-                //PLA
-                0x68 => {
-                    self.pla();
-                }
-                //PLP
-                0x28 => {
-                    self.plp();
-                }
-                //ROL
-                0x2a | 0x26 | 0x36 | 0x2e | 0x3e => {
-                    self.rol(opcode.get_addressing_mode());
-                }
-                //ROR
-                0x6a | 0x66 | 0x76 | 0x6e | 0x7e => {
-                    self.ror(opcode.get_addressing_mode());
-                }
-                //RTI
-                0x40 => {
-                    self.rti();
-                }
-                //RTS
-                0x60 => {
-                    self.rts();
-                }
-                //SBC
-                0xe9 | 0xe5 | 0xf5 | 0xed | 0xfd | 0xf9 | 0xe1 | 0xf1 => {
-                    self.sbc(opcode.get_addressing_mode());
-                }
-                //SEC
-                0x38 => {
-                    self.sec();
-                }
-                //SED
-                0xf8 => {
-                    self.sed();
-                }
-                //SEI
-                0x78 => {
-                    self.sei();
-                }
-                //STA
-                0x85 | 0x95 | 0x8d | 0x9d | 0x99 | 0x81 | 0x91 => {
-                    self.sta(opcode.get_addressing_mode());
-                }
-                //STX
-                0x86 | 0x96 | 0x8e => {
-                    self.stx(opcode.get_addressing_mode());
-                }
-                //STY
-                0x84 | 0x94 | 0x8c => {
-                    self.sty(opcode.get_addressing_mode());
-                }
-                //TAX
-                0xaa => {
-                    self.tax();
-                }
-                //TAY
-                0xa8 => {
-                    self.tay();
-                }
-                //TSX
-                0xba => {
-                    self.tsx();
-                }
-                //TXA
-                0x8a => {
-                    self.txa();
-                }
-                //TXS
-                0x9a => {
-                    self.txs();
-                }
-                //TYA
-                0x98 => {
-                    self.tya();
-                }
-                _ => {
-                    panic!("opcode {:X} not found", opcode.get_code());
-                }
+        match opcode.get_code() {
+            //ADC
+            0x69 | 0x65 | 0x75 | 0x6d | 0x7d | 0x79 | 0x61 | 0x71 => {
+                self.adc(opcode.get_addressing_mode())?;
+            }
+            //AND
+            0x29 | 0x25 | 0x35 | 0x2d | 0x3d | 0x39 | 0x21 | 0x31 => {
+                self.and(opcode.get_addressing_mode())?;
+            }
+            //ASL
+            0x0a | 0x06 | 0x16 | 0x0e | 0x1e => {
+                self.asl(opcode.get_addressing_mode())?;
+            }
+            //BCC
+            0x90 => {
+                extra_cycles = self.bcc(opcode.get_addressing_mode())?;
+            }
+            //BCS
+            0xb0 => {
+                extra_cycles = self.bcs(opcode.get_addressing_mode())?;
+            }
+            //BEQ
+            0xf0 => {
+                extra_cycles = self.beq(opcode.get_addressing_mode())?;
             }
-            self.program_counter += (opcode.get_bytes() - 1) as u16;
+            //BIT
+            0x24 | 0x2c => self.bit(opcode.get_addressing_mode())?,
+            //BMI
+            0x30 => {
+                extra_cycles = self.bmi(opcode.get_addressing_mode())?;
+            }
+            //BNE
+            0xd0 => {
+                extra_cycles = self.bne(opcode.get_addressing_mode())?;
+            }
+            //BPL
+            0x10 => {
+                extra_cycles = self.bpl(opcode.get_addressing_mode())?;
+            }
+            //BRK
+            0x00 => {
+                self.brk()?;
+            }
+            //BVC
+            0x50 => {
+                extra_cycles = self.bvc(opcode.get_addressing_mode())?;
+            }
+            //BVS
+            0x70 => {
+                extra_cycles = self.bvs(opcode.get_addressing_mode())?;
+            }
+            //CLC
+            0x18 => {
+                self.clc(opcode.get_addressing_mode());
+            }
+            //CLD
+            0xd8 => {
+                self.cld(opcode.get_addressing_mode());
+            }
+            //CLI
+            0x58 => {
+                self.cli(opcode.get_addressing_mode());
+            }
+            //CLV
+            0xb8 => {
+                self.clv(opcode.get_addressing_mode());
+            }
+            //CMP
+            0xc9 | 0xc5 | 0xd5 | 0xcd | 0xdd | 0xd9 | 0xc1 | 0xd1 => {
+                self.cmp(opcode.get_addressing_mode())?;
+            }
+            //CPX
+            0xe0 | 0xe4 | 0xec => self.cpx(opcode.get_addressing_mode())?,
+            //CPY
+            0xc0 | 0xc4 | 0xcc => self.cpy(opcode.get_addressing_mode())?,
+            //DEC
+            0xc6 | 0xd6 | 0xce | 0xde => {
+                self.dec(opcode.get_addressing_mode())?;
+            }
+            //DEX
+            0xca => {
+                self.dex(opcode.get_addressing_mode());
+            }
+            //DEY
+            0x88 => {
+                self.dey(opcode.get_addressing_mode());
+            }
+            //EOR
+            0x49 | 0x45 | 0x55 | 0x4d | 0x5d | 0x59 | 0x41 | 0x51 => {
+                self.eor(opcode.get_addressing_mode())?;
+            }
+            //INC
+            0xe6 | 0xf6 | 0xee | 0xfe => {
+                self.inc(opcode.get_addressing_mode())?;
+            }
+            //INX
+            0xe8 => {
+                self.inx();
+            }
+            //INY
+            0xc8 => {
+                self.iny();
+            }
+            //JMP
+            0x4c | 0x6c => {
+                self.jmp(opcode.get_addressing_mode())?;
+            }
+            //JSR
+            0x20 => {
+                self.jsr(opcode.get_addressing_mode())?;
+            }
+            //LDA
+            0xa9 | 0xa5 | 0xb5 | 0xad | 0xbd | 0xb9 | 0xa1 | 0xb1 => {
+                self.lda(opcode.get_addressing_mode())?;
+            }
+            //LDX
+            0xa2 | 0xa6 | 0xb6 | 0xae | 0xbe => {
+                self.ldx(opcode.get_addressing_mode())?;
+            }
+            //LDY
+            0xa0 | 0xa4 | 0xb4 | 0xac | 0xbc => {
+                self.ldy(opcode.get_addressing_mode())?;
+            }
+            //LSR
+            0x4a | 0x46 | 0x56 | 0x4e | 0x5e => {
+                self.lsr(opcode.get_addressing_mode())?;
+            }
+            //NOP
+            0xea => {
+                self.nop();
+            }
+            //ORA
+            0x09 | 0x05 | 0x15 | 0x0d | 0x1d | 0x19 | 0x01 | 0x11 => {
+                self.ora(opcode.get_addressing_mode())?;
+            }
+            //PHA
+            0x48 => {
+                self.pha()?;
+            }
+            //PHP
+            0x08 => {
+                self.php()?;
+            }
+            //PLA
+            0x68 => {
+                self.pla()?;
+            }
+            //PLP
+            0x28 => {
+                self.plp()?;
+            }
+            //ROL
+            0x2a | 0x26 | 0x36 | 0x2e | 0x3e => {
+                self.rol(opcode.get_addressing_mode())?;
+            }
+            //ROR
+            0x6a | 0x66 | 0x76 | 0x6e | 0x7e => {
+                self.ror(opcode.get_addressing_mode())?;
+            }
+            //RTI
+            0x40 => {
+                self.rti()?;
+            }
+            //RTS
+            0x60 => {
+                self.rts()?;
+            }
+            //SBC
+            0xe9 | 0xe5 | 0xf5 | 0xed | 0xfd | 0xf9 | 0xe1 | 0xf1 => {
+                self.sbc(opcode.get_addressing_mode())?;
+            }
+            //SEC
+            0x38 => {
+                self.sec();
+            }
+            //SED
+            0xf8 => {
+                self.sed();
+            }
+            //SEI
+            0x78 => {
+                self.sei();
+            }
+            //STA
+            0x85 | 0x95 | 0x8d | 0x9d | 0x99 | 0x81 | 0x91 => {
+                self.sta(opcode.get_addressing_mode())?;
+            }
+            //STX
+            0x86 | 0x96 | 0x8e => {
+                self.stx(opcode.get_addressing_mode())?;
+            }
+            //STY
+            0x84 | 0x94 | 0x8c => {
+                self.sty(opcode.get_addressing_mode())?;
+            }
+            //TAX
+            0xaa => {
+                self.tax();
+            }
+            //TAY
+            0xa8 => {
+                self.tay();
+            }
+            //TSX
+            0xba => {
+                self.tsx();
+            }
+            //TXA
+            0x8a => {
+                self.txa();
+            }
+            //TXS
+            0x9a => {
+                self.txs();
+            }
+            //TYA
+            0x98 => {
+                self.tya();
+            }
+            //NOP stand-in for illegal opcodes under IllegalOpcodePolicy::TreatAsNop
+            _ => {}
+        }
+        if opcode.adds_cycle_on_page_cross() && self.page_crossed {
+            extra_cycles += 1;
+        }
+        if !opcode.is_control_flow() {
+            self.program_counter = self
+                .program_counter
+                .wrapping_add((opcode.get_bytes() - 1) as u16);
+        }
+
+        let instruction_cycles = opcode.get_cycles() + extra_cycles;
+        self.cycles += instruction_cycles as u64;
+        self.bus.tick(instruction_cycles);
+        self.bus.poll_interrupts(&mut self.interrupt_lines);
+        Ok(StepResult {
+            opcode,
+            program_counter,
+            operand_bytes,
+            cycles: interrupt_cycles + instruction_cycles,
+            interrupt,
+        })
+    }
+
+    fn unknown_opcode(&self, code: u8, program_counter: u16) -> Result<OpCode, CpuError> {
+        if self.fault_policy.illegal_opcode == IllegalOpcodePolicy::TreatAsNop {
+            return Ok(OpCode::new(
+                Instruction::NOP,
+                code,
+                1,
+                2,
+                AddressingMode::Implied,
+            ));
+        }
+        if JAM_OPCODES.contains(&code) {
+            Err(CpuError::Jam {
+                code,
+                program_counter,
+            })
+        } else {
+            Err(CpuError::IllegalOpcode {
+                code,
+                program_counter,
+            })
         }
     }
 
-    pub fn adc(&mut self, addressing_mode: &AddressingMode) {
-        let address = self.get_address_from(addressing_mode);
+    pub fn adc(&mut self, addressing_mode: &AddressingMode) -> Result<(), CpuError> {
+        let address = self.get_address_from(addressing_mode)?;
         let memory_value = self.memory_read_byte(address);
 
-        self.accumulator += memory_value;
-        self.update_zero_and_negative_flag(self.accumulator);
-    } //TODO: CARRY BIT
+        if self.is_decimal_mode_active() {
+            self.add_to_accumulator_decimal(memory_value);
+        } else {
+            self.add_to_accumulator_with_carry(memory_value);
+        }
+        Ok(())
+    }
 
-    fn and(&mut self, addressing_mode: &AddressingMode) {
-        self.accumulator &= self.memory_read_byte(self.get_address_from(addressing_mode));
+    fn and(&mut self, addressing_mode: &AddressingMode) -> Result<(), CpuError> {
+        let address = self.get_address_from(addressing_mode)?;
+        self.accumulator &= self.memory_read_byte(address);
         self.update_zero_and_negative_flag(self.accumulator);
+        Ok(())
     }
-    fn asl(&mut self, addressing_mode: &AddressingMode) {
+    fn asl(&mut self, addressing_mode: &AddressingMode) -> Result<(), CpuError> {
         let out_shifted_bit: u8;
         match addressing_mode {
             AddressingMode::Accumulator => {
@@ -433,8 +733,9 @@ impl CPU {
                 self.update_zero_and_negative_flag(self.accumulator);
             }
             _ => {
-                let address = self.get_address_from(addressing_mode);
+                let address = self.get_address_from(addressing_mode)?;
                 let mut memory_content = self.memory_read_byte(address);
+                self.read_modify_write_dummy_write(address, memory_content);
                 out_shifted_bit = memory_content >> 7;
                 memory_content <<= 1;
                 self.memory_write_byte(address, memory_content);
@@ -442,67 +743,65 @@ impl CPU {
             }
         }
         self.set_carry_flag_to(out_shifted_bit);
+        Ok(())
     }
     //TODO: TEST FROM HERE
-    fn bcc(&mut self, addressing_mode: &AddressingMode) {
-        if !self.is_carry_flag_set() {
-            self.program_counter = self.get_address_from(addressing_mode);
-        }
+    fn bcc(&mut self, addressing_mode: &AddressingMode) -> Result<u8, CpuError> {
+        self.branch_if(!self.is_carry_flag_set(), addressing_mode)
     }
-    fn bcs(&mut self, addressing_mode: &AddressingMode) {
-        if self.is_carry_flag_set() {
-            self.program_counter = self.get_address_from(addressing_mode);
-        }
+    fn bcs(&mut self, addressing_mode: &AddressingMode) -> Result<u8, CpuError> {
+        self.branch_if(self.is_carry_flag_set(), addressing_mode)
     }
-    fn beq(&mut self, addressing_mode: &AddressingMode) {
-        if self.is_zero_flag_set() {
-            self.program_counter = self.get_address_from(addressing_mode);
-        }
+    fn beq(&mut self, addressing_mode: &AddressingMode) -> Result<u8, CpuError> {
+        self.branch_if(self.is_zero_flag_set(), addressing_mode)
     }
-    fn bit(&mut self, addressing_mode: &AddressingMode) {
-        let memory_value = self.memory_read_byte(self.get_address_from(addressing_mode));
+    fn bit(&mut self, addressing_mode: &AddressingMode) -> Result<(), CpuError> {
+        let address = self.get_address_from(addressing_mode)?;
+        let memory_value = self.memory_read_byte(address);
         let and_result = self.accumulator & memory_value;
         self.set_zero_flag_to(and_result);
         let overflow_flag_from_memory = (memory_value << 1) >> 7;
         let negative_flag_from_memory = memory_value >> 7;
         self.set_negative_flag_to(negative_flag_from_memory);
         self.set_overflow_flag_to(overflow_flag_from_memory);
-    }
-    fn bmi(&mut self, addressing_mode: &AddressingMode) {
-        if self.is_negative_flag_set() {
-            let new_program_counter_address = self.get_address_from(addressing_mode);
-            self.program_counter = new_program_counter_address;
-        }
-    }
-    fn bne(&mut self, addressing_mode: &AddressingMode) {
-        if !self.is_zero_flag_set() {
-            let new_program_counter_address = self.get_address_from(addressing_mode);
-            self.program_counter = new_program_counter_address;
+        Ok(())
+    }
+    fn bmi(&mut self, addressing_mode: &AddressingMode) -> Result<u8, CpuError> {
+        self.branch_if(self.is_negative_flag_set(), addressing_mode)
+    }
+    fn bne(&mut self, addressing_mode: &AddressingMode) -> Result<u8, CpuError> {
+        self.branch_if(!self.is_zero_flag_set(), addressing_mode)
+    }
+    fn bpl(&mut self, addressing_mode: &AddressingMode) -> Result<u8, CpuError> {
+        self.branch_if(!self.is_negative_flag_set(), addressing_mode)
+    }
+    // returns the extra cycles: +1 when taken, +1 more when the target is on another page
+    fn branch_if(
+        &mut self,
+        condition: bool,
+        addressing_mode: &AddressingMode,
+    ) -> Result<u8, CpuError> {
+        let next_instruction = self.program_counter.wrapping_add(1);
+        if !condition {
+            self.program_counter = next_instruction;
+            return Ok(0);
         }
-    }
-    fn bpl(&mut self, addressing_mode: &AddressingMode) {
-        if !self.is_negative_flag_set() {
-            let new_program_counter_address = self.get_address_from(addressing_mode);
-            self.program_counter = new_program_counter_address;
+        let target = self.get_address_from(addressing_mode)?;
+        self.program_counter = target;
+        if (next_instruction & 0xff00) != (target & 0xff00) {
+            Ok(2)
+        } else {
+            Ok(1)
         }
     }
-    fn brk(&mut self, _addressing_mode: &AddressingMode) {
-        self.push_2_byte_to_stack(self.program_counter);
-        self.push_byte_to_stack(self.status);
-        //FIXMEself.program_counter = self.memory_read_2_byte(INTEERRUPT_VECTOR_MEMEROY_ADDRESS);
-        self.set_brake_flag_to(0x1);
+    fn brk(&mut self) -> Result<(), CpuError> {
+        self.interrupt(Interrupt::Brk)
     }
-    fn bvc(&mut self, addressing_mode: &AddressingMode) {
-        if !self.is_overflow_flag_set() {
-            let new_program_counter_address = self.get_address_from(addressing_mode);
-            self.program_counter = new_program_counter_address;
-        }
+    fn bvc(&mut self, addressing_mode: &AddressingMode) -> Result<u8, CpuError> {
+        self.branch_if(!self.is_overflow_flag_set(), addressing_mode)
     }
-    fn bvs(&mut self, addressing_mode: &AddressingMode) {
-        if self.is_overflow_flag_set() {
-            let new_program_counter_address = self.get_address_from(addressing_mode);
-            self.program_counter = new_program_counter_address;
-        }
+    fn bvs(&mut self, addressing_mode: &AddressingMode) -> Result<u8, CpuError> {
+        self.branch_if(self.is_overflow_flag_set(), addressing_mode)
     }
     fn clc(&mut self, _addressing_mode: &AddressingMode) {
         self.set_carry_flag_to(0x0);
@@ -516,47 +815,31 @@ impl CPU {
     fn clv(&mut self, _addressing_mode: &AddressingMode) {
         self.set_overflow_flag_to(0x0);
     }
-    fn cmp(&mut self, addressing_mode: &AddressingMode) {
-        let memory_value = self.memory_read_byte(self.get_address_from(addressing_mode));
-        let result = self.accumulator - memory_value;
-        if self.accumulator >= memory_value {
-            self.set_carry_flag_to(0x1)
-        } else if self.accumulator == memory_value {
-            self.set_zero_flag_to(0x1);
-        }
-        if self.is_negativ_bit_present(result) {
-            self.set_negative_flag_to(0x1)
-        }
+    fn cmp(&mut self, addressing_mode: &AddressingMode) -> Result<(), CpuError> {
+        let address = self.get_address_from(addressing_mode)?;
+        let memory_value = self.memory_read_byte(address);
+        self.compare(self.accumulator, memory_value);
+        Ok(())
     }
-    fn cpx(&mut self, addressing_mode: &AddressingMode) {
-        let memory_value = self.memory_read_byte(self.get_address_from(addressing_mode));
-        let result = self.register_x - memory_value;
-        if self.register_x >= memory_value {
-            self.set_carry_flag_to(0x1)
-        } else if self.register_x == memory_value {
-            self.set_zero_flag_to(0x1);
-        }
-        if self.is_negativ_bit_present(result) {
-            self.set_negative_flag_to(0x1)
-        }
+    fn cpx(&mut self, addressing_mode: &AddressingMode) -> Result<(), CpuError> {
+        let address = self.get_address_from(addressing_mode)?;
+        let memory_value = self.memory_read_byte(address);
+        self.compare(self.register_x, memory_value);
+        Ok(())
     }
-    fn cpy(&mut self, addressing_mode: &AddressingMode) {
-        let memory_value = self.memory_read_byte(self.get_address_from(addressing_mode));
-        let result = self.register_y - memory_value;
-        if self.register_y >= memory_value {
-            self.set_carry_flag_to(0x1)
-        } else if self.register_y == memory_value {
-            self.set_zero_flag_to(0x1);
-        }
-        if self.is_negativ_bit_present(result) {
-            self.set_negative_flag_to(0x1)
-        }
+    fn cpy(&mut self, addressing_mode: &AddressingMode) -> Result<(), CpuError> {
+        let address = self.get_address_from(addressing_mode)?;
+        let memory_value = self.memory_read_byte(address);
+        self.compare(self.register_y, memory_value);
+        Ok(())
     }
-    fn dec(&mut self, addressing_mode: &AddressingMode) {
-        let address = self.get_address_from(addressing_mode);
+    fn dec(&mut self, addressing_mode: &AddressingMode) -> Result<(), CpuError> {
+        let address = self.get_address_from(addressing_mode)?;
         let memory_value = self.memory_read_byte(address);
+        self.read_modify_write_dummy_write(address, memory_value);
         self.memory_write_byte(address, memory_value.wrapping_sub(1));
         self.update_zero_and_negative_flag(memory_value.wrapping_sub(1));
+        Ok(())
     }
     fn dex(&mut self, _addressing_mode: &AddressingMode) {
         self.register_x = self.register_x.wrapping_sub(1);
@@ -567,18 +850,22 @@ impl CPU {
         self.update_zero_and_negative_flag(self.register_y);
     }
 
-    fn eor(&mut self, addressing_mode: &AddressingMode) {
-        let address = self.get_address_from(addressing_mode);
+    fn eor(&mut self, addressing_mode: &AddressingMode) -> Result<(), CpuError> {
+        let address = self.get_address_from(addressing_mode)?;
         let memory_value = self.memory_read_byte(address);
         self.accumulator ^= memory_value;
         self.update_zero_and_negative_flag(self.accumulator);
+        Ok(())
     }
 
-    fn inc(&mut self, addressing_mode: &AddressingMode) {
-        let address = self.get_address_from(addressing_mode);
-        let memory_value = self.memory_read_byte(address).wrapping_add(1);
+    fn inc(&mut self, addressing_mode: &AddressingMode) -> Result<(), CpuError> {
+        let address = self.get_address_from(addressing_mode)?;
+        let memory_value = self.memory_read_byte(address);
+        self.read_modify_write_dummy_write(address, memory_value);
+        let memory_value = memory_value.wrapping_add(1);
         self.memory_write_byte(address, memory_value);
         self.update_zero_and_negative_flag(memory_value);
+        Ok(())
     }
 
     fn inx(&mut self) {
@@ -589,79 +876,89 @@ impl CPU {
         self.register_y = self.register_y.wrapping_add(1);
         self.update_zero_and_negative_flag(self.register_y);
     }
-    fn jmp(&mut self, addressing_mode: &AddressingMode) {
-        let address = self.get_address_from(addressing_mode);
-        let new_address = self.memory_read_2_byte(address);
-        self.program_counter = new_address;
+    fn jmp(&mut self, addressing_mode: &AddressingMode) -> Result<(), CpuError> {
+        self.program_counter = self.get_address_from(addressing_mode)?;
+        Ok(())
     }
-    fn jsr(&mut self, addressing_mode: &AddressingMode) {
-        let address = self.get_address_from(addressing_mode);
-        let new_address = self.memory_read_2_byte(address);
+    fn jsr(&mut self, addressing_mode: &AddressingMode) -> Result<(), CpuError> {
+        let new_address = self.get_address_from(addressing_mode)?;
+        // the pushed return address is the last byte of the JSR instruction
+        self.push_2_byte_to_stack(self.program_counter.wrapping_add(1))?;
         self.program_counter = new_address;
+        Ok(())
     }
 
-    fn lda(&mut self, addressing_mode: &AddressingMode) {
-        let address = self.get_address_from(addressing_mode);
+    fn lda(&mut self, addressing_mode: &AddressingMode) -> Result<(), CpuError> {
+        let address = self.get_address_from(addressing_mode)?;
         let value = self.memory_read_byte(address);
 
         self.accumulator = value;
         self.update_zero_and_negative_flag(self.accumulator);
+        Ok(())
     }
-    fn ldx(&mut self, addressing_mode: &AddressingMode) {
-        let address = self.get_address_from(addressing_mode);
+    fn ldx(&mut self, addressing_mode: &AddressingMode) -> Result<(), CpuError> {
+        let address = self.get_address_from(addressing_mode)?;
         let memory_value = self.memory_read_byte(address);
         self.register_x = memory_value;
         self.update_zero_and_negative_flag(self.register_x);
+        Ok(())
     }
-    fn ldy(&mut self, addressing_mode: &AddressingMode) {
-        let address = self.get_address_from(addressing_mode);
+    fn ldy(&mut self, addressing_mode: &AddressingMode) -> Result<(), CpuError> {
+        let address = self.get_address_from(addressing_mode)?;
         let memory_value = self.memory_read_byte(address);
         self.register_y = memory_value;
         self.update_zero_and_negative_flag(self.register_y);
+        Ok(())
     }
-    fn lsr(&mut self, addressing_mode: &AddressingMode) {
+    fn lsr(&mut self, addressing_mode: &AddressingMode) -> Result<(), CpuError> {
         let out_shifted_bit: u8;
         match addressing_mode {
             AddressingMode::Accumulator => {
-                out_shifted_bit = self.accumulator << 7;
+                out_shifted_bit = self.accumulator & 1;
                 self.accumulator >>= 1;
                 self.set_carry_flag_to(out_shifted_bit);
                 self.update_zero_and_negative_flag(self.accumulator);
             }
             _ => {
-                let address = self.get_address_from(addressing_mode);
+                let address = self.get_address_from(addressing_mode)?;
                 let mut memory_value = self.memory_read_byte(address);
-                out_shifted_bit = memory_value << 7;
+                self.read_modify_write_dummy_write(address, memory_value);
+                out_shifted_bit = memory_value & 1;
                 memory_value >>= 1;
                 self.memory_write_byte(address, memory_value);
                 self.set_carry_flag_to(out_shifted_bit);
                 self.update_zero_and_negative_flag(memory_value);
             }
         }
+        Ok(())
     }
-    fn nop(&self) {
-        let dont_do_anything: u8;
-    }
-    fn ora(&mut self, addressing_mode: &AddressingMode) {
-        let address = self.get_address_from(addressing_mode);
+    fn nop(&self) {}
+    fn ora(&mut self, addressing_mode: &AddressingMode) -> Result<(), CpuError> {
+        let address = self.get_address_from(addressing_mode)?;
         let memory_value = self.memory_read_byte(address);
         self.accumulator |= memory_value;
         self.update_zero_and_negative_flag(self.accumulator);
+        Ok(())
     }
-    fn pha(&mut self) {
-        self.push_byte_to_stack(self.accumulator);
+    fn pha(&mut self) -> Result<(), CpuError> {
+        self.push_byte_to_stack(self.accumulator)?;
+        Ok(())
     }
-    fn php(&mut self) {
-        self.push_byte_to_stack(self.status);
+    // like BRK, PHP pushes B and bit 5 set
+    fn php(&mut self) -> Result<(), CpuError> {
+        self.push_byte_to_stack(self.status | BREAK_FLAG | ALWAYS_1_FLAG)?;
+        Ok(())
     }
-    fn pla(&mut self) {
-        self.accumulator = self.pop_byte_from_stack();
+    fn pla(&mut self) -> Result<(), CpuError> {
+        self.accumulator = self.pop_byte_from_stack()?;
         self.update_zero_and_negative_flag(self.accumulator);
+        Ok(())
     }
-    fn plp(&mut self) {
-        self.status = self.pop_byte_from_stack();
+    fn plp(&mut self) -> Result<(), CpuError> {
+        self.status = self.pop_status_from_stack()?;
+        Ok(())
     }
-    fn rol(&mut self, addressing_mode: &AddressingMode) {
+    fn rol(&mut self, addressing_mode: &AddressingMode) -> Result<(), CpuError> {
         match addressing_mode {
             AddressingMode::Accumulator => {
                 let outshifted_bit = self.accumulator >> 7;
@@ -673,8 +970,9 @@ impl CPU {
                 self.update_zero_and_negative_flag(self.accumulator);
             }
             _ => {
-                let address = self.get_address_from(addressing_mode);
+                let address = self.get_address_from(addressing_mode)?;
                 let mut memory_value = self.memory_read_byte(address);
+                self.read_modify_write_dummy_write(address, memory_value);
                 let outshifted_bit = memory_value >> 7;
                 memory_value <<= 1;
                 if self.is_carry_flag_set() {
@@ -682,54 +980,61 @@ impl CPU {
                 }
                 self.memory_write_byte(address, memory_value);
                 self.set_carry_flag_to(outshifted_bit);
-                self.set_negative_flag_to(memory_value >> 7);
+                self.update_zero_and_negative_flag(memory_value);
             }
         }
+        Ok(())
     }
-    fn ror(&mut self, addressing_mode: &AddressingMode) {
+    fn ror(&mut self, addressing_mode: &AddressingMode) -> Result<(), CpuError> {
         match addressing_mode {
             AddressingMode::Accumulator => {
-                let outshifted_bit = self.accumulator << 7;
+                let outshifted_bit = self.accumulator & 1;
                 self.accumulator >>= 1;
                 if self.is_carry_flag_set() {
-                    self.accumulator |= CARRY_FLAG;
+                    self.accumulator |= 0b1000_0000;
                 }
                 self.set_carry_flag_to(outshifted_bit);
                 self.update_zero_and_negative_flag(self.accumulator);
             }
             _ => {
-                let address = self.get_address_from(addressing_mode);
+                let address = self.get_address_from(addressing_mode)?;
                 let mut memory_value = self.memory_read_byte(address);
-                let outshifted_bit = memory_value << 7;
+                self.read_modify_write_dummy_write(address, memory_value);
+                let outshifted_bit = memory_value & 1;
                 memory_value >>= 1;
                 if self.is_carry_flag_set() {
-                    memory_value |= CARRY_FLAG;
+                    memory_value |= 0b1000_0000;
                 }
                 self.memory_write_byte(address, memory_value);
                 self.set_carry_flag_to(outshifted_bit);
-                self.set_negative_flag_to(memory_value >> 7);
+                self.update_zero_and_negative_flag(memory_value);
             }
         }
+        Ok(())
     }
-    fn rti(&mut self) {
-        self.status = self.pop_byte_from_stack();
-        self.program_counter = self.pop_2_byte_from_stack();
+    fn rti(&mut self) -> Result<(), CpuError> {
+        self.status = self.pop_status_from_stack()?;
+        self.program_counter = self.pop_2_byte_from_stack()?;
+        Ok(())
     }
-    fn rts(&mut self) {
-        self.program_counter = self.pop_2_byte_from_stack() - 1;
+    fn rts(&mut self) -> Result<(), CpuError> {
+        self.program_counter = self.pop_2_byte_from_stack()?.wrapping_add(1);
+        Ok(())
     }
-    fn sbc(&mut self, addressing_mode: &AddressingMode) {
-        let address = self.get_address_from(addressing_mode);
+    fn sbc(&mut self, addressing_mode: &AddressingMode) -> Result<(), CpuError> {
+        let address = self.get_address_from(addressing_mode)?;
         let memory_value = self.memory_read_byte(address);
-        if self.is_carry_flag_set() {
-            self.accumulator = self
-                .accumulator
-                .wrapping_sub(memory_value.wrapping_sub(!CARRY_FLAG));
+
+        // A - M - (1 - C) is the same as A + !M + C in two's complement
+        if self.is_decimal_mode_active() {
+            // NMOS flags are the binary ones, only the accumulator gets the BCD result
+            let decimal_result = self.subtract_decimal(memory_value);
+            self.add_to_accumulator_with_carry(!memory_value);
+            self.accumulator = decimal_result;
         } else {
-            self.accumulator = self.accumulator.wrapping_sub(memory_value.wrapping_sub(!0));
+            self.add_to_accumulator_with_carry(!memory_value);
         }
-
-        //TODO: TAKE A LOOK LATER ON (I DONT FIGURED OUT HOW IT WORKS FOR NOW)
+        Ok(())
     }
     fn sec(&mut self) {
         self.set_carry_flag_to(1);
@@ -740,19 +1045,20 @@ impl CPU {
     fn sei(&mut self) {
         self.set_interrupt_disable_flag_to(1);
     }
-    fn sta(&mut self, addressing_mode: &AddressingMode) {
-        let address = self.get_address_from(addressing_mode);
+    fn sta(&mut self, addressing_mode: &AddressingMode) -> Result<(), CpuError> {
+        let address = self.get_address_from(addressing_mode)?;
         self.memory_write_byte(address, self.accumulator);
+        Ok(())
     }
-    fn stx(&mut self, addressing_mode: &AddressingMode) {
-        let address = self.get_address_from(addressing_mode);
-        let memory_value = self.memory_read_byte(address);
-        self.register_x = memory_value;
+    fn stx(&mut self, addressing_mode: &AddressingMode) -> Result<(), CpuError> {
+        let address = self.get_address_from(addressing_mode)?;
+        self.memory_write_byte(address, self.register_x);
+        Ok(())
     }
-    fn sty(&mut self, addressing_mode: &AddressingMode) {
-        let address = self.get_address_from(addressing_mode);
-        let memory_value = self.memory_read_byte(address);
-        self.register_y = memory_value;
+    fn sty(&mut self, addressing_mode: &AddressingMode) -> Result<(), CpuError> {
+        let address = self.get_address_from(addressing_mode)?;
+        self.memory_write_byte(address, self.register_y);
+        Ok(())
     }
     fn tax(&mut self) {
         self.register_x = self.accumulator;
@@ -778,6 +1084,67 @@ impl CPU {
         self.update_zero_and_negative_flag(self.accumulator);
     }
 
+    fn compare(&mut self, register: u8, memory_value: u8) {
+        self.set_carry_flag_to((register >= memory_value) as u8);
+        self.update_zero_and_negative_flag(register.wrapping_sub(memory_value));
+    }
+
+    fn add_to_accumulator_with_carry(&mut self, value: u8) {
+        let sum = self.accumulator as u16 + value as u16 + self.is_carry_flag_set() as u16;
+        let result = sum as u8;
+
+        self.set_carry_flag_to((sum > 0xff) as u8);
+        // signed overflow: both operands share a sign that differs from the result
+        let overflow_bit = ((self.accumulator ^ result) & (value ^ result)) >> 7;
+        self.set_overflow_flag_to(overflow_bit);
+        self.accumulator = result;
+        self.update_zero_and_negative_flag(self.accumulator);
+    }
+
+    // NMOS behaviour as described in Bruce Clark's "Decimal Mode" tutorial:
+    // Z comes from the binary sum, N and V from the sum before the high nibble is adjusted
+    fn add_to_accumulator_decimal(&mut self, value: u8) {
+        let carry = self.is_carry_flag_set() as i16;
+        let binary_result = self
+            .accumulator
+            .wrapping_add(value)
+            .wrapping_add(carry as u8);
+
+        let mut low = (self.accumulator & 0x0f) as i16 + (value & 0x0f) as i16 + carry;
+        if low >= 0x0a {
+            low = ((low + 0x06) & 0x0f) + 0x10;
+        }
+        let mut sum = (self.accumulator & 0xf0) as i16 + (value & 0xf0) as i16 + low;
+        let signed_sum = (self.accumulator & 0xf0) as i8 as i16 + (value & 0xf0) as i8 as i16 + low;
+
+        self.set_zero_flag_to((binary_result == ZERO_RESULT) as u8);
+        self.set_negative_flag_to(self.is_negativ_bit_present(sum as u8) as u8);
+        self.set_overflow_flag_to(!(-128..=127).contains(&signed_sum) as u8);
+        if sum >= 0xa0 {
+            sum += 0x60;
+        }
+        self.set_carry_flag_to((sum >= 0x100) as u8);
+        self.accumulator = sum as u8;
+    }
+
+    fn subtract_decimal(&self, value: u8) -> u8 {
+        let borrow = 1 - self.is_carry_flag_set() as i16;
+
+        let mut low = (self.accumulator & 0x0f) as i16 - (value & 0x0f) as i16 - borrow;
+        if low < 0 {
+            low = ((low - 0x06) & 0x0f) - 0x10;
+        }
+        let mut difference = (self.accumulator & 0xf0) as i16 - (value & 0xf0) as i16 + low;
+        if difference < 0 {
+            difference -= 0x60;
+        }
+        difference as u8
+    }
+
+    fn is_decimal_mode_active(&self) -> bool {
+        self.variant == CpuVariant::Nmos6502 && self.status & DECIMAL_FLAG == DECIMAL_FLAG
+    }
+
     fn update_zero_and_negative_flag(&mut self, byte_to_check: u8) {
         self.set_zero_flag_to((byte_to_check == ZERO_RESULT) as u8);
 
@@ -814,14 +1181,6 @@ impl CPU {
         }
     }
 
-    fn set_brake_flag_to(&mut self, break_bit: u8) {
-        if break_bit == 1 {
-            self.status |= BREAK_FLAG;
-        } else {
-            self.status &= !BREAK_FLAG;
-        }
-    }
-
     fn set_decimal_flag_to(&mut self, decimal_bit: u8) {
         if decimal_bit == 1 {
             self.status |= DECIMAL_FLAG;
@@ -843,19 +1202,20 @@ impl CPU {
         }
     }
 
+    fn is_interrupt_disable_flag_set(&self) -> bool {
+        self.status & INTERRUPT_DISABLE_FLAG == INTERRUPT_DISABLE_FLAG
+    }
+
     fn is_negativ_bit_present(&self, byte_to_check: u8) -> bool {
         byte_to_check >> 7 == 1
     }
-    fn is_zero_flag_present(&self, byte_to_check: u8) -> bool {
-        (byte_to_check >> 1) << 7 == 1
-    }
     fn is_carry_flag_set(&self) -> bool {
-        let carry_bit = self.status << 7;
-        carry_bit == 1
+        let carry_bit = self.status & CARRY_FLAG;
+        carry_bit == CARRY_FLAG
     }
     fn is_zero_flag_set(&self) -> bool {
-        let zero_bit = (self.status >> 1) << 7;
-        zero_bit == 1
+        let zero_bit = self.status & ZERO_FLAG;
+        zero_bit == ZERO_FLAG
     }
     fn is_negative_flag_set(&self) -> bool {
         let negative_bit = self.status >> 7;
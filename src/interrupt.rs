@@ -0,0 +1,61 @@
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Interrupt {
+    Nmi,
+    Irq,
+    Brk,
+}
+
+// the IRQ line is shared (wired-OR), so every device asserts and releases its own bit
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IrqSource {
+    ApuFrameCounter,
+    ApuDmc,
+    Cartridge,
+    Expansion,
+}
+
+impl IrqSource {
+    fn bit(&self) -> u8 {
+        match self {
+            IrqSource::ApuFrameCounter => 0b0000_0001,
+            IrqSource::ApuDmc => 0b0000_0010,
+            IrqSource::Cartridge => 0b0000_0100,
+            IrqSource::Expansion => 0b0000_1000,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct InterruptLines {
+    nmi_line: bool,
+    nmi_pending: bool,
+    irq_sources: u8,
+}
+
+impl InterruptLines {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    // NMI is edge triggered: only the transition to asserted latches a request
+    pub fn set_nmi_line(&mut self, asserted: bool) {
+        if asserted && !self.nmi_line {
+            self.nmi_pending = true;
+        }
+        self.nmi_line = asserted;
+    }
+    pub fn assert_irq(&mut self, source: IrqSource) {
+        self.irq_sources |= source.bit();
+    }
+    pub fn release_irq(&mut self, source: IrqSource) {
+        self.irq_sources &= !source.bit();
+    }
+    pub fn is_irq_asserted(&self) -> bool {
+        self.irq_sources != 0
+    }
+    pub fn is_nmi_pending(&self) -> bool {
+        self.nmi_pending
+    }
+    pub(crate) fn acknowledge_nmi(&mut self) {
+        self.nmi_pending = false;
+    }
+}
@@ -1,11 +1,170 @@
+pub mod bus;
+pub mod cartridge;
 pub mod cpu;
+pub mod interrupt;
+pub mod mapper;
 pub mod opcode;
+pub mod ppu;
+#[cfg(test)]
 pub mod test;
+pub mod timing;
 
 #[macro_use]
 extern crate lazy_static;
+
+use std::env;
+use std::fs;
+use std::io::{self, BufRead};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+
+fn read_file(path: &str) -> Vec<u8> {
+    match fs::read(path) {
+        Ok(bytes) => bytes,
+        Err(error) => {
+            eprintln!("could not read {}: {}", path, error);
+            process::exit(1);
+        }
+    }
+}
+
+// disk images run on the RAM adapter, which needs the BIOS dumped from a Famicom Disk System
+fn load_disk(path: &str, bytes: &[u8]) -> Box<dyn mapper::Mapper> {
+    let bios_path = match env::args().nth(2) {
+        Some(path) => path,
+        None => {
+            eprintln!("usage: nes_emulator <disk.fds> <disksys.rom>");
+            process::exit(1);
+        }
+    };
+    let bios = read_file(&bios_path);
+    match mapper::fds::FdsDisk::from_bytes(bytes).and_then(|disk| mapper::fds::Fds::new(bios, disk))
+    {
+        Ok(fds) => Box::new(fds),
+        Err(error) => {
+            eprintln!("could not load {}: {}", path, error);
+            process::exit(1);
+        }
+    }
+}
+
 fn main() {
-    let cpu = cpu::CPU::default();
+    let path = match env::args().nth(1) {
+        Some(path) => path,
+        None => {
+            eprintln!("usage: nes_emulator <rom.nes> | <disk.fds> <disksys.rom>");
+            eprintln!("commands on stdin: save, quit, eject, insert <side>");
+            process::exit(1);
+        }
+    };
+    let bytes = read_file(&path);
+    if path.to_lowercase().ends_with(".fds") {
+        let cycles_per_frame =
+            timing::TimingConfig::for_region(timing::Region::Ntsc).cpu_cycles_per_frame();
+        run(load_disk(&path, &bytes), cycles_per_frame, save_path(&path));
+    }
+    let cartridge = match cartridge::Cartridge::from_ines(&bytes) {
+        Ok(cartridge) => cartridge,
+        Err(error) => {
+            eprintln!("could not load {}: {}", path, error);
+            process::exit(1);
+        }
+    };
+
+    let cycles_per_frame = cartridge.timing_config().cpu_cycles_per_frame();
+    let mapper = match mapper::create_mapper(cartridge) {
+        Ok(mapper) => mapper,
+        Err(error) => {
+            eprintln!("could not load {}: {}", path, error);
+            process::exit(1);
+        }
+    };
+    run(mapper, cycles_per_frame, save_path(&path));
+}
+
+// battery saves sit next to the game, with the extension swapped for .sav
+fn save_path(path: &str) -> PathBuf {
+    Path::new(path).with_extension("sav")
+}
+fn load_battery(mapper: &mut dyn mapper::Mapper, path: &Path) {
+    if let Ok(data) = fs::read(path) {
+        mapper.load_battery_ram(&data);
+    }
+}
+fn save_battery(bus: &dyn bus::Bus, path: &Path) {
+    let data = match bus.cartridge() {
+        Some(mapper) => mapper.battery_ram(),
+        None => return,
+    };
+    if data.is_empty() {
+        return;
+    }
+    if let Err(error) = fs::write(path, data) {
+        eprintln!("could not write {}: {}", path.display(), error);
+    }
+}
+
+// lines typed on stdin, read on their own thread so the emulation never waits for them
+fn spawn_command_reader() -> Receiver<String> {
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        for line in io::stdin().lock().lines().map_while(Result::ok) {
+            if sender.send(line).is_err() {
+                break;
+            }
+        }
+    });
+    receiver
+}
+
+// sides count from 0 in the order they are stored in the image
+fn swap_disk(bus: &mut dyn bus::Bus, side: Option<usize>) {
+    let Some(drive) = bus.cartridge_mut().and_then(|mapper| mapper.disk_drive()) else {
+        eprintln!("the cartridge has no disk drive");
+        return;
+    };
+    match side {
+        None => drive.eject_disk(),
+        Some(side) if !drive.insert_disk(side) => eprintln!(
+            "the disk has no side {}, it has {}",
+            side,
+            drive.side_count()
+        ),
+        Some(_) => {}
+    }
+}
 
-    //cpu.load_and_run(program.load());
+fn run(mut mapper: Box<dyn mapper::Mapper>, cycles_per_frame: u64, save_path: PathBuf) -> ! {
+    load_battery(mapper.as_mut(), &save_path);
+    let mut bus = bus::NesBus::new();
+    bus.insert_cartridge(mapper);
+    let mut cpu = cpu::CPU::with_bus(Box::new(bus));
+    cpu.reset();
+    let commands = spawn_command_reader();
+    loop {
+        if let Err(error) = cpu.run_for_cycles(cycles_per_frame) {
+            eprintln!("{}", error);
+            save_battery(cpu.bus.as_ref(), &save_path);
+            process::exit(1);
+        }
+        // commands are handled between frames
+        while let Ok(command) = commands.try_recv() {
+            match command.split_whitespace().collect::<Vec<_>>().as_slice() {
+                [] => {}
+                ["save"] => save_battery(cpu.bus.as_ref(), &save_path),
+                ["quit"] => {
+                    save_battery(cpu.bus.as_ref(), &save_path);
+                    process::exit(0);
+                }
+                ["eject"] => swap_disk(cpu.bus.as_mut(), None),
+                ["insert", side] => match side.parse() {
+                    Ok(side) => swap_disk(cpu.bus.as_mut(), Some(side)),
+                    Err(_) => eprintln!("not a disk side: {}", side),
+                },
+                _ => eprintln!("unknown command: {}", command.trim()),
+            }
+        }
+    }
 }
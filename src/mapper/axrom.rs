@@ -0,0 +1,59 @@
+use super::*;
+
+const PRG_BANK_SIZE: usize = 0x8000;
+const PRG_BANK_MASK: u8 = 0b0000_1111;
+const NAMETABLE_PAGE_BIT: u8 = 0b0001_0000;
+
+// mapper 7: 32 KiB PRG banks and a register selected single-screen nametable
+pub struct Axrom {
+    prg_rom: Vec<u8>,
+    chr: Vec<u8>,
+    chr_is_ram: bool,
+    bus_conflicts: bool,
+    register: u8,
+}
+
+impl Axrom {
+    pub fn new(mut cartridge: Cartridge) -> Axrom {
+        let (chr, chr_is_ram) = chr_memory(&mut cartridge);
+        Axrom {
+            bus_conflicts: has_bus_conflicts(&cartridge),
+            prg_rom: cartridge.prg_rom,
+            chr,
+            chr_is_ram,
+            register: 0,
+        }
+    }
+}
+
+impl Mapper for Axrom {
+    fn cpu_peek(&self, address: u16) -> Option<u8> {
+        let offset = address.checked_sub(PRG_ROM_START)? as usize;
+        let bank = (self.register & PRG_BANK_MASK) as usize;
+        Some(self.prg_rom[banked_index(&self.prg_rom, bank, PRG_BANK_SIZE, offset)])
+    }
+    fn cpu_write(&mut self, address: u16, data: u8) {
+        if address >= PRG_ROM_START {
+            self.register = written_value(self, address, data);
+        }
+    }
+    fn ppu_peek(&self, address: u16) -> u8 {
+        self.chr[address as usize % self.chr.len()]
+    }
+    fn ppu_write(&mut self, address: u16, data: u8) {
+        if self.chr_is_ram {
+            let index = address as usize % self.chr.len();
+            self.chr[index] = data;
+        }
+    }
+    fn mirroring(&self) -> Mirroring {
+        if self.register & NAMETABLE_PAGE_BIT == NAMETABLE_PAGE_BIT {
+            Mirroring::SingleScreenUpper
+        } else {
+            Mirroring::SingleScreenLower
+        }
+    }
+    fn has_bus_conflicts(&self) -> bool {
+        self.bus_conflicts
+    }
+}
@@ -0,0 +1,62 @@
+use super::*;
+
+const CHR_BANK_SIZE: usize = 0x2000;
+
+// mapper 3: fixed PRG like NROM, 8 KiB switchable CHR-ROM
+pub struct Cnrom {
+    prg_rom: Vec<u8>,
+    chr: Vec<u8>,
+    chr_is_ram: bool,
+    mirroring: Mirroring,
+    bus_conflicts: bool,
+    chr_bank: u8,
+}
+
+impl Cnrom {
+    pub fn new(mut cartridge: Cartridge) -> Cnrom {
+        let (chr, chr_is_ram) = chr_memory(&mut cartridge);
+        Cnrom {
+            bus_conflicts: has_bus_conflicts(&cartridge),
+            prg_rom: cartridge.prg_rom,
+            chr,
+            chr_is_ram,
+            mirroring: cartridge.mirroring,
+            chr_bank: 0,
+        }
+    }
+    fn chr_index(&self, address: u16) -> usize {
+        banked_index(
+            &self.chr,
+            self.chr_bank as usize,
+            CHR_BANK_SIZE,
+            address as usize,
+        )
+    }
+}
+
+impl Mapper for Cnrom {
+    fn cpu_peek(&self, address: u16) -> Option<u8> {
+        let offset = address.checked_sub(PRG_ROM_START)? as usize;
+        Some(self.prg_rom[offset % self.prg_rom.len()])
+    }
+    fn cpu_write(&mut self, address: u16, data: u8) {
+        if address >= PRG_ROM_START {
+            self.chr_bank = written_value(self, address, data);
+        }
+    }
+    fn ppu_peek(&self, address: u16) -> u8 {
+        self.chr[self.chr_index(address)]
+    }
+    fn ppu_write(&mut self, address: u16, data: u8) {
+        if self.chr_is_ram {
+            let index = self.chr_index(address);
+            self.chr[index] = data;
+        }
+    }
+    fn mirroring(&self) -> Mirroring {
+        self.mirroring
+    }
+    fn has_bus_conflicts(&self) -> bool {
+        self.bus_conflicts
+    }
+}
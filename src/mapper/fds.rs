@@ -0,0 +1,553 @@
+use super::fds_audio::*;
+use super::*;
+use std::fmt;
+
+pub const BIOS_SIZE: usize = 0x2000;
+pub const DISK_SIDE_SIZE: usize = 65500;
+const FWNES_HEADER_SIZE: usize = 16;
+const FWNES_SIGNATURE: [u8; 4] = [b'F', b'D', b'S', 0x1a];
+const PRG_RAM_SIZE: usize = 0x8000;
+const CHR_RAM_SIZE: usize = 0x2000;
+const BIOS_START: u16 = 0xe000;
+
+// .fds images drop the gaps, start marks and CRCs, the drive sees them put back
+const LEADING_GAP_SIZE: usize = 28300 / 8;
+const BLOCK_GAP_SIZE: usize = 976 / 8;
+const BLOCK_START_MARK: u8 = 0x80;
+// a side with its gaps, about the length of the track the head passes over
+const RAW_SIDE_SIZE: usize = 0x12000;
+const DISK_INFO_BLOCK: u8 = 1;
+const FILE_AMOUNT_BLOCK: u8 = 2;
+const FILE_HEADER_BLOCK: u8 = 3;
+const FILE_DATA_BLOCK: u8 = 4;
+const CRC_POLYNOMIAL: u16 = 0x8408;
+
+// a byte passes under the head about every 150 CPU cycles (96.4 kbit/s)
+const BYTE_TRANSFER_CYCLES: u32 = 149;
+// spinning up and moving the head back to the start of the disk
+const HEAD_RETURN_CYCLES: u32 = 50000;
+
+const DISK_REGISTERS_ENABLE_BIT: u8 = 0b01;
+const SOUND_REGISTERS_ENABLE_BIT: u8 = 0b10;
+const TIMER_REPEAT_BIT: u8 = 0b01;
+const TIMER_ENABLE_BIT: u8 = 0b10;
+const MOTOR_ON_BIT: u8 = 0b0000_0001;
+const TRANSFER_RESET_BIT: u8 = 0b0000_0010;
+const READ_MODE_BIT: u8 = 0b0000_0100;
+const HORIZONTAL_MIRRORING_BIT: u8 = 0b0000_1000;
+const CRC_CONTROL_BIT: u8 = 0b0001_0000;
+const TRANSFER_START_BIT: u8 = 0b0100_0000;
+const DISK_IRQ_ENABLE_BIT: u8 = 0b1000_0000;
+const TIMER_IRQ_STATUS_BIT: u8 = 0b0000_0001;
+const TRANSFER_COMPLETE_STATUS_BIT: u8 = 0b0000_0010;
+const END_OF_HEAD_STATUS_BIT: u8 = 0b0100_0000;
+const DISK_MISSING_STATUS_BIT: u8 = 0b0000_0001;
+const DISK_NOT_READY_STATUS_BIT: u8 = 0b0000_0010;
+const WRITE_PROTECTED_STATUS_BIT: u8 = 0b0000_0100;
+const BATTERY_GOOD_BIT: u8 = 0b1000_0000;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FdsError {
+    InvalidBiosSize { length: usize },
+    NoDiskSides,
+    Truncated { expected: usize, actual: usize },
+}
+
+impl fmt::Display for FdsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FdsError::InvalidBiosSize { length } => write!(
+                f,
+                "FDS BIOS is {} bytes long, expected {} bytes",
+                length, BIOS_SIZE
+            ),
+            FdsError::NoDiskSides => write!(f, "disk image holds no disk sides"),
+            FdsError::Truncated { expected, actual } => write!(
+                f,
+                "disk image is truncated: expected {} bytes, found {}",
+                expected, actual
+            ),
+        }
+    }
+}
+
+impl std::error::Error for FdsError {}
+
+// the contents of a .fds file: 65500 bytes per disk side
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FdsDisk {
+    pub sides: Vec<Vec<u8>>,
+    // images with the fwNES header are written back with one
+    pub has_header: bool,
+}
+
+impl FdsDisk {
+    pub fn from_bytes(bytes: &[u8]) -> Result<FdsDisk, FdsError> {
+        let has_header = bytes.len() >= FWNES_HEADER_SIZE && bytes[0..4] == FWNES_SIGNATURE;
+        let (side_count, data) = if has_header {
+            (bytes[4] as usize, &bytes[FWNES_HEADER_SIZE..])
+        } else {
+            // without a side count only whole sides are read, trailing bytes are dropped
+            (bytes.len() / DISK_SIDE_SIZE, bytes)
+        };
+        if side_count == 0 {
+            return Err(FdsError::NoDiskSides);
+        }
+        let expected = side_count * DISK_SIDE_SIZE;
+        if data.len() < expected {
+            return Err(FdsError::Truncated {
+                expected,
+                actual: data.len(),
+            });
+        }
+        Ok(FdsDisk {
+            sides: data[..expected]
+                .chunks(DISK_SIDE_SIZE)
+                .map(|side| side.to_vec())
+                .collect(),
+            has_header,
+        })
+    }
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        if self.has_header {
+            bytes.extend_from_slice(&FWNES_SIGNATURE);
+            bytes.push(self.sides.len() as u8);
+            bytes.resize(FWNES_HEADER_SIZE, 0);
+        }
+        for side in self.sides.iter() {
+            bytes.extend_from_slice(side);
+        }
+        bytes
+    }
+}
+
+fn update_crc(crc: u16, data: u8) -> u16 {
+    let mut crc = crc;
+    for bit in 0..8 {
+        let carry = crc & 1 == 1;
+        crc >>= 1;
+        if carry {
+            crc ^= CRC_POLYNOMIAL;
+        }
+        if data & (1 << bit) != 0 {
+            crc ^= 0x8000;
+        }
+    }
+    crc
+}
+
+// the length of the block starting at `block`, file data blocks take theirs from the last header
+fn block_length(block: &[u8], file_size: usize) -> Option<usize> {
+    match block.first() {
+        Some(&DISK_INFO_BLOCK) => Some(56),
+        Some(&FILE_AMOUNT_BLOCK) => Some(2),
+        Some(&FILE_HEADER_BLOCK) => Some(16),
+        Some(&FILE_DATA_BLOCK) => Some(1 + file_size),
+        _ => None,
+    }
+}
+fn file_size(header_block: &[u8]) -> usize {
+    header_block[13] as usize | (header_block[14] as usize) << 8
+}
+
+fn add_gaps(side: &[u8]) -> Vec<u8> {
+    let mut raw = vec![0; LEADING_GAP_SIZE];
+    let mut position = 0;
+    let mut size = 0;
+    while let Some(length) = block_length(&side[position..], size) {
+        if position + length > side.len() {
+            break;
+        }
+        let block = &side[position..position + length];
+        if block[0] == FILE_HEADER_BLOCK {
+            size = file_size(block);
+        }
+        raw.push(BLOCK_START_MARK);
+        raw.extend_from_slice(block);
+        let crc = block
+            .iter()
+            .chain([0, 0].iter())
+            .fold(update_crc(0, BLOCK_START_MARK), |crc, byte| {
+                update_crc(crc, *byte)
+            });
+        raw.push(crc as u8);
+        raw.push((crc >> 8) as u8);
+        raw.resize(raw.len() + BLOCK_GAP_SIZE, 0);
+        position += length;
+    }
+    raw.resize(raw.len().max(RAW_SIDE_SIZE), 0);
+    raw
+}
+
+fn remove_gaps(raw: &[u8]) -> Vec<u8> {
+    let mut side = Vec::with_capacity(DISK_SIDE_SIZE);
+    let mut position = 0;
+    let mut size = 0;
+    loop {
+        while position < raw.len() && raw[position] == 0 {
+            position += 1;
+        }
+        if position >= raw.len() || raw[position] != BLOCK_START_MARK {
+            break;
+        }
+        position += 1;
+        let length = match block_length(&raw[position..], size) {
+            Some(length) if position + length <= raw.len() => length,
+            _ => break,
+        };
+        let block = &raw[position..position + length];
+        if block[0] == FILE_HEADER_BLOCK {
+            size = file_size(block);
+        }
+        side.extend_from_slice(block);
+        // skip the CRC
+        position += length + 2;
+    }
+    side.resize(DISK_SIDE_SIZE, 0);
+    side
+}
+
+// the Famicom Disk System: the RAM adapter in the cartridge slot running the BIOS, and its drive
+pub struct Fds {
+    bios: Vec<u8>,
+    prg_ram: Vec<u8>,
+    chr_ram: Vec<u8>,
+    has_header: bool,
+    // every side as the head passes over it, gaps and CRCs included
+    sides: Vec<Vec<u8>>,
+    inserted_side: Option<usize>,
+    master_io: u8,
+    timer_reload: u16,
+    timer_counter: u16,
+    timer_control: u8,
+    timer_irq: bool,
+    control: u8,
+    write_data: u8,
+    read_data: u8,
+    external_output: u8,
+    transfer_complete: bool,
+    disk_irq: bool,
+    is_motor_on: bool,
+    is_scanning: bool,
+    is_end_of_head: bool,
+    is_gap_ended: bool,
+    head_position: usize,
+    transfer_delay: u32,
+    crc_accumulator: u16,
+    previous_crc_control: bool,
+    audio: FdsAudio,
+}
+
+impl Fds {
+    pub fn new(bios: Vec<u8>, disk: FdsDisk) -> Result<Fds, FdsError> {
+        if bios.len() != BIOS_SIZE {
+            return Err(FdsError::InvalidBiosSize { length: bios.len() });
+        }
+        if disk.sides.is_empty() {
+            return Err(FdsError::NoDiskSides);
+        }
+        Ok(Fds {
+            bios,
+            prg_ram: vec![0; PRG_RAM_SIZE],
+            chr_ram: vec![0; CHR_RAM_SIZE],
+            has_header: disk.has_header,
+            sides: disk.sides.iter().map(|side| add_gaps(side)).collect(),
+            inserted_side: Some(0),
+            master_io: DISK_REGISTERS_ENABLE_BIT | SOUND_REGISTERS_ENABLE_BIT,
+            timer_reload: 0,
+            timer_counter: 0,
+            timer_control: 0,
+            timer_irq: false,
+            control: 0,
+            write_data: 0,
+            read_data: 0,
+            external_output: 0,
+            transfer_complete: false,
+            disk_irq: false,
+            is_motor_on: false,
+            is_scanning: false,
+            is_end_of_head: true,
+            is_gap_ended: false,
+            head_position: 0,
+            transfer_delay: 0,
+            crc_accumulator: 0,
+            previous_crc_control: false,
+            audio: FdsAudio::new(),
+        })
+    }
+
+    // the image with everything written to it so far
+    pub fn disk(&self) -> FdsDisk {
+        FdsDisk {
+            sides: self.sides.iter().map(|raw| remove_gaps(raw)).collect(),
+            has_header: self.has_header,
+        }
+    }
+
+    fn are_disk_registers_enabled(&self) -> bool {
+        self.master_io & DISK_REGISTERS_ENABLE_BIT == DISK_REGISTERS_ENABLE_BIT
+    }
+    fn are_sound_registers_enabled(&self) -> bool {
+        self.master_io & SOUND_REGISTERS_ENABLE_BIT == SOUND_REGISTERS_ENABLE_BIT
+    }
+    fn write_disk_register(&mut self, address: u16, data: u8) {
+        match address {
+            0x4020 => self.timer_reload = (self.timer_reload & 0xff00) | data as u16,
+            0x4021 => self.timer_reload = (self.timer_reload & 0x00ff) | (data as u16) << 8,
+            0x4022 => {
+                self.timer_control = data;
+                if data & TIMER_ENABLE_BIT == TIMER_ENABLE_BIT {
+                    self.timer_counter = self.timer_reload;
+                } else {
+                    self.timer_irq = false;
+                }
+            }
+            0x4024 => {
+                self.write_data = data;
+                self.transfer_complete = false;
+                self.disk_irq = false;
+            }
+            0x4025 => {
+                self.control = data;
+                self.is_motor_on = data & MOTOR_ON_BIT == MOTOR_ON_BIT;
+                self.disk_irq = false;
+            }
+            0x4026 => self.external_output = data,
+            _ => {}
+        }
+    }
+    fn read_disk_register(&self, address: u16) -> Option<u8> {
+        match address {
+            // CRC errors are never reported
+            0x4030 => {
+                let mut status = 0;
+                if self.timer_irq {
+                    status |= TIMER_IRQ_STATUS_BIT;
+                }
+                if self.transfer_complete {
+                    status |= TRANSFER_COMPLETE_STATUS_BIT;
+                }
+                if self.is_end_of_head {
+                    status |= END_OF_HEAD_STATUS_BIT;
+                }
+                Some(status)
+            }
+            0x4031 => Some(self.read_data),
+            0x4032 => {
+                let mut status = 0;
+                if self.inserted_side.is_none() {
+                    status |= DISK_MISSING_STATUS_BIT | WRITE_PROTECTED_STATUS_BIT;
+                }
+                if self.inserted_side.is_none() || !self.is_scanning {
+                    status |= DISK_NOT_READY_STATUS_BIT;
+                }
+                Some(status)
+            }
+            0x4033 => Some(BATTERY_GOOD_BIT | (self.external_output & 0x7f)),
+            _ => None,
+        }
+    }
+
+    fn clock_timer(&mut self) {
+        if self.timer_control & TIMER_ENABLE_BIT == 0 || !self.are_disk_registers_enabled() {
+            return;
+        }
+        if self.timer_counter == 0 {
+            self.timer_irq = true;
+            self.timer_counter = self.timer_reload;
+            if self.timer_control & TIMER_REPEAT_BIT == 0 {
+                self.timer_control &= !TIMER_ENABLE_BIT;
+            }
+        } else {
+            self.timer_counter -= 1;
+        }
+    }
+    fn clock_drive(&mut self) {
+        let side = match self.inserted_side {
+            Some(side) if self.is_motor_on => side,
+            _ => {
+                self.is_end_of_head = true;
+                self.is_scanning = false;
+                return;
+            }
+        };
+        if self.control & TRANSFER_RESET_BIT == TRANSFER_RESET_BIT && !self.is_scanning {
+            return;
+        }
+        if self.is_end_of_head {
+            self.transfer_delay = HEAD_RETURN_CYCLES;
+            self.is_end_of_head = false;
+            self.head_position = 0;
+            self.is_gap_ended = false;
+            return;
+        }
+        if self.transfer_delay > 0 {
+            self.transfer_delay -= 1;
+            return;
+        }
+        self.is_scanning = true;
+        self.transfer_byte(side);
+        self.head_position += 1;
+        if self.head_position >= self.sides[side].len() {
+            self.is_motor_on = false;
+        } else {
+            self.transfer_delay = BYTE_TRANSFER_CYCLES;
+        }
+    }
+    fn transfer_byte(&mut self, side: usize) {
+        let transfer_started = self.control & TRANSFER_START_BIT == TRANSFER_START_BIT;
+        let crc_control = self.control & CRC_CONTROL_BIT == CRC_CONTROL_BIT;
+        let mut needs_irq = self.control & DISK_IRQ_ENABLE_BIT == DISK_IRQ_ENABLE_BIT;
+        if !transfer_started {
+            self.crc_accumulator = 0;
+        }
+        if self.control & READ_MODE_BIT == READ_MODE_BIT {
+            let data = self.sides[side][self.head_position];
+            if !transfer_started {
+                self.is_gap_ended = false;
+            } else if data != 0 && !self.is_gap_ended {
+                // the start mark ends the gap, it sets the transfer flag but never raises the IRQ
+                self.is_gap_ended = true;
+                needs_irq = false;
+            }
+            if self.is_gap_ended {
+                self.transfer_complete = true;
+                self.read_data = data;
+                self.disk_irq |= needs_irq;
+            }
+        } else {
+            let mut data = 0;
+            if !crc_control {
+                self.transfer_complete = true;
+                self.disk_irq |= needs_irq;
+                if transfer_started {
+                    data = self.write_data;
+                }
+                self.crc_accumulator = update_crc(self.crc_accumulator, data);
+            } else {
+                // the CRC goes out low byte first after the block
+                if !self.previous_crc_control {
+                    self.crc_accumulator = update_crc(self.crc_accumulator, 0);
+                    self.crc_accumulator = update_crc(self.crc_accumulator, 0);
+                }
+                data = self.crc_accumulator as u8;
+                self.crc_accumulator >>= 8;
+            }
+            self.sides[side][self.head_position] = data;
+            self.is_gap_ended = false;
+        }
+        self.previous_crc_control = crc_control;
+    }
+}
+
+impl DiskDrive for Fds {
+    fn side_count(&self) -> usize {
+        self.sides.len()
+    }
+    fn inserted_side(&self) -> Option<usize> {
+        self.inserted_side
+    }
+    fn eject_disk(&mut self) {
+        self.inserted_side = None;
+    }
+    fn insert_disk(&mut self, side: usize) -> bool {
+        if side >= self.sides.len() {
+            return false;
+        }
+        self.inserted_side = Some(side);
+        self.is_end_of_head = true;
+        self.is_scanning = false;
+        true
+    }
+}
+
+impl Mapper for Fds {
+    fn cpu_peek(&self, address: u16) -> Option<u8> {
+        match address {
+            0x4030..=0x4033 if self.are_disk_registers_enabled() => {
+                self.read_disk_register(address)
+            }
+            0x4040..=0x4097 if self.are_sound_registers_enabled() => self.audio.read(address),
+            PRG_RAM_START..=0xdfff => Some(self.prg_ram[(address - PRG_RAM_START) as usize]),
+            BIOS_START..=0xffff => Some(self.bios[(address - BIOS_START) as usize]),
+            _ => None,
+        }
+    }
+    fn cpu_read(&mut self, address: u16) -> Option<u8> {
+        let data = self.cpu_peek(address);
+        if self.are_disk_registers_enabled() {
+            match address {
+                0x4030 => {
+                    self.timer_irq = false;
+                    self.disk_irq = false;
+                    self.transfer_complete = false;
+                }
+                0x4031 => {
+                    self.disk_irq = false;
+                    self.transfer_complete = false;
+                }
+                _ => {}
+            }
+        }
+        data
+    }
+    fn cpu_write(&mut self, address: u16, data: u8) {
+        match address {
+            0x4023 => {
+                self.master_io = data;
+                if !self.are_disk_registers_enabled() {
+                    self.timer_control &= !TIMER_ENABLE_BIT;
+                    self.timer_irq = false;
+                    self.disk_irq = false;
+                }
+            }
+            0x4020..=0x4026 if self.are_disk_registers_enabled() => {
+                self.write_disk_register(address, data)
+            }
+            0x4040..=0x4097 if self.are_sound_registers_enabled() => {
+                self.audio.write(address, data)
+            }
+            PRG_RAM_START..=0xdfff => self.prg_ram[(address - PRG_RAM_START) as usize] = data,
+            _ => {}
+        }
+    }
+    fn ppu_peek(&self, address: u16) -> u8 {
+        self.chr_ram[address as usize % CHR_RAM_SIZE]
+    }
+    fn ppu_write(&mut self, address: u16, data: u8) {
+        self.chr_ram[address as usize % CHR_RAM_SIZE] = data;
+    }
+    fn mirroring(&self) -> Mirroring {
+        if self.control & HORIZONTAL_MIRRORING_BIT == HORIZONTAL_MIRRORING_BIT {
+            Mirroring::Horizontal
+        } else {
+            Mirroring::Vertical
+        }
+    }
+    fn irq_asserted(&self) -> bool {
+        self.timer_irq || self.disk_irq
+    }
+    fn cpu_clock(&mut self) {
+        self.clock_timer();
+        self.clock_drive();
+        self.audio.cpu_clock();
+    }
+    fn audio_output(&self) -> f32 {
+        self.audio.output()
+    }
+    // the disk is the save: the whole image, written back the way it was loaded
+    fn battery_ram(&self) -> Vec<u8> {
+        self.disk().to_bytes()
+    }
+    fn load_battery_ram(&mut self, data: &[u8]) {
+        if let Ok(disk) = FdsDisk::from_bytes(data) {
+            if disk.sides.len() == self.sides.len() {
+                self.sides = disk.sides.iter().map(|side| add_gaps(side)).collect();
+            }
+        }
+    }
+    fn disk_drive(&mut self) -> Option<&mut dyn DiskDrive> {
+        Some(self)
+    }
+}
@@ -0,0 +1,231 @@
+const WAVE_TABLE_SIZE: usize = 64;
+const MODULATION_TABLE_SIZE: usize = 64;
+const ENVELOPE_DISABLE_BIT: u8 = 0b1000_0000;
+const ENVELOPE_INCREASE_BIT: u8 = 0b0100_0000;
+const HALT_BIT: u8 = 0b1000_0000;
+const ENVELOPES_DISABLE_BIT: u8 = 0b0100_0000;
+const WAVE_WRITE_ENABLE_BIT: u8 = 0b1000_0000;
+const MAX_GAIN: u8 = 32;
+// power-on value of the envelope speed multiplier ($408A)
+const DEFAULT_ENVELOPE_SPEED: u8 = 0xe8;
+// the modulation counter steps for mod table entries 0-7, None resets it to 0
+const MODULATION_STEPS: [Option<i8>; 8] = [
+    Some(0),
+    Some(1),
+    Some(2),
+    Some(4),
+    None,
+    Some(-4),
+    Some(-2),
+    Some(-1),
+];
+// $4089 bits 0-1: 2/2, 2/3, 2/4 and 2/5 of the full level
+const MASTER_VOLUMES: [f32; 4] = [1.0, 2.0 / 3.0, 0.5, 0.4];
+// a full-scale wave at full volume peaks a little over twice as loud as an APU pulse
+const OUTPUT_STEP_LEVEL: f32 = 0.00018;
+
+struct FdsEnvelope {
+    speed: u8,
+    gain: u8,
+    is_increasing: bool,
+    is_disabled: bool,
+    timer: u32,
+}
+
+impl FdsEnvelope {
+    fn new() -> FdsEnvelope {
+        FdsEnvelope {
+            speed: 0,
+            gain: 0,
+            is_increasing: false,
+            is_disabled: true,
+            timer: 0,
+        }
+    }
+    fn write(&mut self, data: u8, envelope_speed: u8) {
+        self.is_disabled = data & ENVELOPE_DISABLE_BIT == ENVELOPE_DISABLE_BIT;
+        self.is_increasing = data & ENVELOPE_INCREASE_BIT == ENVELOPE_INCREASE_BIT;
+        self.speed = data & 0b11_1111;
+        // with the envelope off the speed bits set the gain directly
+        if self.is_disabled {
+            self.gain = self.speed;
+        }
+        self.reset_timer(envelope_speed);
+    }
+    fn reset_timer(&mut self, envelope_speed: u8) {
+        self.timer = 8 * (self.speed as u32 + 1) * envelope_speed as u32;
+    }
+    fn cpu_clock(&mut self, envelope_speed: u8) {
+        if self.is_disabled {
+            return;
+        }
+        if self.timer > 0 {
+            self.timer -= 1;
+            return;
+        }
+        self.reset_timer(envelope_speed);
+        if self.is_increasing && self.gain < MAX_GAIN {
+            self.gain += 1;
+        } else if !self.is_increasing && self.gain > 0 {
+            self.gain -= 1;
+        }
+    }
+}
+
+// the RAM adapter's wavetable channel: 64 6-bit samples, pitch bent by a modulation unit
+pub struct FdsAudio {
+    wave_table: [u8; WAVE_TABLE_SIZE],
+    modulation_table: [u8; MODULATION_TABLE_SIZE],
+    volume_envelope: FdsEnvelope,
+    modulation_envelope: FdsEnvelope,
+    wave_frequency: u16,
+    wave_control: u8,
+    wave_accumulator: u32,
+    modulation_frequency: u16,
+    modulation_control: u8,
+    // 7-bit signed
+    modulation_counter: i8,
+    modulation_accumulator: u32,
+    modulation_position: usize,
+    master_control: u8,
+    envelope_speed: u8,
+}
+
+impl FdsAudio {
+    pub fn new() -> FdsAudio {
+        FdsAudio {
+            wave_table: [0; WAVE_TABLE_SIZE],
+            modulation_table: [0; MODULATION_TABLE_SIZE],
+            volume_envelope: FdsEnvelope::new(),
+            modulation_envelope: FdsEnvelope::new(),
+            wave_frequency: 0,
+            wave_control: HALT_BIT,
+            wave_accumulator: 0,
+            modulation_frequency: 0,
+            modulation_control: HALT_BIT,
+            modulation_counter: 0,
+            modulation_accumulator: 0,
+            modulation_position: 0,
+            master_control: 0,
+            envelope_speed: DEFAULT_ENVELOPE_SPEED,
+        }
+    }
+
+    // $4040-$4097
+    pub fn read(&self, address: u16) -> Option<u8> {
+        match address {
+            0x4040..=0x407f => Some(self.wave_table[(address - 0x4040) as usize]),
+            0x4090 => Some(self.volume_envelope.gain),
+            0x4092 => Some(self.modulation_envelope.gain),
+            _ => None,
+        }
+    }
+    pub fn write(&mut self, address: u16, data: u8) {
+        match address {
+            0x4040..=0x407f if self.is_wave_write_enabled() => {
+                self.wave_table[(address - 0x4040) as usize] = data & 0b11_1111;
+            }
+            0x4080 => self.volume_envelope.write(data, self.envelope_speed),
+            0x4082 => self.wave_frequency = (self.wave_frequency & 0x0f00) | data as u16,
+            0x4083 => {
+                self.wave_frequency = (self.wave_frequency & 0x00ff) | ((data & 0x0f) as u16) << 8;
+                self.wave_control = data;
+                // halting the wave also returns it to its first sample
+                if data & HALT_BIT == HALT_BIT {
+                    self.wave_accumulator = 0;
+                }
+            }
+            0x4084 => self.modulation_envelope.write(data, self.envelope_speed),
+            0x4085 => self.modulation_counter = ((data << 1) as i8) >> 1,
+            0x4086 => {
+                self.modulation_frequency = (self.modulation_frequency & 0x0f00) | data as u16;
+            }
+            0x4087 => {
+                self.modulation_frequency =
+                    (self.modulation_frequency & 0x00ff) | ((data & 0x0f) as u16) << 8;
+                self.modulation_control = data;
+                if data & HALT_BIT == HALT_BIT {
+                    self.modulation_accumulator = 0;
+                }
+            }
+            // the table only takes writes while modulation is halted, each entry fills two steps
+            0x4088 if self.modulation_control & HALT_BIT == HALT_BIT => {
+                self.modulation_table[self.modulation_position] = data & 0b111;
+                self.modulation_table[(self.modulation_position + 1) % MODULATION_TABLE_SIZE] =
+                    data & 0b111;
+                self.modulation_position = (self.modulation_position + 2) % MODULATION_TABLE_SIZE;
+            }
+            0x4089 => self.master_control = data,
+            0x408a => self.envelope_speed = data,
+            _ => {}
+        }
+    }
+
+    pub fn cpu_clock(&mut self) {
+        let envelopes_running =
+            self.wave_control & (HALT_BIT | ENVELOPES_DISABLE_BIT) == 0 && self.envelope_speed > 0;
+        if envelopes_running {
+            self.volume_envelope.cpu_clock(self.envelope_speed);
+            self.modulation_envelope.cpu_clock(self.envelope_speed);
+        }
+        if self.modulation_control & HALT_BIT == 0 && self.modulation_frequency > 0 {
+            self.modulation_accumulator += self.modulation_frequency as u32;
+            if self.modulation_accumulator >= 0x10000 {
+                self.modulation_accumulator -= 0x10000;
+                self.step_modulation();
+            }
+        }
+        if self.wave_control & HALT_BIT == 0 && !self.is_wave_write_enabled() {
+            self.wave_accumulator = (self.wave_accumulator + self.wave_pitch()) & 0x3f_ffff;
+        }
+    }
+
+    pub fn output(&self) -> f32 {
+        let sample = self.wave_table[(self.wave_accumulator >> 16) as usize];
+        let gain = self.volume_envelope.gain.min(MAX_GAIN);
+        let level = (sample as u32 * gain as u32) as f32;
+        level * MASTER_VOLUMES[(self.master_control & 0b11) as usize] * OUTPUT_STEP_LEVEL
+    }
+
+    fn is_wave_write_enabled(&self) -> bool {
+        self.master_control & WAVE_WRITE_ENABLE_BIT == WAVE_WRITE_ENABLE_BIT
+    }
+    fn step_modulation(&mut self) {
+        let entry = self.modulation_table[self.modulation_position] as usize;
+        self.modulation_counter = match MODULATION_STEPS[entry] {
+            // wraps within 7 bits
+            Some(step) => (self.modulation_counter.wrapping_add(step) << 1) >> 1,
+            None => 0,
+        };
+        self.modulation_position = (self.modulation_position + 1) % MODULATION_TABLE_SIZE;
+    }
+    // the wave frequency bent by counter * gain, rounded the way the hardware does it
+    fn wave_pitch(&self) -> u32 {
+        let pitch = self.wave_frequency as i32;
+        let counter = self.modulation_counter as i32;
+        let mut offset = counter * self.modulation_envelope.gain as i32;
+        let remainder = offset & 0x0f;
+        offset >>= 4;
+        if remainder > 0 && offset & 0x80 == 0 {
+            offset += if counter < 0 { -1 } else { 2 };
+        }
+        if offset >= 192 {
+            offset -= 256;
+        } else if offset < -64 {
+            offset += 256;
+        }
+        let mut bend = pitch * offset;
+        let remainder = bend & 0x3f;
+        bend >>= 6;
+        if remainder >= 32 {
+            bend += 1;
+        }
+        (pitch + bend).max(0) as u32
+    }
+}
+
+impl Default for FdsAudio {
+    fn default() -> Self {
+        Self::new()
+    }
+}
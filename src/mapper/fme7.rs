@@ -0,0 +1,356 @@
+use super::*;
+
+const PRG_BANK_SIZE: usize = 0x2000;
+const CHR_BANK_SIZE: usize = 0x0400;
+const PRG_BANK_MASK: u8 = 0b11_1111;
+const PRG_RAM_ENABLE_BIT: u8 = 0b1000_0000;
+const PRG_RAM_SELECT_BIT: u8 = 0b0100_0000;
+const IRQ_ENABLE_BIT: u8 = 0b0000_0001;
+const IRQ_COUNTER_ENABLE_BIT: u8 = 0b1000_0000;
+
+// the 5B's tone and noise generators run from the CPU clock divided by 16
+const AUDIO_CLOCK_DIVIDER: u8 = 16;
+// the envelope takes 32 steps of 8 CPU cycles times its period
+const ENVELOPE_CLOCK_DIVIDER: u8 = 8;
+const ENVELOPE_STEPS: u8 = 32;
+const ENVELOPE_CONTINUE_BIT: u8 = 0b1000;
+const ENVELOPE_ATTACK_BIT: u8 = 0b0100;
+const ENVELOPE_ALTERNATE_BIT: u8 = 0b0010;
+const ENVELOPE_HOLD_BIT: u8 = 0b0001;
+const VOLUME_ENVELOPE_BIT: u8 = 0b1_0000;
+// 32 output levels 1.5 dB apart, fixed volumes use every other one
+const DB_PER_LEVEL: f32 = 1.5;
+// a channel at full volume is about as loud as an APU pulse
+const CHANNEL_OUTPUT_SCALE: f32 = 0.15;
+
+#[derive(Default)]
+struct Envelope {
+    period: u16,
+    divider: u32,
+    shape: u8,
+    step: u8,
+    is_attacking: bool,
+    is_holding: bool,
+}
+
+impl Envelope {
+    fn restart(&mut self, shape: u8) {
+        self.shape = shape;
+        self.step = 0;
+        self.divider = 0;
+        self.is_attacking = shape & ENVELOPE_ATTACK_BIT == ENVELOPE_ATTACK_BIT;
+        self.is_holding = false;
+    }
+    fn cpu_clock(&mut self) {
+        self.divider += 1;
+        if self.divider < ENVELOPE_CLOCK_DIVIDER as u32 * self.period.max(1) as u32 {
+            return;
+        }
+        self.divider = 0;
+        if self.is_holding {
+            return;
+        }
+        self.step += 1;
+        if self.step < ENVELOPE_STEPS {
+            return;
+        }
+        // shapes without "continue" drop to 0 and stay there
+        if self.shape & ENVELOPE_CONTINUE_BIT == 0 {
+            self.is_holding = true;
+            self.is_attacking = false;
+            self.step = ENVELOPE_STEPS - 1;
+        } else if self.shape & ENVELOPE_HOLD_BIT == ENVELOPE_HOLD_BIT {
+            self.is_holding = true;
+            if self.shape & ENVELOPE_ALTERNATE_BIT == ENVELOPE_ALTERNATE_BIT {
+                self.is_attacking = !self.is_attacking;
+            }
+            self.step = ENVELOPE_STEPS - 1;
+        } else {
+            if self.shape & ENVELOPE_ALTERNATE_BIT == ENVELOPE_ALTERNATE_BIT {
+                self.is_attacking = !self.is_attacking;
+            }
+            self.step = 0;
+        }
+    }
+    fn level(&self) -> u8 {
+        if self.is_attacking {
+            self.step
+        } else {
+            ENVELOPE_STEPS - 1 - self.step
+        }
+    }
+}
+
+#[derive(Default)]
+struct Tone {
+    period: u16,
+    counter: u16,
+    output: bool,
+}
+
+impl Tone {
+    fn clock(&mut self) {
+        self.counter += 1;
+        if self.counter >= self.period.max(1) {
+            self.counter = 0;
+            self.output = !self.output;
+        }
+    }
+}
+
+// YM2149-style audio of the Sunsoft 5B: three squares sharing a noise generator and an envelope
+struct Sunsoft5bAudio {
+    register_select: u8,
+    tones: [Tone; 3],
+    noise_period: u8,
+    noise_counter: u8,
+    noise_half_clock: bool,
+    // 17-bit LFSR
+    noise_shift_register: u32,
+    mixer: u8,
+    volumes: [u8; 3],
+    envelope: Envelope,
+    divider: u8,
+}
+
+impl Sunsoft5bAudio {
+    fn new() -> Sunsoft5bAudio {
+        Sunsoft5bAudio {
+            register_select: 0,
+            tones: Default::default(),
+            noise_period: 0,
+            noise_counter: 0,
+            noise_half_clock: false,
+            noise_shift_register: 1,
+            mixer: 0,
+            volumes: [0; 3],
+            envelope: Envelope::default(),
+            divider: 0,
+        }
+    }
+    fn write_register(&mut self, data: u8) {
+        let register = self.register_select as usize;
+        match register {
+            0..=5 => {
+                let tone = &mut self.tones[register / 2];
+                tone.period = if register & 1 == 0 {
+                    (tone.period & 0x0f00) | data as u16
+                } else {
+                    (tone.period & 0x00ff) | ((data & 0x0f) as u16) << 8
+                };
+            }
+            6 => self.noise_period = data & 0b1_1111,
+            7 => self.mixer = data,
+            8..=10 => self.volumes[register - 8] = data & 0b1_1111,
+            11 => self.envelope.period = (self.envelope.period & 0xff00) | data as u16,
+            12 => self.envelope.period = (self.envelope.period & 0x00ff) | (data as u16) << 8,
+            13 => self.envelope.restart(data & 0x0f),
+            _ => {}
+        }
+    }
+    fn cpu_clock(&mut self) {
+        self.envelope.cpu_clock();
+        self.divider += 1;
+        if self.divider < AUDIO_CLOCK_DIVIDER {
+            return;
+        }
+        self.divider = 0;
+        for tone in self.tones.iter_mut() {
+            tone.clock();
+        }
+        // the noise generator runs at half the tone rate
+        self.noise_half_clock = !self.noise_half_clock;
+        if self.noise_half_clock {
+            self.noise_counter += 1;
+            if self.noise_counter >= self.noise_period.max(1) {
+                self.noise_counter = 0;
+                let feedback = (self.noise_shift_register ^ (self.noise_shift_register >> 3)) & 1;
+                self.noise_shift_register = (self.noise_shift_register >> 1) | feedback << 16;
+            }
+        }
+    }
+    fn output(&self) -> f32 {
+        let noise = self.noise_shift_register & 1 == 1;
+        let mut output = 0.0;
+        for (channel, tone) in self.tones.iter().enumerate() {
+            // a disabled generator counts as always high
+            let tone_on = tone.output || self.mixer & (1 << channel) != 0;
+            let noise_on = noise || self.mixer & (1 << (channel + 3)) != 0;
+            if !(tone_on && noise_on) {
+                continue;
+            }
+            let level = if self.volumes[channel] & VOLUME_ENVELOPE_BIT == VOLUME_ENVELOPE_BIT {
+                self.envelope.level()
+            } else {
+                let volume = self.volumes[channel] & 0x0f;
+                if volume == 0 {
+                    0
+                } else {
+                    volume * 2 + 1
+                }
+            };
+            if level > 0 {
+                let attenuation = (ENVELOPE_STEPS - 1 - level) as f32 * DB_PER_LEVEL;
+                output += 10f32.powf(-attenuation / 20.0) * CHANNEL_OUTPUT_SCALE;
+            }
+        }
+        output
+    }
+}
+
+// mapper 69: Sunsoft FME-7, and the 5B which adds audio
+pub struct Fme7 {
+    prg_rom: Vec<u8>,
+    prg_ram: Vec<u8>,
+    has_battery: bool,
+    chr: Vec<u8>,
+    chr_is_ram: bool,
+    command: u8,
+    chr_banks: [u8; 8],
+    // $6000, $8000, $A000, $C000
+    prg_banks: [u8; 4],
+    mirroring: u8,
+    irq_control: u8,
+    irq_counter: u16,
+    irq_pending: bool,
+    audio: Sunsoft5bAudio,
+}
+
+impl Fme7 {
+    pub fn new(mut cartridge: Cartridge) -> Fme7 {
+        let (chr, chr_is_ram) = chr_memory(&mut cartridge);
+        Fme7 {
+            prg_ram: prg_ram(&cartridge),
+            has_battery: cartridge.has_battery,
+            prg_rom: cartridge.prg_rom,
+            chr,
+            chr_is_ram,
+            command: 0,
+            chr_banks: [0; 8],
+            prg_banks: [0; 4],
+            mirroring: 0,
+            irq_control: 0,
+            irq_counter: 0,
+            irq_pending: false,
+            audio: Sunsoft5bAudio::new(),
+        }
+    }
+
+    fn write_parameter(&mut self, data: u8) {
+        match self.command {
+            0x0..=0x7 => self.chr_banks[self.command as usize] = data,
+            0x8 => self.prg_banks[0] = data,
+            0x9..=0xb => self.prg_banks[(self.command - 0x8) as usize] = data & PRG_BANK_MASK,
+            0xc => self.mirroring = data & 0b11,
+            0xd => {
+                self.irq_control = data;
+                self.irq_pending = false;
+            }
+            0xe => self.irq_counter = (self.irq_counter & 0xff00) | data as u16,
+            _ => self.irq_counter = (self.irq_counter & 0x00ff) | (data as u16) << 8,
+        }
+    }
+
+    fn is_ram_at_6000(&self) -> bool {
+        self.prg_banks[0] & PRG_RAM_SELECT_BIT == PRG_RAM_SELECT_BIT
+    }
+    fn is_prg_ram_enabled(&self) -> bool {
+        !self.prg_ram.is_empty()
+            && self.is_ram_at_6000()
+            && self.prg_banks[0] & PRG_RAM_ENABLE_BIT == PRG_RAM_ENABLE_BIT
+    }
+    fn prg_rom_index(&self, address: u16) -> usize {
+        let slot = ((address - PRG_RAM_START) as usize) / PRG_BANK_SIZE;
+        let bank = match slot {
+            0..=3 => (self.prg_banks[slot] & PRG_BANK_MASK) as usize,
+            _ => bank_count(&self.prg_rom, PRG_BANK_SIZE) - 1,
+        };
+        banked_index(
+            &self.prg_rom,
+            bank,
+            PRG_BANK_SIZE,
+            (address - PRG_RAM_START) as usize,
+        )
+    }
+    fn prg_ram_index(&self, address: u16) -> usize {
+        let offset = (address - PRG_RAM_START) as usize;
+        banked_index(
+            &self.prg_ram,
+            (self.prg_banks[0] & PRG_BANK_MASK) as usize,
+            PRG_BANK_SIZE,
+            offset,
+        )
+    }
+    fn chr_index(&self, address: u16) -> usize {
+        let bank = self.chr_banks[address as usize / CHR_BANK_SIZE];
+        banked_index(&self.chr, bank as usize, CHR_BANK_SIZE, address as usize)
+    }
+}
+
+impl Mapper for Fme7 {
+    fn cpu_peek(&self, address: u16) -> Option<u8> {
+        match address {
+            PRG_RAM_START..=0x7fff if self.is_ram_at_6000() => {
+                if self.is_prg_ram_enabled() {
+                    Some(self.prg_ram[self.prg_ram_index(address)])
+                } else {
+                    None
+                }
+            }
+            PRG_RAM_START..=0xffff => Some(self.prg_rom[self.prg_rom_index(address)]),
+            _ => None,
+        }
+    }
+    fn cpu_write(&mut self, address: u16, data: u8) {
+        match address {
+            PRG_RAM_START..=0x7fff if self.is_prg_ram_enabled() => {
+                let index = self.prg_ram_index(address);
+                self.prg_ram[index] = data;
+            }
+            0x8000..=0x9fff => self.command = data & 0x0f,
+            0xa000..=0xbfff => self.write_parameter(data),
+            0xc000..=0xdfff => self.audio.register_select = data & 0x0f,
+            0xe000..=0xffff => self.audio.write_register(data),
+            _ => {}
+        }
+    }
+    fn ppu_peek(&self, address: u16) -> u8 {
+        self.chr[self.chr_index(address)]
+    }
+    fn ppu_write(&mut self, address: u16, data: u8) {
+        if self.chr_is_ram {
+            let index = self.chr_index(address);
+            self.chr[index] = data;
+        }
+    }
+    fn mirroring(&self) -> Mirroring {
+        match self.mirroring {
+            0 => Mirroring::Vertical,
+            1 => Mirroring::Horizontal,
+            2 => Mirroring::SingleScreenLower,
+            _ => Mirroring::SingleScreenUpper,
+        }
+    }
+    fn irq_asserted(&self) -> bool {
+        self.irq_pending
+    }
+    // the counter decrements every cycle, wrapping from $0000 to $FFFF raises the IRQ
+    fn cpu_clock(&mut self) {
+        if self.irq_control & IRQ_COUNTER_ENABLE_BIT == IRQ_COUNTER_ENABLE_BIT {
+            self.irq_counter = self.irq_counter.wrapping_sub(1);
+            if self.irq_counter == 0xffff && self.irq_control & IRQ_ENABLE_BIT == IRQ_ENABLE_BIT {
+                self.irq_pending = true;
+            }
+        }
+        self.audio.cpu_clock();
+    }
+    fn audio_output(&self) -> f32 {
+        self.audio.output()
+    }
+    fn battery_ram(&self) -> Vec<u8> {
+        battery_prg_ram(&self.prg_ram, self.has_battery)
+    }
+    fn load_battery_ram(&mut self, data: &[u8]) {
+        load_battery_prg_ram(&mut self.prg_ram, self.has_battery, data);
+    }
+}
@@ -0,0 +1,59 @@
+use super::*;
+
+const PRG_BANK_SIZE: usize = 0x8000;
+const CHR_BANK_SIZE: usize = 0x2000;
+
+// mapper 66: one register holding a 32 KiB PRG bank (bits 4-5) and an 8 KiB CHR bank (bits 0-1)
+pub struct Gxrom {
+    prg_rom: Vec<u8>,
+    chr: Vec<u8>,
+    chr_is_ram: bool,
+    mirroring: Mirroring,
+    register: u8,
+}
+
+impl Gxrom {
+    pub fn new(mut cartridge: Cartridge) -> Gxrom {
+        let (chr, chr_is_ram) = chr_memory(&mut cartridge);
+        Gxrom {
+            prg_rom: cartridge.prg_rom,
+            chr,
+            chr_is_ram,
+            mirroring: cartridge.mirroring,
+            register: 0,
+        }
+    }
+    fn chr_index(&self, address: u16) -> usize {
+        let bank = (self.register & 0b0000_0011) as usize;
+        banked_index(&self.chr, bank, CHR_BANK_SIZE, address as usize)
+    }
+}
+
+impl Mapper for Gxrom {
+    fn cpu_peek(&self, address: u16) -> Option<u8> {
+        let offset = address.checked_sub(PRG_ROM_START)? as usize;
+        let bank = ((self.register >> 4) & 0b0000_0011) as usize;
+        Some(self.prg_rom[banked_index(&self.prg_rom, bank, PRG_BANK_SIZE, offset)])
+    }
+    fn cpu_write(&mut self, address: u16, data: u8) {
+        if address >= PRG_ROM_START {
+            self.register = written_value(self, address, data);
+        }
+    }
+    fn ppu_peek(&self, address: u16) -> u8 {
+        self.chr[self.chr_index(address)]
+    }
+    fn ppu_write(&mut self, address: u16, data: u8) {
+        if self.chr_is_ram {
+            let index = self.chr_index(address);
+            self.chr[index] = data;
+        }
+    }
+    fn mirroring(&self) -> Mirroring {
+        self.mirroring
+    }
+    // GNROM and MHROM both wire the ROM straight to the latch, NES 2.0 defines no clean variant
+    fn has_bus_conflicts(&self) -> bool {
+        true
+    }
+}
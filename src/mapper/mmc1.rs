@@ -0,0 +1,189 @@
+use super::*;
+
+const PRG_BANK_SIZE: usize = 0x4000;
+const CHR_BANK_SIZE: usize = 0x1000;
+const PRG_RAM_BANK_SIZE: usize = 0x2000;
+// a 1 travelling down the shift register marks the fifth write
+const SHIFT_REGISTER_EMPTY: u8 = 0b1_0000;
+const SHIFT_RESET_BIT: u8 = 0b1000_0000;
+const CONTROL_POWER_ON: u8 = 0b0_1100;
+const CONTROL_PRG_MODE_3: u8 = 0b0_1100;
+const CHR_4K_MODE_BIT: u8 = 0b1_0000;
+const PRG_RAM_DISABLE_BIT: u8 = 0b1_0000;
+// SUROM/SXROM use the CHR bank lines as the upper PRG address line
+const PRG_OUTER_BANK_BIT: u8 = 0b1_0000;
+const PRG_OUTER_BANK_ROM_SIZE: usize = 0x40000;
+
+// mapper 1: SxROM boards, registers are loaded one bit at a time through $8000-$FFFF
+pub struct Mmc1 {
+    prg_rom: Vec<u8>,
+    prg_ram: Vec<u8>,
+    has_battery: bool,
+    chr: Vec<u8>,
+    chr_is_ram: bool,
+    shift_register: u8,
+    // writes on consecutive cycles (the dummy write of INC/ASL/...) are ignored
+    written_this_cycle: bool,
+    control: u8,
+    chr_bank_0: u8,
+    chr_bank_1: u8,
+    prg_bank: u8,
+}
+
+impl Mmc1 {
+    pub fn new(mut cartridge: Cartridge) -> Mmc1 {
+        let (chr, chr_is_ram) = chr_memory(&mut cartridge);
+        Mmc1 {
+            prg_ram: prg_ram(&cartridge),
+            has_battery: cartridge.has_battery,
+            prg_rom: cartridge.prg_rom,
+            chr,
+            chr_is_ram,
+            shift_register: SHIFT_REGISTER_EMPTY,
+            written_this_cycle: false,
+            control: CONTROL_POWER_ON,
+            chr_bank_0: 0,
+            chr_bank_1: 0,
+            prg_bank: 0,
+        }
+    }
+
+    fn write_shift_register(&mut self, address: u16, data: u8) {
+        if self.written_this_cycle {
+            return;
+        }
+        self.written_this_cycle = true;
+        if data & SHIFT_RESET_BIT == SHIFT_RESET_BIT {
+            self.shift_register = SHIFT_REGISTER_EMPTY;
+            self.control |= CONTROL_PRG_MODE_3;
+            return;
+        }
+        let is_fifth_write = self.shift_register & 1 == 1;
+        self.shift_register = (self.shift_register >> 1) | ((data & 1) << 4);
+        if is_fifth_write {
+            let value = self.shift_register;
+            // A13 and A14 of the fifth write select the register
+            match address & 0x6000 {
+                0x0000 => self.control = value,
+                0x2000 => self.chr_bank_0 = value,
+                0x4000 => self.chr_bank_1 = value,
+                _ => self.prg_bank = value,
+            }
+            self.shift_register = SHIFT_REGISTER_EMPTY;
+        }
+    }
+
+    fn prg_outer_bank(&self) -> usize {
+        if self.prg_rom.len() > PRG_OUTER_BANK_ROM_SIZE {
+            (self.chr_bank_0 & PRG_OUTER_BANK_BIT) as usize
+        } else {
+            0
+        }
+    }
+    fn prg_rom_index(&self, address: u16) -> usize {
+        let inner_bank = (self.prg_bank & 0x0f) as usize;
+        let is_upper_half = address >= 0xc000;
+        let bank = match (self.control >> 2) & 0b11 {
+            // 32 KiB at $8000, the low bit of the bank number is ignored
+            0 | 1 => (inner_bank & !1) | is_upper_half as usize,
+            // first bank fixed at $8000, $C000 switchable
+            2 => {
+                if is_upper_half {
+                    inner_bank
+                } else {
+                    0
+                }
+            }
+            // $8000 switchable, last bank fixed at $C000
+            _ => {
+                if is_upper_half {
+                    0x0f
+                } else {
+                    inner_bank
+                }
+            }
+        };
+        let offset = (address - PRG_ROM_START) as usize;
+        banked_index(
+            &self.prg_rom,
+            self.prg_outer_bank() | bank,
+            PRG_BANK_SIZE,
+            offset,
+        )
+    }
+
+    fn is_prg_ram_enabled(&self) -> bool {
+        !self.prg_ram.is_empty() && self.prg_bank & PRG_RAM_DISABLE_BIT == 0
+    }
+    // SOROM has 16 KiB and SXROM 32 KiB of PRG-RAM, banked by the CHR bank lines
+    fn prg_ram_index(&self, address: u16) -> usize {
+        let bank = match self.prg_ram.len() / PRG_RAM_BANK_SIZE {
+            4 => (self.chr_bank_0 >> 2) & 0b11,
+            2 => (self.chr_bank_0 >> 3) & 0b1,
+            _ => 0,
+        };
+        let offset = (address - PRG_RAM_START) as usize;
+        banked_index(&self.prg_ram, bank as usize, PRG_RAM_BANK_SIZE, offset)
+    }
+
+    fn chr_index(&self, address: u16) -> usize {
+        let bank = if self.control & CHR_4K_MODE_BIT == CHR_4K_MODE_BIT {
+            if address < 0x1000 {
+                self.chr_bank_0
+            } else {
+                self.chr_bank_1
+            }
+        } else {
+            (self.chr_bank_0 & !1) | (address >= 0x1000) as u8
+        };
+        banked_index(&self.chr, bank as usize, CHR_BANK_SIZE, address as usize)
+    }
+}
+
+impl Mapper for Mmc1 {
+    fn cpu_peek(&self, address: u16) -> Option<u8> {
+        match address {
+            PRG_RAM_START..=0x7fff if self.is_prg_ram_enabled() => {
+                Some(self.prg_ram[self.prg_ram_index(address)])
+            }
+            PRG_ROM_START..=0xffff => Some(self.prg_rom[self.prg_rom_index(address)]),
+            _ => None,
+        }
+    }
+    fn cpu_write(&mut self, address: u16, data: u8) {
+        match address {
+            PRG_RAM_START..=0x7fff if self.is_prg_ram_enabled() => {
+                let index = self.prg_ram_index(address);
+                self.prg_ram[index] = data;
+            }
+            PRG_ROM_START..=0xffff => self.write_shift_register(address, data),
+            _ => {}
+        }
+    }
+    fn ppu_peek(&self, address: u16) -> u8 {
+        self.chr[self.chr_index(address)]
+    }
+    fn ppu_write(&mut self, address: u16, data: u8) {
+        if self.chr_is_ram {
+            let index = self.chr_index(address);
+            self.chr[index] = data;
+        }
+    }
+    fn mirroring(&self) -> Mirroring {
+        match self.control & 0b11 {
+            0 => Mirroring::SingleScreenLower,
+            1 => Mirroring::SingleScreenUpper,
+            2 => Mirroring::Vertical,
+            _ => Mirroring::Horizontal,
+        }
+    }
+    fn cpu_clock(&mut self) {
+        self.written_this_cycle = false;
+    }
+    fn battery_ram(&self) -> Vec<u8> {
+        battery_prg_ram(&self.prg_ram, self.has_battery)
+    }
+    fn load_battery_ram(&mut self, data: &[u8]) {
+        load_battery_prg_ram(&mut self.prg_ram, self.has_battery, data);
+    }
+}
@@ -0,0 +1,141 @@
+use super::*;
+
+const MMC2_PRG_BANK_SIZE: usize = 0x2000;
+const MMC4_PRG_BANK_SIZE: usize = 0x4000;
+const CHR_BANK_SIZE: usize = 0x1000;
+const LATCH_FD: u8 = 0xfd;
+const LATCH_FE: u8 = 0xfe;
+
+// mappers 9 (MMC2, PxROM) and 10 (MMC4, FxROM): each pattern table has two CHR banks, and
+// fetching tile $FD or $FE from it flips a latch that picks the bank for the fetches after it
+pub struct Mmc2 {
+    prg_rom: Vec<u8>,
+    prg_ram: Vec<u8>,
+    has_battery: bool,
+    chr: Vec<u8>,
+    chr_is_ram: bool,
+    is_mmc4: bool,
+    prg_bank: u8,
+    // [pattern table][latch FD, latch FE]
+    chr_banks: [[u8; 2]; 2],
+    latches: [u8; 2],
+    mirroring: Mirroring,
+}
+
+impl Mmc2 {
+    pub fn new(mut cartridge: Cartridge) -> Mmc2 {
+        let (chr, chr_is_ram) = chr_memory(&mut cartridge);
+        Mmc2 {
+            prg_ram: prg_ram(&cartridge),
+            has_battery: cartridge.has_battery,
+            is_mmc4: cartridge.mapper == 10,
+            prg_rom: cartridge.prg_rom,
+            chr,
+            chr_is_ram,
+            prg_bank: 0,
+            chr_banks: [[0; 2]; 2],
+            latches: [LATCH_FE; 2],
+            mirroring: Mirroring::Vertical,
+        }
+    }
+
+    fn prg_rom_index(&self, address: u16) -> usize {
+        let offset = (address - PRG_ROM_START) as usize;
+        let prg_bank_size = if self.is_mmc4 {
+            MMC4_PRG_BANK_SIZE
+        } else {
+            MMC2_PRG_BANK_SIZE
+        };
+        // MMC2 switches $8000-$9FFF, MMC4 $8000-$BFFF, the rest is fixed to the end of PRG-ROM
+        if offset < prg_bank_size {
+            banked_index(&self.prg_rom, self.prg_bank as usize, prg_bank_size, offset)
+        } else {
+            // $8000-$FFFF maps to the last four 8 KiB banks
+            let bank = bank_count(&self.prg_rom, MMC2_PRG_BANK_SIZE)
+                .wrapping_sub(4)
+                .wrapping_add(offset / MMC2_PRG_BANK_SIZE);
+            banked_index(&self.prg_rom, bank, MMC2_PRG_BANK_SIZE, offset)
+        }
+    }
+    fn chr_index(&self, address: u16) -> usize {
+        let table = (address as usize) / CHR_BANK_SIZE;
+        let latch = (self.latches[table] == LATCH_FE) as usize;
+        let bank = self.chr_banks[table][latch];
+        banked_index(&self.chr, bank as usize, CHR_BANK_SIZE, address as usize)
+    }
+    // MMC2 only watches the first row of tile $FD/$FE in the left pattern table
+    fn update_latch(&mut self, address: u16) {
+        let table = (address as usize) / CHR_BANK_SIZE;
+        let exact_only = table == 0 && !self.is_mmc4;
+        let row_matches = |tile_start: u16| {
+            if exact_only {
+                address & 0x0fff == tile_start
+            } else {
+                address & 0x0ff8 == tile_start
+            }
+        };
+        if row_matches(0x0fd8) {
+            self.latches[table] = LATCH_FD;
+        } else if row_matches(0x0fe8) {
+            self.latches[table] = LATCH_FE;
+        }
+    }
+}
+
+impl Mapper for Mmc2 {
+    fn cpu_peek(&self, address: u16) -> Option<u8> {
+        match address {
+            PRG_RAM_START..=0x7fff if !self.prg_ram.is_empty() => {
+                let offset = (address - PRG_RAM_START) as usize;
+                Some(self.prg_ram[offset % self.prg_ram.len()])
+            }
+            PRG_ROM_START..=0xffff => Some(self.prg_rom[self.prg_rom_index(address)]),
+            _ => None,
+        }
+    }
+    fn cpu_write(&mut self, address: u16, data: u8) {
+        match address {
+            PRG_RAM_START..=0x7fff if !self.prg_ram.is_empty() => {
+                let index = (address - PRG_RAM_START) as usize % self.prg_ram.len();
+                self.prg_ram[index] = data;
+            }
+            0xa000..=0xafff => self.prg_bank = data & 0x0f,
+            0xb000..=0xbfff => self.chr_banks[0][0] = data & 0x1f,
+            0xc000..=0xcfff => self.chr_banks[0][1] = data & 0x1f,
+            0xd000..=0xdfff => self.chr_banks[1][0] = data & 0x1f,
+            0xe000..=0xefff => self.chr_banks[1][1] = data & 0x1f,
+            0xf000..=0xffff => {
+                self.mirroring = if data & 1 == 1 {
+                    Mirroring::Horizontal
+                } else {
+                    Mirroring::Vertical
+                }
+            }
+            _ => {}
+        }
+    }
+    fn ppu_peek(&self, address: u16) -> u8 {
+        self.chr[self.chr_index(address)]
+    }
+    // the fetch that hits the trigger tile still comes from the old bank
+    fn ppu_read(&mut self, address: u16) -> u8 {
+        let data = self.ppu_peek(address);
+        self.update_latch(address);
+        data
+    }
+    fn ppu_write(&mut self, address: u16, data: u8) {
+        if self.chr_is_ram {
+            let index = self.chr_index(address);
+            self.chr[index] = data;
+        }
+    }
+    fn mirroring(&self) -> Mirroring {
+        self.mirroring
+    }
+    fn battery_ram(&self) -> Vec<u8> {
+        battery_prg_ram(&self.prg_ram, self.has_battery)
+    }
+    fn load_battery_ram(&mut self, data: &[u8]) {
+        load_battery_prg_ram(&mut self.prg_ram, self.has_battery, data);
+    }
+}
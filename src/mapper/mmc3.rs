@@ -0,0 +1,234 @@
+use super::*;
+
+const PRG_BANK_SIZE: usize = 0x2000;
+const CHR_BANK_SIZE: usize = 0x0400;
+const PRG_RAM_BANK_SIZE: usize = 0x2000;
+const BANK_REGISTER_MASK: u8 = 0b0000_0111;
+const PRG_MODE_BIT: u8 = 0b0100_0000;
+const CHR_INVERSION_BIT: u8 = 0b1000_0000;
+const PRG_RAM_ENABLE_BIT: u8 = 0b1000_0000;
+const PRG_RAM_WRITE_PROTECT_BIT: u8 = 0b0100_0000;
+const PPU_A12: u16 = 0x1000;
+// A12 has to stay low for a few M2 cycles before a rise clocks the counter again,
+// which filters out the toggling between background and sprite pattern fetches
+const A12_LOW_CYCLES_FILTER: u64 = 3;
+
+// the two MMC3 generations differ in when a reload to zero raises the IRQ
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mmc3Revision {
+    // MMC3B/MMC3C: every clock that leaves the counter at 0 raises the IRQ
+    Sharp,
+    // MMC3A: only a decrement to 0 or a forced reload ($C001) to 0 raises the IRQ
+    Nec,
+}
+
+// mapper 4: TxROM boards
+pub struct Mmc3 {
+    prg_rom: Vec<u8>,
+    prg_ram: Vec<u8>,
+    has_battery: bool,
+    chr: Vec<u8>,
+    chr_is_ram: bool,
+    four_screen: bool,
+    revision: Mmc3Revision,
+    bank_select: u8,
+    bank_registers: [u8; 8],
+    mirroring: Mirroring,
+    prg_ram_protect: u8,
+    irq_latch: u8,
+    irq_counter: u8,
+    irq_reload: bool,
+    irq_enabled: bool,
+    irq_pending: bool,
+    cpu_cycles: u64,
+    a12_high: bool,
+    a12_low_since: u64,
+}
+
+impl Mmc3 {
+    pub fn new(cartridge: Cartridge) -> Mmc3 {
+        // NES 2.0 submapper 4 is the MMC3A
+        let revision = if cartridge.submapper == 4 {
+            Mmc3Revision::Nec
+        } else {
+            Mmc3Revision::Sharp
+        };
+        Mmc3::with_revision(cartridge, revision)
+    }
+    pub fn with_revision(mut cartridge: Cartridge, revision: Mmc3Revision) -> Mmc3 {
+        let (chr, chr_is_ram) = chr_memory(&mut cartridge);
+        Mmc3 {
+            prg_ram: prg_ram(&cartridge),
+            has_battery: cartridge.has_battery,
+            prg_rom: cartridge.prg_rom,
+            chr,
+            chr_is_ram,
+            four_screen: cartridge.mirroring == Mirroring::FourScreen,
+            revision,
+            bank_select: 0,
+            bank_registers: [0, 2, 4, 5, 6, 7, 0, 1],
+            mirroring: cartridge.mirroring,
+            prg_ram_protect: PRG_RAM_ENABLE_BIT,
+            irq_latch: 0,
+            irq_counter: 0,
+            irq_reload: false,
+            irq_enabled: false,
+            irq_pending: false,
+            cpu_cycles: 0,
+            a12_high: false,
+            a12_low_since: 0,
+        }
+    }
+
+    fn write_register(&mut self, address: u16, data: u8) {
+        let is_odd = address & 1 == 1;
+        match (address & 0xe000, is_odd) {
+            (0x8000, false) => self.bank_select = data,
+            (0x8000, true) => {
+                self.bank_registers[(self.bank_select & BANK_REGISTER_MASK) as usize] = data
+            }
+            (0xa000, false) => {
+                if !self.four_screen {
+                    self.mirroring = if data & 1 == 1 {
+                        Mirroring::Horizontal
+                    } else {
+                        Mirroring::Vertical
+                    };
+                }
+            }
+            (0xa000, true) => self.prg_ram_protect = data,
+            (0xc000, false) => self.irq_latch = data,
+            (0xc000, true) => {
+                self.irq_counter = 0;
+                self.irq_reload = true;
+            }
+            (0xe000, false) => {
+                self.irq_enabled = false;
+                self.irq_pending = false;
+            }
+            _ => self.irq_enabled = true,
+        }
+    }
+
+    fn clock_irq_counter(&mut self) {
+        let was_forced_reload = self.irq_reload;
+        let was_decrement = self.irq_counter != 0 && !self.irq_reload;
+        if self.irq_counter == 0 || self.irq_reload {
+            self.irq_counter = self.irq_latch;
+            self.irq_reload = false;
+        } else {
+            self.irq_counter -= 1;
+        }
+        let raises_irq = match self.revision {
+            Mmc3Revision::Sharp => self.irq_counter == 0,
+            Mmc3Revision::Nec => self.irq_counter == 0 && (was_decrement || was_forced_reload),
+        };
+        if raises_irq && self.irq_enabled {
+            self.irq_pending = true;
+        }
+    }
+
+    fn prg_rom_index(&self, address: u16) -> usize {
+        let second_last = bank_count(&self.prg_rom, PRG_BANK_SIZE).wrapping_sub(2);
+        let slot = ((address - PRG_ROM_START) / PRG_BANK_SIZE as u16) as usize;
+        let swapped = self.bank_select & PRG_MODE_BIT == PRG_MODE_BIT;
+        let bank = match (slot, swapped) {
+            (0, false) | (2, true) => self.bank_registers[6] as usize,
+            (0, true) | (2, false) => second_last,
+            (1, _) => self.bank_registers[7] as usize,
+            _ => second_last.wrapping_add(1),
+        };
+        banked_index(
+            &self.prg_rom,
+            bank,
+            PRG_BANK_SIZE,
+            (address - PRG_ROM_START) as usize,
+        )
+    }
+    fn chr_index(&self, address: u16) -> usize {
+        // inversion swaps the 2 KiB half with the 1 KiB half
+        let address = if self.bank_select & CHR_INVERSION_BIT == CHR_INVERSION_BIT {
+            address ^ PPU_A12
+        } else {
+            address
+        };
+        let slot = (address / CHR_BANK_SIZE as u16) as usize;
+        let bank = match slot {
+            0..=3 => (self.bank_registers[slot / 2] & !1) as usize + slot % 2,
+            _ => self.bank_registers[slot - 2] as usize,
+        };
+        banked_index(&self.chr, bank, CHR_BANK_SIZE, address as usize)
+    }
+    fn is_prg_ram_enabled(&self) -> bool {
+        !self.prg_ram.is_empty() && self.prg_ram_protect & PRG_RAM_ENABLE_BIT == PRG_RAM_ENABLE_BIT
+    }
+    fn prg_ram_index(&self, address: u16) -> usize {
+        banked_index(
+            &self.prg_ram,
+            0,
+            PRG_RAM_BANK_SIZE,
+            (address - PRG_RAM_START) as usize,
+        )
+    }
+}
+
+impl Mapper for Mmc3 {
+    fn cpu_peek(&self, address: u16) -> Option<u8> {
+        match address {
+            PRG_RAM_START..=0x7fff if self.is_prg_ram_enabled() => {
+                Some(self.prg_ram[self.prg_ram_index(address)])
+            }
+            PRG_ROM_START..=0xffff => Some(self.prg_rom[self.prg_rom_index(address)]),
+            _ => None,
+        }
+    }
+    fn cpu_write(&mut self, address: u16, data: u8) {
+        match address {
+            PRG_RAM_START..=0x7fff => {
+                let is_write_protected =
+                    self.prg_ram_protect & PRG_RAM_WRITE_PROTECT_BIT == PRG_RAM_WRITE_PROTECT_BIT;
+                if self.is_prg_ram_enabled() && !is_write_protected {
+                    let index = self.prg_ram_index(address);
+                    self.prg_ram[index] = data;
+                }
+            }
+            PRG_ROM_START..=0xffff => self.write_register(address, data),
+            _ => {}
+        }
+    }
+    fn ppu_peek(&self, address: u16) -> u8 {
+        self.chr[self.chr_index(address)]
+    }
+    fn ppu_write(&mut self, address: u16, data: u8) {
+        if self.chr_is_ram {
+            let index = self.chr_index(address);
+            self.chr[index] = data;
+        }
+    }
+    fn ppu_address_bus(&mut self, address: u16) {
+        let a12_high = address & PPU_A12 == PPU_A12;
+        if a12_high && !self.a12_high {
+            if self.cpu_cycles - self.a12_low_since >= A12_LOW_CYCLES_FILTER {
+                self.clock_irq_counter();
+            }
+        } else if !a12_high && self.a12_high {
+            self.a12_low_since = self.cpu_cycles;
+        }
+        self.a12_high = a12_high;
+    }
+    fn mirroring(&self) -> Mirroring {
+        self.mirroring
+    }
+    fn irq_asserted(&self) -> bool {
+        self.irq_pending
+    }
+    fn cpu_clock(&mut self) {
+        self.cpu_cycles += 1;
+    }
+    fn battery_ram(&self) -> Vec<u8> {
+        battery_prg_ram(&self.prg_ram, self.has_battery)
+    }
+    fn load_battery_ram(&mut self, data: &[u8]) {
+        load_battery_prg_ram(&mut self.prg_ram, self.has_battery, data);
+    }
+}
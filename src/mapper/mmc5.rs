@@ -0,0 +1,635 @@
+use super::*;
+
+const PRG_BANK_SIZE: usize = 0x2000;
+const CHR_1K: usize = 0x0400;
+const BACKGROUND_CHR_BANK_SIZE: usize = 0x1000;
+const EXRAM_SIZE: usize = 0x0400;
+const PRG_ROM_SELECT_BIT: u8 = 0b1000_0000;
+const NAMETABLE_SIZE: u16 = 0x0400;
+const ATTRIBUTE_TABLE_OFFSET: u16 = 0x03c0;
+const PPUCTRL_SPRITE_8X16_BIT: u8 = 0b0010_0000;
+const PPUMASK_RENDERING_BITS: u8 = 0b0001_1000;
+const SCANLINE_IRQ_ENABLE_BIT: u8 = 0b1000_0000;
+const SPLIT_ENABLE_BIT: u8 = 0b1000_0000;
+const SPLIT_RIGHT_SIDE_BIT: u8 = 0b0100_0000;
+const PCM_READ_MODE_BIT: u8 = 0b0000_0001;
+const PCM_IRQ_ENABLE_BIT: u8 = 0b1000_0000;
+const VISIBLE_SCANLINES: u16 = 240;
+// the PPU reads its bus every other dot, so a few silent CPU cycles mean rendering stopped
+const IDLE_CYCLES_OUT_OF_FRAME: u8 = 3;
+// pattern fetches of a scanline counted from the one after the triple nametable read:
+// 32 background tiles (columns 2-33), 8 sprites, then the 2 tiles prefetched for the next line
+const SPRITE_FETCHES_START: usize = 64;
+const PREFETCH_START: usize = 80;
+const FETCHES_PER_SCANLINE: usize = 84;
+// envelope and length counters run from a fixed 240 Hz divider instead of a frame counter
+const AUDIO_FRAME_PERIOD: u16 = 7457;
+
+const DUTY_SEQUENCES: [[u8; 8]; 4] = [
+    [0, 1, 0, 0, 0, 0, 0, 0],
+    [0, 1, 1, 0, 0, 0, 0, 0],
+    [0, 1, 1, 1, 1, 0, 0, 0],
+    [1, 0, 0, 1, 1, 1, 1, 1],
+];
+const LENGTH_TABLE: [u8; 32] = [
+    10, 254, 20, 2, 40, 4, 80, 6, 160, 8, 60, 10, 14, 12, 26, 14, 12, 16, 24, 18, 48, 20, 96, 22,
+    192, 24, 72, 26, 16, 28, 32, 30,
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ExramMode {
+    Nametable,
+    ExtendedAttributes,
+    Ram,
+    ReadOnlyRam,
+}
+
+// the APU pulse without its sweep unit
+#[derive(Default)]
+struct Mmc5Pulse {
+    enabled: bool,
+    duty: usize,
+    // doubles as the envelope loop flag
+    length_halt: bool,
+    constant_volume: bool,
+    // doubles as the envelope period
+    volume: u8,
+    envelope_start: bool,
+    envelope_divider: u8,
+    envelope_decay: u8,
+    timer_period: u16,
+    timer: u16,
+    sequence_step: usize,
+    length_counter: u8,
+}
+
+impl Mmc5Pulse {
+    fn write(&mut self, register: u16, data: u8) {
+        match register {
+            0 => {
+                self.duty = (data >> 6) as usize;
+                self.length_halt = data & 0b0010_0000 != 0;
+                self.constant_volume = data & 0b0001_0000 != 0;
+                self.volume = data & 0x0f;
+            }
+            2 => self.timer_period = (self.timer_period & 0x0700) | data as u16,
+            3 => {
+                self.timer_period = (self.timer_period & 0x00ff) | ((data & 0b111) as u16) << 8;
+                if self.enabled {
+                    self.length_counter = LENGTH_TABLE[(data >> 3) as usize];
+                }
+                self.sequence_step = 0;
+                self.envelope_start = true;
+            }
+            // $5001/$5005 would be the sweep unit, MMC5 has none
+            _ => {}
+        }
+    }
+    fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+        if !enabled {
+            self.length_counter = 0;
+        }
+    }
+    fn clock_timer(&mut self) {
+        if self.timer == 0 {
+            self.timer = self.timer_period;
+            self.sequence_step = (self.sequence_step + 1) % 8;
+        } else {
+            self.timer -= 1;
+        }
+    }
+    fn clock_envelope_and_length(&mut self) {
+        if self.envelope_start {
+            self.envelope_start = false;
+            self.envelope_decay = 15;
+            self.envelope_divider = self.volume;
+        } else if self.envelope_divider == 0 {
+            self.envelope_divider = self.volume;
+            if self.envelope_decay > 0 {
+                self.envelope_decay -= 1;
+            } else if self.length_halt {
+                self.envelope_decay = 15;
+            }
+        } else {
+            self.envelope_divider -= 1;
+        }
+        if !self.length_halt && self.length_counter > 0 {
+            self.length_counter -= 1;
+        }
+    }
+    fn output(&self) -> u8 {
+        if self.length_counter == 0 || DUTY_SEQUENCES[self.duty][self.sequence_step] == 0 {
+            0
+        } else if self.constant_volume {
+            self.volume
+        } else {
+            self.envelope_decay
+        }
+    }
+}
+
+// mapper 5: ExROM boards
+pub struct Mmc5 {
+    prg_rom: Vec<u8>,
+    prg_ram: Vec<u8>,
+    has_battery: bool,
+    chr: Vec<u8>,
+    chr_is_ram: bool,
+    exram: [u8; EXRAM_SIZE],
+    prg_mode: u8,
+    chr_mode: u8,
+    prg_ram_protect: [u8; 2],
+    exram_mode: ExramMode,
+    nametable_mapping: u8,
+    fill_tile: u8,
+    fill_attribute: u8,
+    // $5113-$5117
+    prg_banks: [u8; 5],
+    // $5120-$5127 for sprites, $5128-$512B for the background in 8x16 sprite mode
+    chr_banks: [u16; 12],
+    chr_upper_bits: u8,
+    last_chr_write_was_background: bool,
+    split_control: u8,
+    split_scroll: u8,
+    split_chr_bank: u8,
+    irq_compare: u8,
+    irq_enabled: bool,
+    irq_pending: bool,
+    multiplicand: u8,
+    multiplier: u8,
+    // snooped from PPUCTRL
+    sprites_8x16: bool,
+    in_frame: bool,
+    scanline: u16,
+    last_ppu_address: u16,
+    identical_reads: u8,
+    idle_cycles: u8,
+    pattern_fetches: usize,
+    // position in the scanline of the fetch currently on the PPU bus
+    fetch_index: usize,
+    last_tile: usize,
+    pulses: [Mmc5Pulse; 2],
+    pcm_control: u8,
+    pcm_level: u8,
+    pcm_irq_pending: bool,
+    audio_cycles: u16,
+    odd_cycle: bool,
+}
+
+impl Mmc5 {
+    pub fn new(mut cartridge: Cartridge) -> Mmc5 {
+        let (chr, chr_is_ram) = chr_memory(&mut cartridge);
+        Mmc5 {
+            prg_ram: prg_ram(&cartridge),
+            has_battery: cartridge.has_battery,
+            prg_rom: cartridge.prg_rom,
+            chr,
+            chr_is_ram,
+            exram: [0; EXRAM_SIZE],
+            prg_mode: 3,
+            chr_mode: 3,
+            prg_ram_protect: [0; 2],
+            exram_mode: ExramMode::Nametable,
+            nametable_mapping: 0,
+            fill_tile: 0,
+            fill_attribute: 0,
+            prg_banks: [0, 0, 0, 0, 0xff],
+            chr_banks: [0; 12],
+            chr_upper_bits: 0,
+            last_chr_write_was_background: false,
+            split_control: 0,
+            split_scroll: 0,
+            split_chr_bank: 0,
+            irq_compare: 0,
+            irq_enabled: false,
+            irq_pending: false,
+            multiplicand: 0xff,
+            multiplier: 0xff,
+            sprites_8x16: false,
+            in_frame: false,
+            scanline: 0,
+            last_ppu_address: 0,
+            identical_reads: 0,
+            idle_cycles: 0,
+            pattern_fetches: FETCHES_PER_SCANLINE,
+            fetch_index: FETCHES_PER_SCANLINE,
+            last_tile: 0,
+            pulses: [Mmc5Pulse::default(), Mmc5Pulse::default()],
+            pcm_control: 0,
+            pcm_level: 0,
+            pcm_irq_pending: false,
+            audio_cycles: 0,
+            odd_cycle: false,
+        }
+    }
+
+    fn write_register(&mut self, address: u16, data: u8) {
+        match address {
+            0x5000..=0x5007 => self.pulses[(address as usize >> 2) & 1].write(address & 0b11, data),
+            0x5010 => self.pcm_control = data,
+            // a zero cannot be written, it is the end marker of read mode
+            0x5011 if self.pcm_control & PCM_READ_MODE_BIT == 0 && data != 0 => {
+                self.pcm_level = data
+            }
+            0x5015 => {
+                self.pulses[0].set_enabled(data & 0b01 != 0);
+                self.pulses[1].set_enabled(data & 0b10 != 0);
+            }
+            0x5100 => self.prg_mode = data & 0b11,
+            0x5101 => self.chr_mode = data & 0b11,
+            0x5102 => self.prg_ram_protect[0] = data & 0b11,
+            0x5103 => self.prg_ram_protect[1] = data & 0b11,
+            0x5104 => {
+                self.exram_mode = match data & 0b11 {
+                    0 => ExramMode::Nametable,
+                    1 => ExramMode::ExtendedAttributes,
+                    2 => ExramMode::Ram,
+                    _ => ExramMode::ReadOnlyRam,
+                }
+            }
+            0x5105 => self.nametable_mapping = data,
+            0x5106 => self.fill_tile = data,
+            0x5107 => self.fill_attribute = data & 0b11,
+            0x5113..=0x5117 => self.prg_banks[(address - 0x5113) as usize] = data,
+            0x5120..=0x512b => {
+                // the upper bits are latched together with the register
+                let register = (address - 0x5120) as usize;
+                self.chr_banks[register] = data as u16 | (self.chr_upper_bits as u16) << 8;
+                self.last_chr_write_was_background = register >= 8;
+            }
+            0x5130 => self.chr_upper_bits = data & 0b11,
+            0x5200 => self.split_control = data,
+            0x5201 => self.split_scroll = data,
+            0x5202 => self.split_chr_bank = data,
+            0x5203 => self.irq_compare = data,
+            0x5204 => self.irq_enabled = data & SCANLINE_IRQ_ENABLE_BIT != 0,
+            0x5205 => self.multiplicand = data,
+            0x5206 => self.multiplier = data,
+            0x5c00..=0x5fff => {
+                let index = (address - 0x5c00) as usize;
+                match self.exram_mode {
+                    // while the PPU is not rendering, the nametable modes only store zeroes
+                    ExramMode::Nametable | ExramMode::ExtendedAttributes => {
+                        self.exram[index] = if self.in_frame { data } else { 0 }
+                    }
+                    ExramMode::Ram => self.exram[index] = data,
+                    ExramMode::ReadOnlyRam => {}
+                }
+            }
+            _ => {}
+        }
+    }
+    fn peek_register(&self, address: u16) -> Option<u8> {
+        match address {
+            0x5010 => Some((self.pcm_irq_pending as u8) << 7),
+            0x5015 => Some(
+                (self.pulses[0].length_counter > 0) as u8
+                    | ((self.pulses[1].length_counter > 0) as u8) << 1,
+            ),
+            0x5204 => Some((self.irq_pending as u8) << 7 | (self.in_frame as u8) << 6),
+            0x5205 => Some((self.multiplicand as u16 * self.multiplier as u16) as u8),
+            0x5206 => Some(((self.multiplicand as u16 * self.multiplier as u16) >> 8) as u8),
+            0x5c00..=0x5fff => match self.exram_mode {
+                ExramMode::Ram | ExramMode::ReadOnlyRam => {
+                    Some(self.exram[(address - 0x5c00) as usize])
+                }
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    // (is ROM, 8 KiB bank) of the window an address falls into
+    fn prg_window(&self, address: u16) -> (bool, usize) {
+        if address < PRG_ROM_START {
+            return (false, (self.prg_banks[0] & 0x0f) as usize);
+        }
+        let slot = ((address - PRG_ROM_START) as usize) / PRG_BANK_SIZE;
+        let (register, bank_mask) = match (self.prg_mode, slot) {
+            (0, _) => (4, 0b0111_1100),
+            (1, 0..=1) | (2, 0..=1) => (2, 0b0111_1110),
+            (1, _) => (4, 0b0111_1110),
+            (2, 2) => (3, 0b0111_1111),
+            (2, _) => (4, 0b0111_1111),
+            _ => (slot + 1, 0b0111_1111),
+        };
+        let value = self.prg_banks[register];
+        // $5117 always maps ROM
+        let is_rom = register == 4 || value & PRG_ROM_SELECT_BIT == PRG_ROM_SELECT_BIT;
+        let slot_in_bank = slot & !(bank_mask as usize) & 0b11;
+        if is_rom {
+            (true, (value & bank_mask) as usize | slot_in_bank)
+        } else {
+            (false, (value & bank_mask & 0x0f) as usize | slot_in_bank)
+        }
+    }
+    fn is_prg_ram_writable(&self) -> bool {
+        !self.prg_ram.is_empty() && self.prg_ram_protect == [0b10, 0b01]
+    }
+
+    fn background_column(&self) -> Option<usize> {
+        if !self.in_frame {
+            return None;
+        }
+        match self.fetch_index {
+            0..SPRITE_FETCHES_START => Some(2 + self.fetch_index / 2),
+            PREFETCH_START..FETCHES_PER_SCANLINE => Some((self.fetch_index - PREFETCH_START) / 2),
+            _ => None,
+        }
+    }
+    // the scanline the current background fetch belongs to, prefetched tiles are for the next one
+    fn background_scanline(&self) -> u16 {
+        if self.fetch_index >= PREFETCH_START {
+            self.scanline + 1
+        } else {
+            self.scanline
+        }
+    }
+    fn split_column(&self) -> Option<usize> {
+        if self.split_control & SPLIT_ENABLE_BIT == 0
+            || self.exram_mode == ExramMode::Ram
+            || self.exram_mode == ExramMode::ReadOnlyRam
+        {
+            return None;
+        }
+        let column = self.background_column()?;
+        let split_tiles = (self.split_control & 0b1_1111) as usize;
+        let is_inside = if self.split_control & SPLIT_RIGHT_SIDE_BIT == SPLIT_RIGHT_SIDE_BIT {
+            column >= split_tiles
+        } else {
+            column < split_tiles
+        };
+        is_inside.then_some(column)
+    }
+    fn split_y(&self) -> u16 {
+        (self.split_scroll as u16 + self.background_scanline()) % VISIBLE_SCANLINES
+    }
+    fn uses_background_chr_banks(&self) -> bool {
+        if self.sprites_8x16 && self.in_frame {
+            !(SPRITE_FETCHES_START..PREFETCH_START).contains(&self.fetch_index)
+        } else {
+            self.last_chr_write_was_background
+        }
+    }
+    fn chr_index(&self, address: u16) -> usize {
+        if self.split_column().is_some() {
+            let offset = (address as usize & 0x0ff8) | (self.split_y() & 0b111) as usize;
+            return banked_index(
+                &self.chr,
+                self.split_chr_bank as usize,
+                BACKGROUND_CHR_BANK_SIZE,
+                offset,
+            );
+        }
+        if self.exram_mode == ExramMode::ExtendedAttributes && self.background_column().is_some() {
+            let bank = (self.exram[self.last_tile] & 0b11_1111) as usize
+                | (self.chr_upper_bits as usize) << 6;
+            return banked_index(&self.chr, bank, BACKGROUND_CHR_BANK_SIZE, address as usize);
+        }
+        // 8 KiB >> mode per bank, the last register of each group selects it
+        let bank_size = (CHR_1K * 8) >> self.chr_mode;
+        let registers_per_bank = 1 << (3 - self.chr_mode);
+        let register = if self.uses_background_chr_banks() {
+            // the background set only covers 4 KiB, repeated in both pattern tables
+            let registers_per_bank = registers_per_bank.min(4);
+            let slot = (address as usize & 0x0fff) / (CHR_1K * registers_per_bank);
+            8 + (slot + 1) * registers_per_bank - 1
+        } else {
+            let slot = address as usize / bank_size;
+            (slot + 1) * registers_per_bank - 1
+        };
+        banked_index(
+            &self.chr,
+            self.chr_banks[register] as usize,
+            bank_size,
+            address as usize,
+        )
+    }
+
+    fn nametable_source(&self, address: u16) -> u8 {
+        let quadrant = (address >> 10) & 0b11;
+        (self.nametable_mapping >> (quadrant * 2)) & 0b11
+    }
+    // the split region has its own nametable and attributes in ExRAM
+    fn split_nametable_byte(&self, address: u16, column: usize) -> u8 {
+        let row = (self.split_y() / 8) as usize;
+        if address % NAMETABLE_SIZE >= ATTRIBUTE_TABLE_OFFSET {
+            let attribute =
+                self.exram[ATTRIBUTE_TABLE_OFFSET as usize + (row / 4) * 8 + column / 4];
+            let shift = ((row & 0b10) << 1) | (column & 0b10);
+            ((attribute >> shift) & 0b11) * 0b0101_0101
+        } else {
+            self.exram[row * 32 + column]
+        }
+    }
+
+    fn end_frame(&mut self) {
+        self.in_frame = false;
+        self.irq_pending = false;
+        self.pattern_fetches = FETCHES_PER_SCANLINE;
+        self.fetch_index = FETCHES_PER_SCANLINE;
+    }
+    fn detect_scanline(&mut self) {
+        if self.in_frame {
+            self.scanline += 1;
+            if self.scanline == self.irq_compare as u16 {
+                self.irq_pending = true;
+            }
+        } else {
+            self.in_frame = true;
+            self.scanline = 0;
+        }
+        self.pattern_fetches = 0;
+    }
+
+    fn clock_audio(&mut self) {
+        self.odd_cycle = !self.odd_cycle;
+        if self.odd_cycle {
+            for pulse in self.pulses.iter_mut() {
+                pulse.clock_timer();
+            }
+        }
+        self.audio_cycles += 1;
+        if self.audio_cycles == AUDIO_FRAME_PERIOD {
+            self.audio_cycles = 0;
+            for pulse in self.pulses.iter_mut() {
+                pulse.clock_envelope_and_length();
+            }
+        }
+    }
+}
+
+impl Mapper for Mmc5 {
+    fn cpu_peek(&self, address: u16) -> Option<u8> {
+        match address {
+            0x5000..=0x5fff => self.peek_register(address),
+            PRG_RAM_START..=0xffff => {
+                let (is_rom, bank) = self.prg_window(address);
+                let offset = (address - PRG_RAM_START) as usize;
+                if is_rom {
+                    Some(self.prg_rom[banked_index(&self.prg_rom, bank, PRG_BANK_SIZE, offset)])
+                } else if self.prg_ram.is_empty() {
+                    None
+                } else {
+                    Some(self.prg_ram[banked_index(&self.prg_ram, bank, PRG_BANK_SIZE, offset)])
+                }
+            }
+            _ => None,
+        }
+    }
+    fn cpu_read(&mut self, address: u16) -> Option<u8> {
+        let data = self.cpu_peek(address);
+        match address {
+            0x5010 => self.pcm_irq_pending = false,
+            0x5204 => self.irq_pending = false,
+            // fetching the NMI vector marks the end of the frame
+            0xfffa | 0xfffb => self.end_frame(),
+            0x8000..=0xbfff if self.pcm_control & PCM_READ_MODE_BIT == PCM_READ_MODE_BIT => {
+                match data {
+                    Some(0) => self.pcm_irq_pending = true,
+                    Some(level) => self.pcm_level = level,
+                    None => {}
+                }
+            }
+            _ => {}
+        }
+        data
+    }
+    fn cpu_write(&mut self, address: u16, data: u8) {
+        match address {
+            0x5000..=0x5fff => self.write_register(address, data),
+            PRG_RAM_START..=0xdfff => {
+                let (is_rom, bank) = self.prg_window(address);
+                if !is_rom && self.is_prg_ram_writable() {
+                    let offset = (address - PRG_RAM_START) as usize;
+                    let index = banked_index(&self.prg_ram, bank, PRG_BANK_SIZE, offset);
+                    self.prg_ram[index] = data;
+                }
+            }
+            _ => {}
+        }
+    }
+    fn ppu_peek(&self, address: u16) -> u8 {
+        self.chr[self.chr_index(address)]
+    }
+    fn ppu_write(&mut self, address: u16, data: u8) {
+        if self.chr_is_ram {
+            let index = self.chr_index(address);
+            self.chr[index] = data;
+        }
+    }
+    // scanlines are found by the three identical nametable reads that end every rendered line
+    fn ppu_address_bus(&mut self, address: u16) {
+        self.idle_cycles = 0;
+        let is_nametable = (0x2000..0x3000).contains(&address);
+        if is_nametable && address == self.last_ppu_address {
+            self.identical_reads += 1;
+            if self.identical_reads == 2 {
+                self.detect_scanline();
+            }
+        } else {
+            self.identical_reads = 0;
+        }
+        self.last_ppu_address = address;
+        self.fetch_index = self.pattern_fetches;
+        if address < 0x2000 {
+            self.pattern_fetches = (self.pattern_fetches + 1).min(FETCHES_PER_SCANLINE);
+        } else if is_nametable && address % NAMETABLE_SIZE < ATTRIBUTE_TABLE_OFFSET {
+            self.last_tile = (address % NAMETABLE_SIZE) as usize;
+        }
+    }
+    fn mirroring(&self) -> Mirroring {
+        match self.nametable_mapping {
+            0x00 => Mirroring::SingleScreenLower,
+            0x55 => Mirroring::SingleScreenUpper,
+            0x44 => Mirroring::Vertical,
+            0x50 => Mirroring::Horizontal,
+            // anything involving ExRAM or fill mode has no name
+            _ => Mirroring::FourScreen,
+        }
+    }
+    fn nametable_page(&self, address: u16) -> usize {
+        (self.nametable_source(address) & 1) as usize
+    }
+    fn nametable_peek(&self, address: u16) -> Option<u8> {
+        let is_attribute = address % NAMETABLE_SIZE >= ATTRIBUTE_TABLE_OFFSET;
+        if let Some(column) = self.split_column() {
+            return Some(self.split_nametable_byte(address, column));
+        }
+        if is_attribute
+            && self.exram_mode == ExramMode::ExtendedAttributes
+            && self.background_column().is_some()
+        {
+            return Some((self.exram[self.last_tile] >> 6) * 0b0101_0101);
+        }
+        match self.nametable_source(address) {
+            2 => match self.exram_mode {
+                ExramMode::Nametable | ExramMode::ExtendedAttributes => {
+                    Some(self.exram[(address % NAMETABLE_SIZE) as usize])
+                }
+                _ => Some(0),
+            },
+            3 if is_attribute => Some(self.fill_attribute * 0b0101_0101),
+            3 => Some(self.fill_tile),
+            _ => None,
+        }
+    }
+    fn nametable_write(&mut self, address: u16, data: u8) -> bool {
+        match self.nametable_source(address) {
+            2 => {
+                if matches!(
+                    self.exram_mode,
+                    ExramMode::Nametable | ExramMode::ExtendedAttributes
+                ) {
+                    self.exram[(address % NAMETABLE_SIZE) as usize] = data;
+                }
+                true
+            }
+            3 => true,
+            _ => false,
+        }
+    }
+    fn snoop_cpu_write(&mut self, address: u16, data: u8) {
+        // the PPU registers repeat every 8 bytes up to $3FFF
+        match address & 0xe007 {
+            0x2000 => self.sprites_8x16 = data & PPUCTRL_SPRITE_8X16_BIT != 0,
+            0x2001 if data & PPUMASK_RENDERING_BITS == 0 => self.end_frame(),
+            _ => {}
+        }
+    }
+    fn irq_asserted(&self) -> bool {
+        (self.irq_pending && self.irq_enabled)
+            || (self.pcm_irq_pending && self.pcm_control & PCM_IRQ_ENABLE_BIT != 0)
+    }
+    fn cpu_clock(&mut self) {
+        if self.idle_cycles < IDLE_CYCLES_OUT_OF_FRAME {
+            self.idle_cycles += 1;
+        } else if self.in_frame {
+            self.end_frame();
+        }
+        self.clock_audio();
+    }
+    // same curves as the APU's pulse and DMC mix
+    fn audio_output(&self) -> f32 {
+        let pulses = (self.pulses[0].output() + self.pulses[1].output()) as f32;
+        let pulse_out = if pulses == 0.0 {
+            0.0
+        } else {
+            95.88 / (8128.0 / pulses + 100.0)
+        };
+        let pcm = (self.pcm_level / 2) as f32;
+        let pcm_out = if pcm == 0.0 {
+            0.0
+        } else {
+            159.79 / (1.0 / (pcm / 22638.0) + 100.0)
+        };
+        pulse_out + pcm_out
+    }
+    fn battery_ram(&self) -> Vec<u8> {
+        battery_prg_ram(&self.prg_ram, self.has_battery)
+    }
+    fn load_battery_ram(&mut self, data: &[u8]) {
+        load_battery_prg_ram(&mut self.prg_ram, self.has_battery, data);
+    }
+}
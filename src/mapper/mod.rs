@@ -0,0 +1,180 @@
+pub mod axrom;
+pub mod cnrom;
+pub mod color_dreams;
+pub mod fds;
+pub mod fds_audio;
+pub mod fme7;
+pub mod gxrom;
+pub mod mmc1;
+pub mod mmc2;
+pub mod mmc3;
+pub mod mmc5;
+pub mod namco163;
+pub mod nrom;
+pub mod opll;
+pub mod uxrom;
+pub mod vrc;
+pub mod vrc4;
+pub mod vrc6;
+pub mod vrc7;
+
+use super::cartridge::*;
+
+pub const PRG_RAM_START: u16 = 0x6000;
+pub const PRG_ROM_START: u16 = 0x8000;
+
+// the cartridge board: decodes CPU $4020-$FFFF and PPU $0000-$1FFF
+pub trait Mapper {
+    // None when nothing on the board drives the data bus (open bus)
+    fn cpu_peek(&self, address: u16) -> Option<u8>;
+    fn cpu_read(&mut self, address: u16) -> Option<u8> {
+        self.cpu_peek(address)
+    }
+    fn cpu_write(&mut self, address: u16, data: u8);
+    fn ppu_peek(&self, address: u16) -> u8;
+    // a real PPU fetch: pattern fetches arrive in rendering order, so boards can switch on them
+    fn ppu_read(&mut self, address: u16) -> u8 {
+        self.ppu_peek(address)
+    }
+    fn ppu_write(&mut self, address: u16, data: u8);
+    // every address the PPU drives, nametable fetches included, for boards watching A12 and friends
+    fn ppu_address_bus(&mut self, _address: u16) {}
+    fn mirroring(&self) -> Mirroring;
+    // the 1 KiB page of console VRAM (CIRAM A10) a nametable address selects
+    fn nametable_page(&self, address: u16) -> usize {
+        mirrored_nametable_page(self.mirroring(), address)
+    }
+    // nametable bytes the board answers itself (ExRAM, fill mode, ...), None reads console VRAM
+    fn nametable_peek(&self, _address: u16) -> Option<u8> {
+        None
+    }
+    fn nametable_read(&mut self, address: u16) -> Option<u8> {
+        self.nametable_peek(address)
+    }
+    // true when the board took the write, otherwise it lands in console VRAM
+    fn nametable_write(&mut self, _address: u16, _data: u8) -> bool {
+        false
+    }
+    // CPU writes below cartridge space, for boards snooping the PPU registers
+    fn snoop_cpu_write(&mut self, _address: u16, _data: u8) {}
+    // expansion audio on the scale of the APU's mixed output (0.0 - 1.0), sampled every CPU cycle
+    fn audio_output(&self) -> f32 {
+        0.0
+    }
+    fn irq_asserted(&self) -> bool {
+        false
+    }
+    // once per CPU cycle, after the instruction's bus accesses
+    fn cpu_clock(&mut self) {}
+    // battery-backed memory to keep between sessions, empty when the board saves nothing
+    fn battery_ram(&self) -> Vec<u8> {
+        Vec::new()
+    }
+    fn load_battery_ram(&mut self, _data: &[u8]) {}
+    // boards without a register decoder let the ROM drive the data bus during writes too
+    fn has_bus_conflicts(&self) -> bool {
+        false
+    }
+    // boards reading from a disk let the host swap it
+    fn disk_drive(&mut self) -> Option<&mut dyn DiskDrive> {
+        None
+    }
+}
+
+// the host's side of a disk drive: which side of the image sits in it
+pub trait DiskDrive {
+    fn side_count(&self) -> usize;
+    fn inserted_side(&self) -> Option<usize>;
+    fn eject_disk(&mut self);
+    // false when the image has no such side
+    fn insert_disk(&mut self, side: usize) -> bool;
+}
+
+pub fn create_mapper(cartridge: Cartridge) -> Result<Box<dyn Mapper>, CartridgeError> {
+    match cartridge.mapper {
+        0 => Ok(Box::new(nrom::Nrom::new(cartridge))),
+        1 => Ok(Box::new(mmc1::Mmc1::new(cartridge))),
+        2 => Ok(Box::new(uxrom::Uxrom::new(cartridge))),
+        3 => Ok(Box::new(cnrom::Cnrom::new(cartridge))),
+        4 => Ok(Box::new(mmc3::Mmc3::new(cartridge))),
+        5 => Ok(Box::new(mmc5::Mmc5::new(cartridge))),
+        7 => Ok(Box::new(axrom::Axrom::new(cartridge))),
+        9 | 10 => Ok(Box::new(mmc2::Mmc2::new(cartridge))),
+        11 => Ok(Box::new(color_dreams::ColorDreams::new(cartridge))),
+        19 => Ok(Box::new(namco163::Namco163::new(cartridge))),
+        21 | 22 | 23 | 25 => Ok(Box::new(vrc4::Vrc4::new(cartridge))),
+        24 | 26 => Ok(Box::new(vrc6::Vrc6::new(cartridge))),
+        66 => Ok(Box::new(gxrom::Gxrom::new(cartridge))),
+        69 => Ok(Box::new(fme7::Fme7::new(cartridge))),
+        85 => Ok(Box::new(vrc7::Vrc7::new(cartridge))),
+        mapper => Err(CartridgeError::UnsupportedMapper {
+            mapper,
+            submapper: cartridge.submapper,
+        }),
+    }
+}
+
+// CHR-ROM, or the CHR-RAM the header asks for when the board has none
+pub(crate) fn chr_memory(cartridge: &mut Cartridge) -> (Vec<u8>, bool) {
+    if !cartridge.chr_rom.is_empty() {
+        return (std::mem::take(&mut cartridge.chr_rom), false);
+    }
+    let size = (cartridge.chr_ram_size + cartridge.chr_nvram_size).max(0x2000);
+    (vec![0; size], true)
+}
+
+// mappers 2, 3 and 7: NES 2.0 submapper 2 marks AND-type bus conflicts, 1 and 0 are clean
+pub(crate) fn has_bus_conflicts(cartridge: &Cartridge) -> bool {
+    cartridge.submapper == 2
+}
+
+// the value a register latches: with a bus conflict the ROM byte at the address wins every 0 bit
+pub(crate) fn written_value(mapper: &dyn Mapper, address: u16, data: u8) -> u8 {
+    if mapper.has_bus_conflicts() {
+        data & mapper.cpu_peek(address).unwrap_or(0xff)
+    } else {
+        data
+    }
+}
+
+// index of an offset inside a bank, bank numbers wrap around the memory present on the board
+pub(crate) fn banked_index(memory: &[u8], bank: usize, bank_size: usize, offset: usize) -> usize {
+    bank.wrapping_mul(bank_size)
+        .wrapping_add(offset % bank_size)
+        % memory.len()
+}
+// memory smaller than a bank still counts as one, mirrored across it
+pub(crate) fn bank_count(memory: &[u8], bank_size: usize) -> usize {
+    (memory.len() / bank_size).max(1)
+}
+
+pub(crate) fn prg_ram(cartridge: &Cartridge) -> Vec<u8> {
+    vec![0; cartridge.prg_ram_size + cartridge.prg_nvram_size]
+}
+
+// boards with the battery bit set keep their whole PRG-RAM in the save
+pub(crate) fn battery_prg_ram(prg_ram: &[u8], has_battery: bool) -> Vec<u8> {
+    if has_battery {
+        prg_ram.to_vec()
+    } else {
+        Vec::new()
+    }
+}
+
+pub(crate) fn load_battery_prg_ram(prg_ram: &mut [u8], has_battery: bool, data: &[u8]) {
+    if has_battery && data.len() == prg_ram.len() {
+        prg_ram.copy_from_slice(data);
+    }
+}
+
+// four-screen boards bring the two extra pages, so they count as pages 2 and 3
+pub fn mirrored_nametable_page(mirroring: Mirroring, address: u16) -> usize {
+    let quadrant = ((address >> 10) & 0b11) as usize;
+    match mirroring {
+        Mirroring::Horizontal => quadrant >> 1,
+        Mirroring::Vertical => quadrant & 1,
+        Mirroring::SingleScreenLower => 0,
+        Mirroring::SingleScreenUpper => 1,
+        Mirroring::FourScreen => quadrant,
+    }
+}
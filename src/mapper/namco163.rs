@@ -0,0 +1,288 @@
+use super::*;
+
+const PRG_BANK_SIZE: usize = 0x2000;
+const CHR_BANK_SIZE: usize = 0x0400;
+const PRG_BANK_MASK: u8 = 0b11_1111;
+const SOUND_RAM_SIZE: usize = 0x80;
+const SOUND_AUTO_INCREMENT_BIT: u8 = 0b1000_0000;
+const SOUND_DISABLE_BIT: u8 = 0b0100_0000;
+// $5800 bit 7, kept as the top bit of the counter register
+const IRQ_ENABLE_BIT: u16 = 0x8000;
+const IRQ_COUNTER_MAX: u16 = 0x7fff;
+// banks $E0-$FF in the nametable registers pick a page of console VRAM instead of CHR-ROM
+const CIRAM_BANKS_START: u8 = 0xe0;
+// $F800 bits 4-7 have to hold this for PRG-RAM to accept writes
+const PRG_RAM_WRITE_ENABLE: u8 = 0b0100_0000;
+const CHANNEL_REGISTERS_START: usize = 0x40;
+const CHANNEL_REGISTERS_SIZE: usize = 8;
+// the chip updates one channel every 15 CPU cycles and outputs it until the next update
+const CYCLES_PER_CHANNEL: u8 = 15;
+// one step of (sample - 8) * volume, a single loud channel is about as loud as an APU pulse
+const OUTPUT_STEP_LEVEL: f32 = 0.0015;
+
+// how the time-multiplexed channels reach the mix
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Namco163Mixing {
+    // one channel at a time like the real chip, which whines with many channels enabled
+    Multiplexed,
+    // the average of every enabled channel's last output
+    Averaged,
+}
+
+// mapper 19: Namco 129/163
+pub struct Namco163 {
+    prg_rom: Vec<u8>,
+    prg_ram: Vec<u8>,
+    chr: Vec<u8>,
+    chr_is_ram: bool,
+    has_battery: bool,
+    mixing: Namco163Mixing,
+    // $8000-$B800 pattern table banks, then $C000-$D800 nametable banks
+    chr_banks: [u8; 12],
+    // $8000, $A000, $C000, bit 6 of the first disables the sound
+    prg_banks: [u8; 3],
+    write_protect: u8,
+    // 15-bit counter with the enable bit on top
+    irq_counter: u16,
+    irq_pending: bool,
+    sound_ram: [u8; SOUND_RAM_SIZE],
+    sound_address: u8,
+    channel_divider: u8,
+    current_channel: usize,
+    channel_outputs: [i16; 8],
+}
+
+impl Namco163 {
+    pub fn new(cartridge: Cartridge) -> Namco163 {
+        Namco163::with_mixing(cartridge, Namco163Mixing::Multiplexed)
+    }
+    pub fn with_mixing(mut cartridge: Cartridge, mixing: Namco163Mixing) -> Namco163 {
+        let (chr, chr_is_ram) = chr_memory(&mut cartridge);
+        Namco163 {
+            prg_ram: prg_ram(&cartridge),
+            has_battery: cartridge.has_battery,
+            prg_rom: cartridge.prg_rom,
+            chr,
+            chr_is_ram,
+            mixing,
+            chr_banks: [0; 12],
+            prg_banks: [0; 3],
+            write_protect: 0,
+            irq_counter: 0,
+            irq_pending: false,
+            sound_ram: [0; SOUND_RAM_SIZE],
+            sound_address: 0,
+            channel_divider: 0,
+            // wraps around to channel 7 on the first update
+            current_channel: 0,
+            channel_outputs: [0; 8],
+        }
+    }
+
+    fn prg_rom_index(&self, address: u16) -> usize {
+        let slot = ((address - PRG_ROM_START) as usize) / PRG_BANK_SIZE;
+        let bank = match slot {
+            0..=2 => (self.prg_banks[slot] & PRG_BANK_MASK) as usize,
+            _ => bank_count(&self.prg_rom, PRG_BANK_SIZE) - 1,
+        };
+        banked_index(
+            &self.prg_rom,
+            bank,
+            PRG_BANK_SIZE,
+            (address - PRG_ROM_START) as usize,
+        )
+    }
+    // pattern table banks of $E0 and up would map console VRAM when enabled by $E800,
+    // which the board cannot reach through this interface, so they read CHR like any other bank
+    fn chr_index(&self, address: u16) -> usize {
+        let bank = self.chr_banks[address as usize / CHR_BANK_SIZE];
+        banked_index(&self.chr, bank as usize, CHR_BANK_SIZE, address as usize)
+    }
+    fn nametable_bank(&self, address: u16) -> u8 {
+        self.chr_banks[8 + ((address >> 10) & 0b11) as usize]
+    }
+    // each bit of $F800 bits 0-3 protects 2 KiB of the 8 KiB
+    fn is_prg_ram_writable(&self, address: u16) -> bool {
+        let region = (address - PRG_RAM_START) / 0x0800;
+        self.write_protect & 0xf0 == PRG_RAM_WRITE_ENABLE && self.write_protect & (1 << region) == 0
+    }
+    fn advance_sound_address(&mut self) {
+        if self.sound_address & SOUND_AUTO_INCREMENT_BIT == SOUND_AUTO_INCREMENT_BIT {
+            let address = ((self.sound_address & 0x7f) + 1) & 0x7f;
+            self.sound_address = SOUND_AUTO_INCREMENT_BIT | address;
+        }
+    }
+
+    fn enabled_channels(&self) -> usize {
+        ((self.sound_ram[0x7f] >> 4) & 0b111) as usize + 1
+    }
+    // channel 7 sits at $78-$7F, the enabled ones count down from there
+    fn update_channel(&mut self, channel: usize) {
+        let base = CHANNEL_REGISTERS_START + channel * CHANNEL_REGISTERS_SIZE;
+        let registers = &self.sound_ram[base..base + CHANNEL_REGISTERS_SIZE];
+        let frequency =
+            registers[0] as u32 | (registers[2] as u32) << 8 | ((registers[4] & 0b11) as u32) << 16;
+        let length = 256 - (registers[4] & 0b1111_1100) as u32;
+        let phase = registers[1] as u32 | (registers[3] as u32) << 8 | (registers[5] as u32) << 16;
+        let phase = (phase + frequency) % (length << 16);
+        let wave_address = registers[6] as u32;
+        let volume = (registers[7] & 0x0f) as i16;
+        self.sound_ram[base + 1] = phase as u8;
+        self.sound_ram[base + 3] = (phase >> 8) as u8;
+        self.sound_ram[base + 5] = (phase >> 16) as u8;
+
+        let sample_index = ((wave_address + (phase >> 16)) & 0xff) as usize;
+        let sample_byte = self.sound_ram[sample_index / 2 % SOUND_RAM_SIZE];
+        let sample = if sample_index & 1 == 0 {
+            sample_byte & 0x0f
+        } else {
+            sample_byte >> 4
+        };
+        self.channel_outputs[channel] = (sample as i16 - 8) * volume;
+    }
+    fn clock_audio(&mut self) {
+        self.channel_divider += 1;
+        if self.channel_divider < CYCLES_PER_CHANNEL {
+            return;
+        }
+        self.channel_divider = 0;
+        let first_channel = 8 - self.enabled_channels();
+        self.current_channel = if self.current_channel <= first_channel {
+            7
+        } else {
+            self.current_channel - 1
+        };
+        self.update_channel(self.current_channel);
+    }
+}
+
+impl Mapper for Namco163 {
+    fn cpu_peek(&self, address: u16) -> Option<u8> {
+        match address {
+            0x4800..=0x4fff => Some(self.sound_ram[(self.sound_address & 0x7f) as usize]),
+            0x5000..=0x57ff => Some(self.irq_counter as u8),
+            0x5800..=0x5fff => Some((self.irq_counter >> 8) as u8),
+            PRG_RAM_START..=0x7fff if !self.prg_ram.is_empty() => {
+                let offset = (address - PRG_RAM_START) as usize;
+                Some(self.prg_ram[offset % self.prg_ram.len()])
+            }
+            PRG_ROM_START..=0xffff => Some(self.prg_rom[self.prg_rom_index(address)]),
+            _ => None,
+        }
+    }
+    fn cpu_read(&mut self, address: u16) -> Option<u8> {
+        let data = self.cpu_peek(address);
+        if let 0x4800..=0x4fff = address {
+            self.advance_sound_address();
+        }
+        data
+    }
+    fn cpu_write(&mut self, address: u16, data: u8) {
+        match address {
+            0x4800..=0x4fff => {
+                self.sound_ram[(self.sound_address & 0x7f) as usize] = data;
+                self.advance_sound_address();
+            }
+            // writing either half of the counter acknowledges the IRQ
+            0x5000..=0x57ff => {
+                self.irq_counter = (self.irq_counter & 0xff00) | data as u16;
+                self.irq_pending = false;
+            }
+            0x5800..=0x5fff => {
+                self.irq_counter = (self.irq_counter & 0x00ff) | (data as u16) << 8;
+                self.irq_pending = false;
+            }
+            PRG_RAM_START..=0x7fff
+                if !self.prg_ram.is_empty() && self.is_prg_ram_writable(address) =>
+            {
+                let index = (address - PRG_RAM_START) as usize % self.prg_ram.len();
+                self.prg_ram[index] = data;
+            }
+            0x8000..=0xdfff => self.chr_banks[((address - 0x8000) / 0x0800) as usize] = data,
+            0xe000..=0xe7ff => self.prg_banks[0] = data,
+            0xe800..=0xefff => self.prg_banks[1] = data & PRG_BANK_MASK,
+            0xf000..=0xf7ff => self.prg_banks[2] = data & PRG_BANK_MASK,
+            // the same register holds the PRG-RAM protection and the sound RAM address
+            0xf800..=0xffff => {
+                self.write_protect = data;
+                self.sound_address = data;
+            }
+            _ => {}
+        }
+    }
+    fn ppu_peek(&self, address: u16) -> u8 {
+        self.chr[self.chr_index(address)]
+    }
+    fn ppu_write(&mut self, address: u16, data: u8) {
+        if self.chr_is_ram {
+            let index = self.chr_index(address);
+            self.chr[index] = data;
+        }
+    }
+    // the nametable registers decide everything, this only matters for their VRAM pages
+    fn mirroring(&self) -> Mirroring {
+        Mirroring::Vertical
+    }
+    fn nametable_page(&self, address: u16) -> usize {
+        (self.nametable_bank(address) & 1) as usize
+    }
+    fn nametable_peek(&self, address: u16) -> Option<u8> {
+        let bank = self.nametable_bank(address);
+        if bank >= CIRAM_BANKS_START {
+            return None;
+        }
+        let index = banked_index(&self.chr, bank as usize, CHR_BANK_SIZE, address as usize);
+        Some(self.chr[index])
+    }
+    fn nametable_write(&mut self, address: u16, _data: u8) -> bool {
+        self.nametable_bank(address) < CIRAM_BANKS_START
+    }
+    fn audio_output(&self) -> f32 {
+        if self.prg_banks[0] & SOUND_DISABLE_BIT == SOUND_DISABLE_BIT {
+            return 0.0;
+        }
+        let level = match self.mixing {
+            Namco163Mixing::Multiplexed => self.channel_outputs[self.current_channel] as f32,
+            Namco163Mixing::Averaged => {
+                let enabled = self.enabled_channels();
+                let sum: i16 = self.channel_outputs[8 - enabled..].iter().sum();
+                sum as f32 / enabled as f32
+            }
+        };
+        level * OUTPUT_STEP_LEVEL
+    }
+    fn irq_asserted(&self) -> bool {
+        self.irq_pending
+    }
+    // the counter counts up to $7FFF and stays there
+    fn cpu_clock(&mut self) {
+        if self.irq_counter & IRQ_ENABLE_BIT == IRQ_ENABLE_BIT
+            && self.irq_counter & IRQ_COUNTER_MAX != IRQ_COUNTER_MAX
+        {
+            self.irq_counter += 1;
+            if self.irq_counter & IRQ_COUNTER_MAX == IRQ_COUNTER_MAX {
+                self.irq_pending = true;
+            }
+        }
+        if self.prg_banks[0] & SOUND_DISABLE_BIT == 0 {
+            self.clock_audio();
+        }
+    }
+    // the wavetables live in the sound RAM, so it is saved behind PRG-RAM
+    fn battery_ram(&self) -> Vec<u8> {
+        if !self.has_battery {
+            return Vec::new();
+        }
+        let mut data = self.prg_ram.clone();
+        data.extend_from_slice(&self.sound_ram);
+        data
+    }
+    fn load_battery_ram(&mut self, data: &[u8]) {
+        if !self.has_battery || data.len() != self.prg_ram.len() + SOUND_RAM_SIZE {
+            return;
+        }
+        let (prg_ram, sound_ram) = data.split_at(self.prg_ram.len());
+        self.prg_ram.copy_from_slice(prg_ram);
+        self.sound_ram.copy_from_slice(sound_ram);
+    }
+}
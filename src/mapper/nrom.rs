@@ -0,0 +1,63 @@
+use super::*;
+
+// mapper 0: NROM-128 (16 KiB mirrored at $C000) and NROM-256, no bank switching
+pub struct Nrom {
+    prg_rom: Vec<u8>,
+    prg_ram: Vec<u8>,
+    has_battery: bool,
+    chr: Vec<u8>,
+    chr_is_ram: bool,
+    mirroring: Mirroring,
+}
+
+impl Nrom {
+    pub fn new(mut cartridge: Cartridge) -> Nrom {
+        let (chr, chr_is_ram) = chr_memory(&mut cartridge);
+        Nrom {
+            prg_ram: prg_ram(&cartridge),
+            has_battery: cartridge.has_battery,
+            prg_rom: cartridge.prg_rom,
+            chr,
+            chr_is_ram,
+            mirroring: cartridge.mirroring,
+        }
+    }
+}
+
+impl Mapper for Nrom {
+    fn cpu_peek(&self, address: u16) -> Option<u8> {
+        match address {
+            PRG_RAM_START..=0x7fff if !self.prg_ram.is_empty() => {
+                Some(self.prg_ram[(address - PRG_RAM_START) as usize % self.prg_ram.len()])
+            }
+            PRG_ROM_START..=0xffff => {
+                Some(self.prg_rom[(address - PRG_ROM_START) as usize % self.prg_rom.len()])
+            }
+            _ => None,
+        }
+    }
+    fn cpu_write(&mut self, address: u16, data: u8) {
+        if (PRG_RAM_START..=0x7fff).contains(&address) && !self.prg_ram.is_empty() {
+            let index = (address - PRG_RAM_START) as usize % self.prg_ram.len();
+            self.prg_ram[index] = data;
+        }
+    }
+    fn ppu_peek(&self, address: u16) -> u8 {
+        self.chr[address as usize % self.chr.len()]
+    }
+    fn ppu_write(&mut self, address: u16, data: u8) {
+        if self.chr_is_ram {
+            let index = address as usize % self.chr.len();
+            self.chr[index] = data;
+        }
+    }
+    fn mirroring(&self) -> Mirroring {
+        self.mirroring
+    }
+    fn battery_ram(&self) -> Vec<u8> {
+        battery_prg_ram(&self.prg_ram, self.has_battery)
+    }
+    fn load_battery_ram(&mut self, data: &[u8]) {
+        load_battery_prg_ram(&mut self.prg_ram, self.has_battery, data);
+    }
+}
@@ -0,0 +1,393 @@
+use std::f32::consts::TAU;
+
+// the FM core of the VRC7: a YM2413 (OPLL) cut down to 6 channels, without rhythm mode
+// and with its own set of built-in instruments. Envelope and LFO timings are approximations.
+
+const CHANNELS: usize = 6;
+// 3.58 MHz / 72 on the chip, every 36 CPU cycles on an NTSC console: 49716 Hz
+const CPU_CYCLES_PER_SAMPLE: u8 = 36;
+// attenuation is tracked in 0.375 dB steps, the envelope covers 127 of them (~48 dB)
+const DB_PER_STEP: f32 = 0.375;
+const ENVELOPE_SILENT: u16 = 127;
+const ENVELOPE_COUNTER_OVERFLOW: u32 = 1 << 16;
+const KEY_ON_BIT: u8 = 0b0001_0000;
+const SUSTAIN_BIT: u8 = 0b0010_0000;
+const AM_BIT: u8 = 0b1000_0000;
+const VIBRATO_BIT: u8 = 0b0100_0000;
+const SUSTAINED_ENVELOPE_BIT: u8 = 0b0010_0000;
+const KEY_SCALE_RATE_BIT: u8 = 0b0001_0000;
+// 8 vibrato steps of 1024 samples: ~6.1 Hz, a little under +-14 cents
+const VIBRATO_SHAPE: [f32; 8] = [0.0, 0.5, 1.0, 0.5, 0.0, -0.5, -1.0, -0.5];
+const VIBRATO_DEPTH: f32 = 0.008;
+const VIBRATO_STEP_SAMPLES: u32 = 1024;
+// ~3.7 Hz triangle, 4.8 dB deep
+const TREMOLO_PERIOD_SAMPLES: u32 = 13_436;
+const TREMOLO_DEPTH: f32 = 13.0;
+// how far (in sine periods) a full scale modulator pushes the carrier's phase
+const MODULATION_DEPTH: f32 = 1.0;
+// the DAC idles at mid-scale, all 6 channels at full volume swing it to either end
+const OUTPUT_MIDPOINT: f32 = 0.5;
+const CHANNEL_OUTPUT_SCALE: f32 = OUTPUT_MIDPOINT / CHANNELS as f32;
+
+// frequency multipliers, doubled so the 0.5 fits
+const MULTIPLIERS_X2: [f32; 16] = [
+    1.0, 2.0, 4.0, 6.0, 8.0, 10.0, 12.0, 14.0, 16.0, 18.0, 20.0, 20.0, 24.0, 24.0, 30.0, 30.0,
+];
+// key scale level at block 7 in 0.75 dB, indexed by the upper 4 bits of the F-number
+const KEY_SCALE_LEVELS: [i16; 16] = [
+    0, 24, 32, 37, 40, 43, 45, 47, 48, 50, 51, 52, 53, 54, 55, 56,
+];
+// the VRC7's built-in instruments 1-15, instrument 0 is the custom one from registers $00-$07
+const INSTRUMENTS: [[u8; 8]; 15] = [
+    [0x03, 0x21, 0x05, 0x06, 0xe8, 0x81, 0x42, 0x27],
+    [0x13, 0x41, 0x14, 0x0d, 0xd8, 0xf6, 0x23, 0x12],
+    [0x11, 0x11, 0x08, 0x08, 0xfa, 0xb2, 0x20, 0x12],
+    [0x31, 0x61, 0x0c, 0x07, 0xa8, 0x64, 0x61, 0x27],
+    [0x32, 0x21, 0x1e, 0x06, 0xe1, 0x76, 0x01, 0x28],
+    [0x02, 0x01, 0x06, 0x00, 0xa3, 0xe2, 0xf4, 0xf4],
+    [0x21, 0x61, 0x1d, 0x07, 0x82, 0x81, 0x11, 0x07],
+    [0x23, 0x21, 0x22, 0x17, 0xa2, 0x72, 0x01, 0x17],
+    [0x35, 0x11, 0x25, 0x00, 0x40, 0x73, 0x72, 0x01],
+    [0xb5, 0x01, 0x0f, 0x0f, 0xa8, 0xa5, 0x51, 0x02],
+    [0x17, 0xc1, 0x24, 0x07, 0xf8, 0xf8, 0x22, 0x12],
+    [0x71, 0x23, 0x11, 0x06, 0x65, 0x74, 0x18, 0x16],
+    [0x01, 0x02, 0xd3, 0x05, 0xc9, 0x95, 0x03, 0x02],
+    [0x61, 0x63, 0x0c, 0x00, 0x94, 0xc0, 0x33, 0xf6],
+    [0x21, 0x72, 0x0d, 0x00, 0xc1, 0xd5, 0x56, 0x06],
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+enum EnvelopeState {
+    Attack,
+    Decay,
+    Sustain,
+    Release,
+    #[default]
+    Off,
+}
+
+// one half of a channel's patch: byte 0/1 (flags and multiplier), the shared bytes 2/3,
+// then 4/5 (attack and decay) and 6/7 (sustain level and release)
+#[derive(Debug, Clone, Copy)]
+struct OperatorPatch {
+    flags: u8,
+    multiplier: usize,
+    key_scale_level: u8,
+    is_rectified: bool,
+    attack_rate: u8,
+    decay_rate: u8,
+    sustain_level: u16,
+    release_rate: u8,
+}
+
+impl OperatorPatch {
+    fn from_instrument(instrument: &[u8; 8], operator: usize) -> OperatorPatch {
+        let rectified_bit = if operator == 0 {
+            0b0000_1000
+        } else {
+            0b0001_0000
+        };
+        OperatorPatch {
+            flags: instrument[operator],
+            multiplier: (instrument[operator] & 0x0f) as usize,
+            key_scale_level: instrument[2 + operator] >> 6,
+            is_rectified: instrument[3] & rectified_bit == rectified_bit,
+            attack_rate: instrument[4 + operator] >> 4,
+            decay_rate: instrument[4 + operator] & 0x0f,
+            sustain_level: (instrument[6 + operator] >> 4) as u16,
+            release_rate: instrument[6 + operator] & 0x0f,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+struct Operator {
+    // position in the sine period, 0.0 - 1.0
+    phase: f32,
+    state: EnvelopeState,
+    attenuation: u16,
+    envelope_counter: u32,
+    output: f32,
+    previous_output: f32,
+}
+
+impl Operator {
+    fn key_on(&mut self) {
+        if self.state == EnvelopeState::Off {
+            self.attenuation = ENVELOPE_SILENT;
+        }
+        self.phase = 0.0;
+        self.state = EnvelopeState::Attack;
+        self.envelope_counter = 0;
+    }
+    fn key_off(&mut self) {
+        if self.state != EnvelopeState::Off {
+            self.state = EnvelopeState::Release;
+        }
+    }
+    fn clock_envelope(&mut self, patch: &OperatorPatch, key_scale_rate: u8, channel_sustain: bool) {
+        let rate = match self.state {
+            EnvelopeState::Attack => patch.attack_rate,
+            EnvelopeState::Decay => patch.decay_rate,
+            EnvelopeState::Sustain if patch.flags & SUSTAINED_ENVELOPE_BIT != 0 => 0,
+            EnvelopeState::Sustain => patch.release_rate,
+            EnvelopeState::Release if channel_sustain => 5,
+            EnvelopeState::Release if patch.flags & SUSTAINED_ENVELOPE_BIT != 0 => {
+                patch.release_rate
+            }
+            EnvelopeState::Release => 7,
+            EnvelopeState::Off => 0,
+        };
+        let rate = if rate == 0 {
+            0
+        } else {
+            (rate * 4 + key_scale_rate).min(63)
+        };
+        let steps = if rate == 0 {
+            0
+        } else {
+            self.envelope_counter += (4 + (rate as u32 & 0b11)) << (rate / 4);
+            let steps = self.envelope_counter / ENVELOPE_COUNTER_OVERFLOW;
+            self.envelope_counter %= ENVELOPE_COUNTER_OVERFLOW;
+            steps as u16
+        };
+        match self.state {
+            EnvelopeState::Attack => {
+                if rate >= 60 {
+                    self.attenuation = 0;
+                }
+                for _ in 0..steps {
+                    self.attenuation = self.attenuation.saturating_sub((self.attenuation >> 3) + 1);
+                }
+                if self.attenuation == 0 {
+                    self.state = EnvelopeState::Decay;
+                }
+            }
+            EnvelopeState::Decay => {
+                self.attenuation = (self.attenuation + steps).min(ENVELOPE_SILENT);
+                // sustain levels are 3 dB apart
+                if self.attenuation >= patch.sustain_level * 8 {
+                    self.state = EnvelopeState::Sustain;
+                }
+            }
+            EnvelopeState::Sustain | EnvelopeState::Release => {
+                self.attenuation = (self.attenuation + steps).min(ENVELOPE_SILENT);
+                if self.attenuation == ENVELOPE_SILENT {
+                    self.state = EnvelopeState::Off;
+                }
+            }
+            EnvelopeState::Off => self.attenuation = ENVELOPE_SILENT,
+        }
+    }
+    // extra attenuation in 0.375 dB steps, the phase increment in sine periods per sample
+    fn render(
+        &mut self,
+        phase_increment: f32,
+        modulation: f32,
+        attenuation: f32,
+        is_rectified: bool,
+    ) {
+        self.phase = (self.phase + phase_increment).fract();
+        let total = self.attenuation as f32 + attenuation;
+        let wave = (TAU * (self.phase + modulation)).sin();
+        let wave = if is_rectified && wave < 0.0 {
+            0.0
+        } else {
+            wave
+        };
+        self.previous_output = self.output;
+        self.output = if self.state == EnvelopeState::Off {
+            0.0
+        } else {
+            wave * 10f32.powf(-total * DB_PER_STEP / 20.0)
+        };
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+struct Channel {
+    f_number: u16,
+    block: u8,
+    key_on: bool,
+    sustain: bool,
+    instrument: usize,
+    volume: u8,
+    modulator: Operator,
+    carrier: Operator,
+}
+
+impl Channel {
+    fn key_scale_rate(&self, patch: &OperatorPatch) -> u8 {
+        let rate = (self.block << 1) | (self.f_number >> 8) as u8;
+        if patch.flags & KEY_SCALE_RATE_BIT == KEY_SCALE_RATE_BIT {
+            rate
+        } else {
+            rate >> 2
+        }
+    }
+    fn key_scale_attenuation(&self, patch: &OperatorPatch) -> f32 {
+        if patch.key_scale_level == 0 {
+            return 0.0;
+        }
+        let level = KEY_SCALE_LEVELS[(self.f_number >> 5) as usize] - 8 * (7 - self.block as i16);
+        // the table is 6 dB per octave, key scale levels 1-3 are 1.5, 3 and 6 dB per octave
+        ((level.max(0) * 2) >> (3 - patch.key_scale_level)) as f32
+    }
+    fn phase_increment(&self, patch: &OperatorPatch, vibrato: f32) -> f32 {
+        let f_number = if patch.flags & VIBRATO_BIT == VIBRATO_BIT {
+            self.f_number as f32 * (1.0 + vibrato * VIBRATO_DEPTH)
+        } else {
+            self.f_number as f32
+        };
+        // f = 49716 Hz * fnum * 2^(block - 1) / 2^18
+        f_number * (1u32 << self.block) as f32 * MULTIPLIERS_X2[patch.multiplier] / (1 << 20) as f32
+    }
+}
+
+pub struct Opll {
+    address: u8,
+    custom_instrument: [u8; 8],
+    channels: [Channel; CHANNELS],
+    cycles: u8,
+    samples: u32,
+    output: f32,
+}
+
+impl Opll {
+    pub fn new() -> Opll {
+        Opll {
+            address: 0,
+            custom_instrument: [0; 8],
+            channels: Default::default(),
+            cycles: 0,
+            samples: 0,
+            output: 0.0,
+        }
+    }
+    pub fn reset(&mut self) {
+        *self = Opll::new();
+    }
+    pub fn write_address(&mut self, data: u8) {
+        self.address = data;
+    }
+    pub fn write_data(&mut self, data: u8) {
+        let channel = (self.address & 0x0f) as usize;
+        match self.address {
+            0x00..=0x07 => self.custom_instrument[self.address as usize] = data,
+            0x10..=0x15 => {
+                let channel = &mut self.channels[channel];
+                channel.f_number = (channel.f_number & 0x100) | data as u16;
+            }
+            0x20..=0x25 => {
+                let channel = &mut self.channels[channel];
+                channel.f_number = (channel.f_number & 0xff) | ((data & 1) as u16) << 8;
+                channel.block = (data >> 1) & 0b111;
+                channel.sustain = data & SUSTAIN_BIT == SUSTAIN_BIT;
+                let key_on = data & KEY_ON_BIT == KEY_ON_BIT;
+                if key_on && !channel.key_on {
+                    channel.modulator.key_on();
+                    channel.carrier.key_on();
+                } else if !key_on && channel.key_on {
+                    channel.modulator.key_off();
+                    channel.carrier.key_off();
+                }
+                channel.key_on = key_on;
+            }
+            0x30..=0x35 => {
+                let channel = &mut self.channels[channel];
+                channel.instrument = (data >> 4) as usize;
+                channel.volume = data & 0x0f;
+            }
+            _ => {}
+        }
+    }
+    pub fn cpu_clock(&mut self) {
+        self.cycles += 1;
+        if self.cycles == CPU_CYCLES_PER_SAMPLE {
+            self.cycles = 0;
+            self.render_sample();
+        }
+    }
+    pub fn output(&self) -> f32 {
+        OUTPUT_MIDPOINT + self.output * CHANNEL_OUTPUT_SCALE
+    }
+
+    fn instrument(&self, instrument: usize) -> [u8; 8] {
+        if instrument == 0 {
+            self.custom_instrument
+        } else {
+            INSTRUMENTS[instrument - 1]
+        }
+    }
+    fn render_sample(&mut self) {
+        self.samples = self.samples.wrapping_add(1);
+        let vibrato = VIBRATO_SHAPE[(self.samples / VIBRATO_STEP_SAMPLES) as usize % 8];
+        let tremolo_phase =
+            (self.samples % TREMOLO_PERIOD_SAMPLES) as f32 / TREMOLO_PERIOD_SAMPLES as f32;
+        let tremolo = (1.0 - (2.0 * tremolo_phase - 1.0).abs()) * TREMOLO_DEPTH;
+        let mut output = 0.0;
+        for index in 0..CHANNELS {
+            let instrument = self.instrument(self.channels[index].instrument);
+            let channel = &mut self.channels[index];
+            let modulator_patch = OperatorPatch::from_instrument(&instrument, 0);
+            let carrier_patch = OperatorPatch::from_instrument(&instrument, 1);
+            let sustain = channel.sustain;
+            let rate = channel.key_scale_rate(&modulator_patch);
+            channel
+                .modulator
+                .clock_envelope(&modulator_patch, rate, sustain);
+            let rate = channel.key_scale_rate(&carrier_patch);
+            channel
+                .carrier
+                .clock_envelope(&carrier_patch, rate, sustain);
+
+            let am = |patch: &OperatorPatch| {
+                if patch.flags & AM_BIT == AM_BIT {
+                    tremolo
+                } else {
+                    0.0
+                }
+            };
+            // feedback 1-7 is pi/16 to 4 pi of phase modulation
+            let feedback = instrument[3] & 0b111;
+            let feedback_modulation = if feedback == 0 {
+                0.0
+            } else {
+                (channel.modulator.output + channel.modulator.previous_output) / 2.0
+                    * (1 << feedback) as f32
+                    / 64.0
+            };
+            // the modulator's total level is in 0.75 dB steps
+            let modulator_attenuation = (instrument[2] & 0x3f) as f32 * 2.0
+                + channel.key_scale_attenuation(&modulator_patch)
+                + am(&modulator_patch);
+            let increment = channel.phase_increment(&modulator_patch, vibrato);
+            channel.modulator.render(
+                increment,
+                feedback_modulation,
+                modulator_attenuation,
+                modulator_patch.is_rectified,
+            );
+            // the channel volume is in 3 dB steps
+            let carrier_attenuation = channel.volume as f32 * 8.0
+                + channel.key_scale_attenuation(&carrier_patch)
+                + am(&carrier_patch);
+            let increment = channel.phase_increment(&carrier_patch, vibrato);
+            let modulation = channel.modulator.output * MODULATION_DEPTH;
+            channel.carrier.render(
+                increment,
+                modulation,
+                carrier_attenuation,
+                carrier_patch.is_rectified,
+            );
+            output += channel.carrier.output;
+        }
+        self.output = output;
+    }
+}
+
+impl Default for Opll {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,59 @@
+use super::*;
+
+const PRG_BANK_SIZE: usize = 0x4000;
+
+// mapper 2: 16 KiB switchable at $8000, the last bank fixed at $C000
+pub struct Uxrom {
+    prg_rom: Vec<u8>,
+    chr: Vec<u8>,
+    chr_is_ram: bool,
+    mirroring: Mirroring,
+    bus_conflicts: bool,
+    prg_bank: u8,
+}
+
+impl Uxrom {
+    pub fn new(mut cartridge: Cartridge) -> Uxrom {
+        let (chr, chr_is_ram) = chr_memory(&mut cartridge);
+        Uxrom {
+            bus_conflicts: has_bus_conflicts(&cartridge),
+            prg_rom: cartridge.prg_rom,
+            chr,
+            chr_is_ram,
+            mirroring: cartridge.mirroring,
+            prg_bank: 0,
+        }
+    }
+}
+
+impl Mapper for Uxrom {
+    fn cpu_peek(&self, address: u16) -> Option<u8> {
+        let offset = address.checked_sub(PRG_ROM_START)? as usize;
+        let bank = if address >= 0xc000 {
+            bank_count(&self.prg_rom, PRG_BANK_SIZE) - 1
+        } else {
+            self.prg_bank as usize
+        };
+        Some(self.prg_rom[banked_index(&self.prg_rom, bank, PRG_BANK_SIZE, offset)])
+    }
+    fn cpu_write(&mut self, address: u16, data: u8) {
+        if address >= PRG_ROM_START {
+            self.prg_bank = written_value(self, address, data);
+        }
+    }
+    fn ppu_peek(&self, address: u16) -> u8 {
+        self.chr[address as usize % self.chr.len()]
+    }
+    fn ppu_write(&mut self, address: u16, data: u8) {
+        if self.chr_is_ram {
+            let index = address as usize % self.chr.len();
+            self.chr[index] = data;
+        }
+    }
+    fn mirroring(&self) -> Mirroring {
+        self.mirroring
+    }
+    fn has_bus_conflicts(&self) -> bool {
+        self.bus_conflicts
+    }
+}
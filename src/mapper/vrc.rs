@@ -0,0 +1,103 @@
+// pieces shared by the Konami VRC boards
+
+const IRQ_ENABLE_AFTER_ACK_BIT: u8 = 0b001;
+const IRQ_ENABLE_BIT: u8 = 0b010;
+const IRQ_CYCLE_MODE_BIT: u8 = 0b100;
+// scanline mode divides the CPU clock by 113.667: 3 per CPU cycle out of 341 per scanline
+const PRESCALER_PERIOD: i16 = 341;
+const PRESCALER_STEP: i16 = 3;
+
+// the two CPU address lines a board connects to the chip's register select pins
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RegisterWiring {
+    pub low_mask: u16,
+    pub high_mask: u16,
+}
+
+impl RegisterWiring {
+    pub const fn new(low_line: u8, high_line: u8) -> RegisterWiring {
+        RegisterWiring {
+            low_mask: 1 << low_line,
+            high_mask: 1 << high_line,
+        }
+    }
+    // boards without a submapper are decoded with both candidate wirings OR'd together
+    pub const fn either(first: RegisterWiring, second: RegisterWiring) -> RegisterWiring {
+        RegisterWiring {
+            low_mask: first.low_mask | second.low_mask,
+            high_mask: first.high_mask | second.high_mask,
+        }
+    }
+    // $x000-$x003 as the chip sees it
+    pub fn register(&self, address: u16) -> u16 {
+        let low = (address & self.low_mask != 0) as u16;
+        let high = (address & self.high_mask != 0) as u16;
+        (address & 0xf000) | high << 1 | low
+    }
+}
+
+// the VRC4/VRC6/VRC7 IRQ: an 8-bit up counter clocked by CPU cycles or a scanline prescaler
+#[derive(Debug, Clone, Default)]
+pub struct VrcIrq {
+    latch: u8,
+    control: u8,
+    counter: u8,
+    prescaler: i16,
+    pending: bool,
+}
+
+impl VrcIrq {
+    pub fn new() -> VrcIrq {
+        VrcIrq::default()
+    }
+    pub fn write_latch(&mut self, data: u8) {
+        self.latch = data;
+    }
+    // VRC4 loads the latch one nibble at a time
+    pub fn write_latch_nibble(&mut self, high: bool, data: u8) {
+        self.latch = if high {
+            (self.latch & 0x0f) | (data & 0x0f) << 4
+        } else {
+            (self.latch & 0xf0) | (data & 0x0f)
+        };
+    }
+    pub fn write_control(&mut self, data: u8) {
+        self.control = data;
+        self.pending = false;
+        if data & IRQ_ENABLE_BIT == IRQ_ENABLE_BIT {
+            self.counter = self.latch;
+            self.prescaler = PRESCALER_PERIOD;
+        }
+    }
+    pub fn acknowledge(&mut self) {
+        self.pending = false;
+        let enable_after_ack = self.control & IRQ_ENABLE_AFTER_ACK_BIT == IRQ_ENABLE_AFTER_ACK_BIT;
+        self.control = (self.control & !IRQ_ENABLE_BIT) | (enable_after_ack as u8) << 1;
+    }
+    pub fn is_pending(&self) -> bool {
+        self.pending
+    }
+    pub fn cpu_clock(&mut self) {
+        if self.control & IRQ_ENABLE_BIT == 0 {
+            return;
+        }
+        if self.control & IRQ_CYCLE_MODE_BIT == IRQ_CYCLE_MODE_BIT {
+            self.clock_counter();
+        } else {
+            self.prescaler -= PRESCALER_STEP;
+            if self.prescaler <= 0 {
+                self.prescaler += PRESCALER_PERIOD;
+                self.clock_counter();
+            }
+        }
+    }
+
+    fn clock_counter(&mut self) {
+        if self.counter == 0xff {
+            self.counter = self.latch;
+            self.pending = true;
+        } else {
+            self.counter += 1;
+        }
+    }
+}
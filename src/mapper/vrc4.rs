@@ -0,0 +1,177 @@
+use super::vrc::*;
+use super::*;
+
+const PRG_BANK_SIZE: usize = 0x2000;
+const CHR_BANK_SIZE: usize = 0x0400;
+const PRG_BANK_MASK: u8 = 0b1_1111;
+const PRG_SWAP_BIT: u8 = 0b10;
+const VRC2_MICROWIRE_END: u16 = 0x6fff;
+
+const VRC2A: RegisterWiring = RegisterWiring::new(1, 0);
+const VRC2B: RegisterWiring = RegisterWiring::new(0, 1);
+const VRC2C: RegisterWiring = RegisterWiring::new(1, 0);
+const VRC4A: RegisterWiring = RegisterWiring::new(1, 2);
+const VRC4B: RegisterWiring = RegisterWiring::new(1, 0);
+const VRC4C: RegisterWiring = RegisterWiring::new(6, 7);
+const VRC4D: RegisterWiring = RegisterWiring::new(3, 2);
+const VRC4E: RegisterWiring = RegisterWiring::new(2, 3);
+const VRC4F: RegisterWiring = RegisterWiring::new(0, 1);
+
+// mappers 21, 22, 23 and 25: VRC2 and VRC4, which only differ in how the board wires them
+pub struct Vrc4 {
+    prg_rom: Vec<u8>,
+    prg_ram: Vec<u8>,
+    has_battery: bool,
+    chr: Vec<u8>,
+    chr_is_ram: bool,
+    wiring: RegisterWiring,
+    is_vrc2: bool,
+    // VRC2a leaves CHR A10 unconnected, the bank registers count in 2 KiB
+    chr_bank_shift: u8,
+    prg_banks: [u8; 2],
+    prg_swap_mode: u8,
+    chr_banks: [u16; 8],
+    mirroring: u8,
+    // VRC2 boards without PRG-RAM answer $6000 with a single latched bit
+    microwire_latch: u8,
+    irq: VrcIrq,
+}
+
+impl Vrc4 {
+    pub fn new(mut cartridge: Cartridge) -> Vrc4 {
+        let (wiring, is_vrc2) = match (cartridge.mapper, cartridge.submapper) {
+            (21, 1) => (VRC4A, false),
+            (21, 2) => (VRC4C, false),
+            (21, _) => (RegisterWiring::either(VRC4A, VRC4C), false),
+            (22, _) => (VRC2A, true),
+            (23, 1) => (VRC4F, false),
+            (23, 2) => (VRC4E, false),
+            (23, 3) => (VRC2B, true),
+            (23, _) => (RegisterWiring::either(VRC4F, VRC4E), false),
+            (_, 1) => (VRC4B, false),
+            (_, 2) => (VRC4D, false),
+            (_, 3) => (VRC2C, true),
+            _ => (RegisterWiring::either(VRC4B, VRC4D), false),
+        };
+        let (chr, chr_is_ram) = chr_memory(&mut cartridge);
+        Vrc4 {
+            prg_ram: prg_ram(&cartridge),
+            has_battery: cartridge.has_battery,
+            prg_rom: cartridge.prg_rom,
+            chr,
+            chr_is_ram,
+            wiring,
+            is_vrc2,
+            chr_bank_shift: (cartridge.mapper == 22) as u8,
+            prg_banks: [0, 0],
+            prg_swap_mode: 0,
+            chr_banks: [0; 8],
+            mirroring: 0,
+            microwire_latch: 0,
+            irq: VrcIrq::new(),
+        }
+    }
+
+    fn write_register(&mut self, address: u16, data: u8) {
+        let register = self.wiring.register(address);
+        match register {
+            0x8000..=0x8003 => self.prg_banks[0] = data & PRG_BANK_MASK,
+            0xa000..=0xa003 => self.prg_banks[1] = data & PRG_BANK_MASK,
+            0x9000..=0x9003 if self.is_vrc2 => self.mirroring = data & 0b01,
+            0x9000 | 0x9001 => self.mirroring = data & 0b11,
+            0x9002 => self.prg_swap_mode = data & PRG_SWAP_BIT,
+            0xb000..=0xe003 => {
+                // each bank register is split into a low and a high nibble
+                let bank = ((register >> 12) - 0xb) as usize * 2 + ((register >> 1) & 1) as usize;
+                let high_mask = if self.is_vrc2 { 0x0f } else { 0x1f };
+                self.chr_banks[bank] = if register & 1 == 1 {
+                    (self.chr_banks[bank] & 0x0f) | ((data & high_mask) as u16) << 4
+                } else {
+                    (self.chr_banks[bank] & !0x0f) | (data & 0x0f) as u16
+                };
+            }
+            _ if self.is_vrc2 => {}
+            0xf000 => self.irq.write_latch_nibble(false, data),
+            0xf001 => self.irq.write_latch_nibble(true, data),
+            0xf002 => self.irq.write_control(data),
+            0xf003 => self.irq.acknowledge(),
+            _ => {}
+        }
+    }
+
+    fn prg_rom_index(&self, address: u16) -> usize {
+        let second_last = bank_count(&self.prg_rom, PRG_BANK_SIZE).wrapping_sub(2);
+        let slot = ((address - PRG_ROM_START) as usize) / PRG_BANK_SIZE;
+        let swapped = self.prg_swap_mode == PRG_SWAP_BIT;
+        let bank = match (slot, swapped) {
+            (0, false) | (2, true) => self.prg_banks[0] as usize,
+            (0, true) | (2, false) => second_last,
+            (1, _) => self.prg_banks[1] as usize,
+            _ => second_last.wrapping_add(1),
+        };
+        banked_index(
+            &self.prg_rom,
+            bank,
+            PRG_BANK_SIZE,
+            (address - PRG_ROM_START) as usize,
+        )
+    }
+    fn chr_index(&self, address: u16) -> usize {
+        let bank = self.chr_banks[address as usize / CHR_BANK_SIZE] >> self.chr_bank_shift;
+        banked_index(&self.chr, bank as usize, CHR_BANK_SIZE, address as usize)
+    }
+}
+
+impl Mapper for Vrc4 {
+    fn cpu_peek(&self, address: u16) -> Option<u8> {
+        match address {
+            PRG_RAM_START..=0x7fff if !self.prg_ram.is_empty() => {
+                let offset = (address - PRG_RAM_START) as usize;
+                Some(self.prg_ram[offset % self.prg_ram.len()])
+            }
+            PRG_RAM_START..=VRC2_MICROWIRE_END if self.is_vrc2 => Some(self.microwire_latch),
+            PRG_ROM_START..=0xffff => Some(self.prg_rom[self.prg_rom_index(address)]),
+            _ => None,
+        }
+    }
+    fn cpu_write(&mut self, address: u16, data: u8) {
+        match address {
+            PRG_RAM_START..=0x7fff if !self.prg_ram.is_empty() => {
+                let index = (address - PRG_RAM_START) as usize % self.prg_ram.len();
+                self.prg_ram[index] = data;
+            }
+            PRG_RAM_START..=VRC2_MICROWIRE_END if self.is_vrc2 => self.microwire_latch = data & 1,
+            PRG_ROM_START..=0xffff => self.write_register(address, data),
+            _ => {}
+        }
+    }
+    fn ppu_peek(&self, address: u16) -> u8 {
+        self.chr[self.chr_index(address)]
+    }
+    fn ppu_write(&mut self, address: u16, data: u8) {
+        if self.chr_is_ram {
+            let index = self.chr_index(address);
+            self.chr[index] = data;
+        }
+    }
+    fn mirroring(&self) -> Mirroring {
+        match self.mirroring {
+            0 => Mirroring::Vertical,
+            1 => Mirroring::Horizontal,
+            2 => Mirroring::SingleScreenLower,
+            _ => Mirroring::SingleScreenUpper,
+        }
+    }
+    fn irq_asserted(&self) -> bool {
+        self.irq.is_pending()
+    }
+    fn cpu_clock(&mut self) {
+        self.irq.cpu_clock();
+    }
+    fn battery_ram(&self) -> Vec<u8> {
+        battery_prg_ram(&self.prg_ram, self.has_battery)
+    }
+    fn load_battery_ram(&mut self, data: &[u8]) {
+        load_battery_prg_ram(&mut self.prg_ram, self.has_battery, data);
+    }
+}
@@ -0,0 +1,287 @@
+use super::vrc::*;
+use super::*;
+
+const PRG_16K_BANK_SIZE: usize = 0x4000;
+const PRG_8K_BANK_SIZE: usize = 0x2000;
+const CHR_BANK_SIZE: usize = 0x0400;
+const PRG_RAM_ENABLE_BIT: u8 = 0b1000_0000;
+const CHANNEL_ENABLE_BIT: u8 = 0b1000_0000;
+const PULSE_IGNORE_DUTY_BIT: u8 = 0b1000_0000;
+const HALT_BIT: u8 = 0b001;
+const PERIOD_SHIFT_4_BIT: u8 = 0b010;
+const PERIOD_SHIFT_8_BIT: u8 = 0b100;
+// the saw adds its rate on every even step from 2 to 12 (6 additions) and resets on step 14
+const SAW_STEPS: u8 = 14;
+// one output step is about as loud as a step of an APU pulse
+const OUTPUT_STEP_LEVEL: f32 = 0.00752;
+
+const VRC6A: RegisterWiring = RegisterWiring::new(0, 1);
+const VRC6B: RegisterWiring = RegisterWiring::new(1, 0);
+
+#[derive(Default)]
+struct Vrc6Pulse {
+    volume: u8,
+    duty: u8,
+    ignore_duty: bool,
+    period: u16,
+    enabled: bool,
+    timer: u16,
+    step: u8,
+}
+
+impl Vrc6Pulse {
+    fn write(&mut self, register: u16, data: u8) {
+        match register {
+            0 => {
+                self.ignore_duty = data & PULSE_IGNORE_DUTY_BIT == PULSE_IGNORE_DUTY_BIT;
+                self.duty = (data >> 4) & 0b111;
+                self.volume = data & 0x0f;
+            }
+            1 => self.period = (self.period & 0x0f00) | data as u16,
+            _ => {
+                self.period = (self.period & 0x00ff) | ((data & 0x0f) as u16) << 8;
+                self.enabled = data & CHANNEL_ENABLE_BIT == CHANNEL_ENABLE_BIT;
+                if !self.enabled {
+                    self.step = 0;
+                }
+            }
+        }
+    }
+    fn clock(&mut self, period_shift: u8) {
+        if !self.enabled {
+            return;
+        }
+        if self.timer == 0 {
+            self.timer = self.period >> period_shift;
+            self.step = (self.step + 1) & 0x0f;
+        } else {
+            self.timer -= 1;
+        }
+    }
+    fn output(&self) -> u8 {
+        if self.enabled && (self.ignore_duty || self.step <= self.duty) {
+            self.volume
+        } else {
+            0
+        }
+    }
+}
+
+#[derive(Default)]
+struct Vrc6Saw {
+    rate: u8,
+    period: u16,
+    enabled: bool,
+    timer: u16,
+    step: u8,
+    accumulator: u8,
+}
+
+impl Vrc6Saw {
+    fn write(&mut self, register: u16, data: u8) {
+        match register {
+            0 => self.rate = data & 0b11_1111,
+            1 => self.period = (self.period & 0x0f00) | data as u16,
+            _ => {
+                self.period = (self.period & 0x00ff) | ((data & 0x0f) as u16) << 8;
+                self.enabled = data & CHANNEL_ENABLE_BIT == CHANNEL_ENABLE_BIT;
+                if !self.enabled {
+                    self.step = 0;
+                    self.accumulator = 0;
+                }
+            }
+        }
+    }
+    fn clock(&mut self, period_shift: u8) {
+        if !self.enabled {
+            return;
+        }
+        if self.timer > 0 {
+            self.timer -= 1;
+            return;
+        }
+        self.timer = self.period >> period_shift;
+        self.step += 1;
+        if self.step == SAW_STEPS {
+            self.step = 0;
+            self.accumulator = 0;
+        } else if self.step & 1 == 0 {
+            self.accumulator = self.accumulator.wrapping_add(self.rate);
+        }
+    }
+    // only the upper 5 bits reach the DAC
+    fn output(&self) -> u8 {
+        self.accumulator >> 3
+    }
+}
+
+// mappers 24 (VRC6a) and 26 (VRC6b, A0 and A1 swapped)
+pub struct Vrc6 {
+    prg_rom: Vec<u8>,
+    prg_ram: Vec<u8>,
+    has_battery: bool,
+    chr: Vec<u8>,
+    chr_is_ram: bool,
+    wiring: RegisterWiring,
+    prg_bank_16k: u8,
+    prg_bank_8k: u8,
+    chr_banks: [u8; 8],
+    banking_control: u8,
+    irq: VrcIrq,
+    frequency_control: u8,
+    pulses: [Vrc6Pulse; 2],
+    saw: Vrc6Saw,
+}
+
+impl Vrc6 {
+    pub fn new(mut cartridge: Cartridge) -> Vrc6 {
+        let (chr, chr_is_ram) = chr_memory(&mut cartridge);
+        Vrc6 {
+            prg_ram: prg_ram(&cartridge),
+            has_battery: cartridge.has_battery,
+            wiring: if cartridge.mapper == 26 { VRC6B } else { VRC6A },
+            prg_rom: cartridge.prg_rom,
+            chr,
+            chr_is_ram,
+            prg_bank_16k: 0,
+            prg_bank_8k: 0,
+            chr_banks: [0; 8],
+            banking_control: 0,
+            irq: VrcIrq::new(),
+            frequency_control: 0,
+            pulses: [Vrc6Pulse::default(), Vrc6Pulse::default()],
+            saw: Vrc6Saw::default(),
+        }
+    }
+
+    fn write_register(&mut self, address: u16, data: u8) {
+        let register = self.wiring.register(address);
+        match register {
+            0x8000..=0x8003 => self.prg_bank_16k = data & 0x0f,
+            0x9000..=0x9002 => self.pulses[0].write(register & 0b11, data),
+            0x9003 => self.frequency_control = data,
+            0xa000..=0xa002 => self.pulses[1].write(register & 0b11, data),
+            0xb000..=0xb002 => self.saw.write(register & 0b11, data),
+            0xb003 => self.banking_control = data,
+            0xc000..=0xc003 => self.prg_bank_8k = data & 0x1f,
+            0xd000..=0xe003 => {
+                let bank = ((register >> 12) - 0xd) as usize * 4 + (register & 0b11) as usize;
+                self.chr_banks[bank] = data;
+            }
+            0xf000 => self.irq.write_latch(data),
+            0xf001 => self.irq.write_control(data),
+            0xf002 => self.irq.acknowledge(),
+            _ => {}
+        }
+    }
+
+    fn prg_rom_index(&self, address: u16) -> usize {
+        let offset = (address - PRG_ROM_START) as usize;
+        match address {
+            0x8000..=0xbfff => banked_index(
+                &self.prg_rom,
+                self.prg_bank_16k as usize,
+                PRG_16K_BANK_SIZE,
+                offset,
+            ),
+            0xc000..=0xdfff => banked_index(
+                &self.prg_rom,
+                self.prg_bank_8k as usize,
+                PRG_8K_BANK_SIZE,
+                offset,
+            ),
+            _ => banked_index(
+                &self.prg_rom,
+                bank_count(&self.prg_rom, PRG_8K_BANK_SIZE) - 1,
+                PRG_8K_BANK_SIZE,
+                offset,
+            ),
+        }
+    }
+    // $B003 bits 0-1: 1 KiB banks, 2 KiB banks, or 1 KiB below $1000 and 2 KiB above
+    fn chr_index(&self, address: u16) -> usize {
+        let slot = address as usize / CHR_BANK_SIZE;
+        let two_kib = |register: usize| self.chr_banks[register] as usize * 2 + (slot & 1);
+        let bank = match (self.banking_control & 0b11, slot) {
+            (0, _) => self.chr_banks[slot] as usize,
+            (1, _) => two_kib(slot / 2),
+            (_, 0..=3) => self.chr_banks[slot] as usize,
+            (_, _) => two_kib(4 + (slot - 4) / 2),
+        };
+        banked_index(&self.chr, bank, CHR_BANK_SIZE, address as usize)
+    }
+    fn is_prg_ram_enabled(&self) -> bool {
+        !self.prg_ram.is_empty() && self.banking_control & PRG_RAM_ENABLE_BIT == PRG_RAM_ENABLE_BIT
+    }
+    fn period_shift(&self) -> u8 {
+        if self.frequency_control & PERIOD_SHIFT_8_BIT == PERIOD_SHIFT_8_BIT {
+            8
+        } else if self.frequency_control & PERIOD_SHIFT_4_BIT == PERIOD_SHIFT_4_BIT {
+            4
+        } else {
+            0
+        }
+    }
+}
+
+impl Mapper for Vrc6 {
+    fn cpu_peek(&self, address: u16) -> Option<u8> {
+        match address {
+            PRG_RAM_START..=0x7fff if self.is_prg_ram_enabled() => {
+                let offset = (address - PRG_RAM_START) as usize;
+                Some(self.prg_ram[offset % self.prg_ram.len()])
+            }
+            PRG_ROM_START..=0xffff => Some(self.prg_rom[self.prg_rom_index(address)]),
+            _ => None,
+        }
+    }
+    fn cpu_write(&mut self, address: u16, data: u8) {
+        match address {
+            PRG_RAM_START..=0x7fff if self.is_prg_ram_enabled() => {
+                let index = (address - PRG_RAM_START) as usize % self.prg_ram.len();
+                self.prg_ram[index] = data;
+            }
+            PRG_ROM_START..=0xffff => self.write_register(address, data),
+            _ => {}
+        }
+    }
+    fn ppu_peek(&self, address: u16) -> u8 {
+        self.chr[self.chr_index(address)]
+    }
+    fn ppu_write(&mut self, address: u16, data: u8) {
+        if self.chr_is_ram {
+            let index = self.chr_index(address);
+            self.chr[index] = data;
+        }
+    }
+    fn mirroring(&self) -> Mirroring {
+        match (self.banking_control >> 2) & 0b11 {
+            0 => Mirroring::Vertical,
+            1 => Mirroring::Horizontal,
+            2 => Mirroring::SingleScreenLower,
+            _ => Mirroring::SingleScreenUpper,
+        }
+    }
+    fn irq_asserted(&self) -> bool {
+        self.irq.is_pending()
+    }
+    fn cpu_clock(&mut self) {
+        self.irq.cpu_clock();
+        if self.frequency_control & HALT_BIT == 0 {
+            let period_shift = self.period_shift();
+            self.pulses[0].clock(period_shift);
+            self.pulses[1].clock(period_shift);
+            self.saw.clock(period_shift);
+        }
+    }
+    fn audio_output(&self) -> f32 {
+        let level = self.pulses[0].output() + self.pulses[1].output() + self.saw.output();
+        level as f32 * OUTPUT_STEP_LEVEL
+    }
+    fn battery_ram(&self) -> Vec<u8> {
+        battery_prg_ram(&self.prg_ram, self.has_battery)
+    }
+    fn load_battery_ram(&mut self, data: &[u8]) {
+        load_battery_prg_ram(&mut self.prg_ram, self.has_battery, data);
+    }
+}
@@ -0,0 +1,162 @@
+use super::opll::*;
+use super::vrc::*;
+use super::*;
+
+const PRG_BANK_SIZE: usize = 0x2000;
+const CHR_BANK_SIZE: usize = 0x0400;
+const PRG_BANK_MASK: u8 = 0b11_1111;
+const AUDIO_RESET_BIT: u8 = 0b0100_0000;
+const PRG_RAM_ENABLE_BIT: u8 = 0b1000_0000;
+// the second register of each pair is selected by A4 on VRC7a and A3 on VRC7b
+const VRC7A_SELECT: u16 = 0x0010;
+const VRC7B_SELECT: u16 = 0x0008;
+const AUDIO_ADDRESS_PORT: u16 = 0x9010;
+const AUDIO_DATA_PORT: u16 = 0x9030;
+
+// mapper 85: VRC7, with the OPLL FM synthesizer on VRC7a boards
+pub struct Vrc7 {
+    prg_rom: Vec<u8>,
+    prg_ram: Vec<u8>,
+    has_battery: bool,
+    chr: Vec<u8>,
+    chr_is_ram: bool,
+    select_mask: u16,
+    prg_banks: [u8; 3],
+    chr_banks: [u8; 8],
+    control: u8,
+    irq: VrcIrq,
+    opll: Opll,
+}
+
+impl Vrc7 {
+    pub fn new(mut cartridge: Cartridge) -> Vrc7 {
+        let select_mask = match cartridge.submapper {
+            1 => VRC7B_SELECT,
+            2 => VRC7A_SELECT,
+            _ => VRC7A_SELECT | VRC7B_SELECT,
+        };
+        let (chr, chr_is_ram) = chr_memory(&mut cartridge);
+        Vrc7 {
+            prg_ram: prg_ram(&cartridge),
+            has_battery: cartridge.has_battery,
+            prg_rom: cartridge.prg_rom,
+            chr,
+            chr_is_ram,
+            select_mask,
+            prg_banks: [0; 3],
+            chr_banks: [0; 8],
+            control: 0,
+            irq: VrcIrq::new(),
+            opll: Opll::new(),
+        }
+    }
+
+    fn write_register(&mut self, address: u16, data: u8) {
+        // the audio ports are decoded on their full address
+        match address & 0xf030 {
+            AUDIO_ADDRESS_PORT => return self.opll.write_address(data),
+            AUDIO_DATA_PORT => return self.opll.write_data(data),
+            _ => {}
+        }
+        let is_second = address & self.select_mask != 0;
+        match (address & 0xf000, is_second) {
+            (0x8000, false) => self.prg_banks[0] = data & PRG_BANK_MASK,
+            (0x8000, true) => self.prg_banks[1] = data & PRG_BANK_MASK,
+            (0x9000, false) => self.prg_banks[2] = data & PRG_BANK_MASK,
+            (0xa000..=0xd000, _) => {
+                let bank = ((address >> 12) - 0xa) as usize * 2 + is_second as usize;
+                self.chr_banks[bank] = data;
+            }
+            (0xe000, false) => {
+                self.control = data;
+                if data & AUDIO_RESET_BIT == AUDIO_RESET_BIT {
+                    self.opll.reset();
+                }
+            }
+            (0xe000, true) => self.irq.write_latch(data),
+            (0xf000, false) => self.irq.write_control(data),
+            (0xf000, true) => self.irq.acknowledge(),
+            _ => {}
+        }
+    }
+
+    fn prg_rom_index(&self, address: u16) -> usize {
+        let slot = ((address - PRG_ROM_START) as usize) / PRG_BANK_SIZE;
+        let bank = match slot {
+            0..=2 => self.prg_banks[slot] as usize,
+            _ => bank_count(&self.prg_rom, PRG_BANK_SIZE) - 1,
+        };
+        banked_index(
+            &self.prg_rom,
+            bank,
+            PRG_BANK_SIZE,
+            (address - PRG_ROM_START) as usize,
+        )
+    }
+    fn chr_index(&self, address: u16) -> usize {
+        let bank = self.chr_banks[address as usize / CHR_BANK_SIZE];
+        banked_index(&self.chr, bank as usize, CHR_BANK_SIZE, address as usize)
+    }
+    fn is_prg_ram_enabled(&self) -> bool {
+        !self.prg_ram.is_empty() && self.control & PRG_RAM_ENABLE_BIT == PRG_RAM_ENABLE_BIT
+    }
+}
+
+impl Mapper for Vrc7 {
+    fn cpu_peek(&self, address: u16) -> Option<u8> {
+        match address {
+            PRG_RAM_START..=0x7fff if self.is_prg_ram_enabled() => {
+                let offset = (address - PRG_RAM_START) as usize;
+                Some(self.prg_ram[offset % self.prg_ram.len()])
+            }
+            PRG_ROM_START..=0xffff => Some(self.prg_rom[self.prg_rom_index(address)]),
+            _ => None,
+        }
+    }
+    fn cpu_write(&mut self, address: u16, data: u8) {
+        match address {
+            PRG_RAM_START..=0x7fff if self.is_prg_ram_enabled() => {
+                let index = (address - PRG_RAM_START) as usize % self.prg_ram.len();
+                self.prg_ram[index] = data;
+            }
+            PRG_ROM_START..=0xffff => self.write_register(address, data),
+            _ => {}
+        }
+    }
+    fn ppu_peek(&self, address: u16) -> u8 {
+        self.chr[self.chr_index(address)]
+    }
+    fn ppu_write(&mut self, address: u16, data: u8) {
+        if self.chr_is_ram {
+            let index = self.chr_index(address);
+            self.chr[index] = data;
+        }
+    }
+    fn mirroring(&self) -> Mirroring {
+        match self.control & 0b11 {
+            0 => Mirroring::Vertical,
+            1 => Mirroring::Horizontal,
+            2 => Mirroring::SingleScreenLower,
+            _ => Mirroring::SingleScreenUpper,
+        }
+    }
+    fn irq_asserted(&self) -> bool {
+        self.irq.is_pending()
+    }
+    fn cpu_clock(&mut self) {
+        self.irq.cpu_clock();
+        // held in reset the synthesizer is silent
+        if self.control & AUDIO_RESET_BIT == 0 {
+            self.opll.cpu_clock();
+        }
+    }
+    fn audio_output(&self) -> f32 {
+        self.opll.output()
+    }
+    fn battery_ram(&self) -> Vec<u8> {
+        battery_prg_ram(&self.prg_ram, self.has_battery)
+    }
+    fn load_battery_ram(&mut self, data: &[u8]) {
+        load_battery_prg_ram(&mut self.prg_ram, self.has_battery, data);
+    }
+}
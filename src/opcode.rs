@@ -9,7 +9,7 @@ lazy_static! {
         OpCode::new(Instruction::ADC, 0x7d, 3, 4, AddressingMode::AbsoluteX),
         OpCode::new(Instruction::ADC, 0x79, 3, 4, AddressingMode::AbsoluteY),
         OpCode::new(Instruction::ADC, 0x61, 2, 6, AddressingMode::IndirectX),
-        OpCode::new(Instruction::ADC, 0x61, 2, 5, AddressingMode::IndirectY),
+        OpCode::new(Instruction::ADC, 0x71, 2, 5, AddressingMode::IndirectY),
         //
         OpCode::new(Instruction::AND, 0x29, 2, 2, AddressingMode::Immediate),
         OpCode::new(Instruction::AND, 0x25, 2, 3, AddressingMode::ZeroPage),
@@ -210,7 +210,7 @@ lazy_static! {
         OpCode::new(Instruction::TYA, 0x98, 1, 2, AddressingMode::Implied)
     ];
 }
-#[derive(Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Instruction {
     ADC,
     AND,
@@ -269,7 +269,7 @@ pub enum Instruction {
     TXS,
     TYA,
 }
-#[derive(Clone)]
+#[derive(Debug, Clone)]
 pub struct OpCode {
     instruction: Instruction,
     code: u8,
@@ -308,6 +308,38 @@ impl OpCode {
     pub fn get_addressing_mode(&self) -> &AddressingMode {
         &self.addressing_mode
     }
+    pub fn adds_cycle_on_page_cross(&self) -> bool {
+        matches!(
+            self.instruction,
+            Instruction::ADC
+                | Instruction::AND
+                | Instruction::CMP
+                | Instruction::EOR
+                | Instruction::LDA
+                | Instruction::LDX
+                | Instruction::LDY
+                | Instruction::ORA
+                | Instruction::SBC
+        )
+    }
+    pub fn is_control_flow(&self) -> bool {
+        matches!(
+            self.instruction,
+            Instruction::BCC
+                | Instruction::BCS
+                | Instruction::BEQ
+                | Instruction::BMI
+                | Instruction::BNE
+                | Instruction::BPL
+                | Instruction::BRK
+                | Instruction::BVC
+                | Instruction::BVS
+                | Instruction::JMP
+                | Instruction::JSR
+                | Instruction::RTI
+                | Instruction::RTS
+        )
+    }
 }
 
 pub fn get_opcode_with_code(code: u8) -> Option<OpCode> {
@@ -0,0 +1,304 @@
+use super::mapper::*;
+
+pub const PPUCTRL: usize = 0;
+pub const PPUMASK: usize = 1;
+pub const PPUSTATUS: usize = 2;
+pub const OAMADDR: usize = 3;
+pub const OAMDATA: usize = 4;
+pub const PPUSCROLL: usize = 5;
+pub const PPUADDR: usize = 6;
+pub const PPUDATA: usize = 7;
+
+// the console's 2 KiB, plus the two pages four-screen boards bring
+const VRAM_SIZE: usize = 0x1000;
+const NAMETABLE_PAGE_SIZE: usize = 0x0400;
+const PALETTE_SIZE: usize = 0x20;
+const OAM_SIZE: usize = 0x100;
+const PATTERN_TABLES_END: u16 = 0x1fff;
+const PALETTE_START: u16 = 0x3f00;
+const VRAM_ADDRESS_MASK: u16 = 0x3fff;
+
+const CTRL_NAMETABLE_MASK: u8 = 0b0000_0011;
+const CTRL_INCREMENT_32_BIT: u8 = 0b0000_0100;
+const CTRL_NMI_ENABLE_BIT: u8 = 0b1000_0000;
+const MASK_GREYSCALE_BIT: u8 = 0b0000_0001;
+const STATUS_VBLANK_BIT: u8 = 0b1000_0000;
+const STATUS_SPRITE_0_HIT_BIT: u8 = 0b0100_0000;
+const STATUS_SPRITE_OVERFLOW_BIT: u8 = 0b0010_0000;
+// PPUSTATUS only drives its top 3 bits, palette reads leave the top 2 to the latch
+const STATUS_BITS_MASK: u8 = 0b1110_0000;
+const PALETTE_BITS_MASK: u8 = 0b0011_1111;
+// bits 2-4 of the sprite attribute byte do not exist and read back as 0
+const OAM_ATTRIBUTE_MASK: u8 = 0b1110_0011;
+
+// a bit of the open-bus latch fades to 0 about 600 ms after it was last driven
+const OPEN_BUS_DECAY_CYCLES: u64 = 1_073_864;
+
+// the 2C02 as the CPU sees it through $2000-$2007: registers, VRAM, palette and OAM
+pub struct Ppu {
+    ctrl: u8,
+    mask: u8,
+    status: u8,
+    oam_address: u8,
+    oam: [u8; OAM_SIZE],
+    vram: [u8; VRAM_SIZE],
+    palette: [u8; PALETTE_SIZE],
+    // current and temporary VRAM address, 15 bits each, shared by PPUSCROLL and PPUADDR
+    vram_address: u16,
+    temp_vram_address: u16,
+    fine_x_scroll: u8,
+    // first or second write to PPUSCROLL/PPUADDR
+    write_toggle: bool,
+    read_buffer: u8,
+    // the data bus between the CPU and the PPU holds its value for a while
+    open_bus: u8,
+    open_bus_driven_at: [u64; 8],
+    cpu_cycles: u64,
+}
+
+impl Ppu {
+    pub fn new() -> Ppu {
+        Ppu {
+            ctrl: 0,
+            mask: 0,
+            status: 0,
+            oam_address: 0,
+            oam: [0; OAM_SIZE],
+            vram: [0; VRAM_SIZE],
+            palette: [0; PALETTE_SIZE],
+            vram_address: 0,
+            temp_vram_address: 0,
+            fine_x_scroll: 0,
+            write_toggle: false,
+            read_buffer: 0,
+            open_bus: 0,
+            open_bus_driven_at: [0; 8],
+            cpu_cycles: 0,
+        }
+    }
+
+    pub fn tick(&mut self, cpu_cycles: u8) {
+        self.cpu_cycles += cpu_cycles as u64;
+    }
+    pub fn start_vblank(&mut self) {
+        self.status |= STATUS_VBLANK_BIT;
+    }
+    // the pre-render line clears all three flags
+    pub fn end_vblank(&mut self) {
+        self.status &= !(STATUS_VBLANK_BIT | STATUS_SPRITE_0_HIT_BIT | STATUS_SPRITE_OVERFLOW_BIT);
+    }
+    pub fn nmi_asserted(&self) -> bool {
+        self.ctrl & CTRL_NMI_ENABLE_BIT == CTRL_NMI_ENABLE_BIT
+            && self.status & STATUS_VBLANK_BIT == STATUS_VBLANK_BIT
+    }
+    pub fn temp_vram_address(&self) -> u16 {
+        self.temp_vram_address
+    }
+    pub fn fine_x_scroll(&self) -> u8 {
+        self.fine_x_scroll
+    }
+
+    pub fn read_register(&mut self, register: usize, mapper: Option<&mut Box<dyn Mapper>>) -> u8 {
+        match register {
+            PPUSTATUS => {
+                let data = self.status_value();
+                self.drive_open_bus(STATUS_BITS_MASK, data);
+                self.status &= !STATUS_VBLANK_BIT;
+                self.write_toggle = false;
+                data
+            }
+            OAMDATA => {
+                let data = self.oam_value();
+                self.drive_open_bus(0xff, data);
+                data
+            }
+            PPUDATA => {
+                let address = self.vram_address & VRAM_ADDRESS_MASK;
+                let data = if address >= PALETTE_START {
+                    let data = self.palette_value(address);
+                    self.drive_open_bus(PALETTE_BITS_MASK, data);
+                    // the buffer still gets the nametable byte "under" the palette
+                    self.read_buffer = self.read_vram(address - 0x1000, mapper);
+                    data
+                } else {
+                    let data = self.read_buffer;
+                    self.drive_open_bus(0xff, data);
+                    self.read_buffer = self.read_vram(address, mapper);
+                    data
+                };
+                self.increment_vram_address();
+                data
+            }
+            // write-only registers answer with the latch
+            _ => self.open_bus_value(),
+        }
+    }
+    // what a read would return, without clearing flags or advancing the buffer
+    pub fn peek_register(&self, register: usize) -> u8 {
+        match register {
+            PPUSTATUS => self.status_value(),
+            OAMDATA => self.oam_value(),
+            PPUDATA => {
+                let address = self.vram_address & VRAM_ADDRESS_MASK;
+                if address >= PALETTE_START {
+                    self.palette_value(address)
+                } else {
+                    self.read_buffer
+                }
+            }
+            _ => self.open_bus_value(),
+        }
+    }
+    pub fn write_register(
+        &mut self,
+        register: usize,
+        data: u8,
+        mapper: Option<&mut Box<dyn Mapper>>,
+    ) {
+        self.drive_open_bus(0xff, data);
+        match register {
+            PPUCTRL => {
+                self.ctrl = data;
+                self.temp_vram_address = (self.temp_vram_address & !0x0c00)
+                    | ((data & CTRL_NAMETABLE_MASK) as u16) << 10;
+            }
+            PPUMASK => self.mask = data,
+            OAMADDR => self.oam_address = data,
+            OAMDATA => {
+                self.oam[self.oam_address as usize] = data;
+                self.oam_address = self.oam_address.wrapping_add(1);
+            }
+            PPUSCROLL => {
+                if !self.write_toggle {
+                    self.temp_vram_address =
+                        (self.temp_vram_address & !0x001f) | (data >> 3) as u16;
+                    self.fine_x_scroll = data & 0b111;
+                } else {
+                    self.temp_vram_address = (self.temp_vram_address & !0x73e0)
+                        | ((data & 0b111) as u16) << 12
+                        | ((data >> 3) as u16) << 5;
+                }
+                self.write_toggle = !self.write_toggle;
+            }
+            PPUADDR => {
+                if !self.write_toggle {
+                    // bit 14 of the 15-bit register is cleared by the first write
+                    self.temp_vram_address =
+                        (self.temp_vram_address & 0x00ff) | ((data & 0x3f) as u16) << 8;
+                } else {
+                    self.temp_vram_address = (self.temp_vram_address & 0xff00) | data as u16;
+                    self.vram_address = self.temp_vram_address;
+                }
+                self.write_toggle = !self.write_toggle;
+            }
+            PPUDATA => {
+                self.write_vram(self.vram_address & VRAM_ADDRESS_MASK, data, mapper);
+                self.increment_vram_address();
+            }
+            // PPUSTATUS is read-only, the write only reaches the latch
+            _ => {}
+        }
+    }
+
+    fn drive_open_bus(&mut self, bits: u8, data: u8) {
+        self.open_bus = (self.open_bus & !bits) | (data & bits);
+        for bit in 0..8 {
+            if bits & (1 << bit) != 0 {
+                self.open_bus_driven_at[bit] = self.cpu_cycles;
+            }
+        }
+    }
+    fn open_bus_value(&self) -> u8 {
+        (0..8)
+            .filter(|bit| self.cpu_cycles - self.open_bus_driven_at[*bit] < OPEN_BUS_DECAY_CYCLES)
+            .fold(0, |value, bit| value | (self.open_bus & (1 << bit)))
+    }
+    fn status_value(&self) -> u8 {
+        (self.status & STATUS_BITS_MASK) | (self.open_bus_value() & !STATUS_BITS_MASK)
+    }
+    fn oam_value(&self) -> u8 {
+        let data = self.oam[self.oam_address as usize];
+        if self.oam_address & 0b11 == 2 {
+            data & OAM_ATTRIBUTE_MASK
+        } else {
+            data
+        }
+    }
+    fn palette_value(&self, address: u16) -> u8 {
+        let mut data = self.palette[palette_index(address)];
+        if self.mask & MASK_GREYSCALE_BIT == MASK_GREYSCALE_BIT {
+            data &= 0x30;
+        }
+        data | (self.open_bus_value() & !PALETTE_BITS_MASK)
+    }
+    fn increment_vram_address(&mut self) {
+        let increment = if self.ctrl & CTRL_INCREMENT_32_BIT == CTRL_INCREMENT_32_BIT {
+            32
+        } else {
+            1
+        };
+        self.vram_address = (self.vram_address + increment) & 0x7fff;
+    }
+
+    // a real PPU bus access: boards see the address and the pattern fetch
+    fn read_vram(&mut self, address: u16, mapper: Option<&mut Box<dyn Mapper>>) -> u8 {
+        let Some(mapper) = mapper else {
+            return match address {
+                0x0000..=PATTERN_TABLES_END => 0,
+                _ => self.vram[address as usize % 0x0800],
+            };
+        };
+        mapper.ppu_address_bus(address);
+        match address {
+            0x0000..=PATTERN_TABLES_END => mapper.ppu_read(address),
+            _ => {
+                let address = 0x2000 | (address & 0x0fff);
+                match mapper.nametable_read(address) {
+                    Some(data) => data,
+                    None => self.vram[nametable_index(mapper.nametable_page(address), address)],
+                }
+            }
+        }
+    }
+    fn write_vram(&mut self, address: u16, data: u8, mapper: Option<&mut Box<dyn Mapper>>) {
+        if address >= PALETTE_START {
+            self.palette[palette_index(address)] = data & PALETTE_BITS_MASK;
+            return;
+        }
+        let Some(mapper) = mapper else {
+            if address > PATTERN_TABLES_END {
+                self.vram[address as usize % 0x0800] = data;
+            }
+            return;
+        };
+        mapper.ppu_address_bus(address);
+        match address {
+            0x0000..=PATTERN_TABLES_END => mapper.ppu_write(address, data),
+            _ => {
+                let address = 0x2000 | (address & 0x0fff);
+                if !mapper.nametable_write(address, data) {
+                    self.vram[nametable_index(mapper.nametable_page(address), address)] = data;
+                }
+            }
+        }
+    }
+}
+
+impl Default for Ppu {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn nametable_index(page: usize, address: u16) -> usize {
+    (page * NAMETABLE_PAGE_SIZE + (address as usize & (NAMETABLE_PAGE_SIZE - 1))) % VRAM_SIZE
+}
+// $3F10/$3F14/$3F18/$3F1C share the backdrop entries of the background palettes
+fn palette_index(address: u16) -> usize {
+    let index = address as usize & (PALETTE_SIZE - 1);
+    if index & 0x13 == 0x10 {
+        index & !0x10
+    } else {
+        index
+    }
+}
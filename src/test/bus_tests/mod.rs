@@ -0,0 +1 @@
+mod nes_bus_tests;
@@ -0,0 +1,210 @@
+use crate::bus::*;
+use crate::cartridge::*;
+use crate::cpu::*;
+use crate::interrupt::*;
+use crate::mapper::*;
+
+// writable memory over the whole cartridge space, so raw test programs can be loaded
+struct RamCartridge {
+    memory: Vec<u8>,
+    irq: bool,
+}
+impl Mapper for RamCartridge {
+    fn cpu_peek(&self, address: u16) -> Option<u8> {
+        Some(self.memory[address as usize - 0x4020])
+    }
+    fn cpu_write(&mut self, address: u16, data: u8) {
+        if address == 0x4020 && data == 0xff {
+            self.irq = true;
+        }
+        self.memory[address as usize - 0x4020] = data;
+    }
+    fn ppu_peek(&self, _address: u16) -> u8 {
+        0
+    }
+    fn ppu_write(&mut self, _address: u16, _data: u8) {}
+    fn mirroring(&self) -> Mirroring {
+        Mirroring::Horizontal
+    }
+    fn irq_asserted(&self) -> bool {
+        self.irq
+    }
+    fn audio_output(&self) -> f32 {
+        self.memory[0] as f32
+    }
+}
+fn bus_with_ram_cartridge() -> NesBus {
+    let mut bus = NesBus::new();
+    bus.insert_cartridge(Box::new(RamCartridge {
+        memory: vec![0; 0x10000 - 0x4020],
+        irq: false,
+    }));
+    bus
+}
+
+#[test]
+fn test_ram_is_mirrored_through_1fff() {
+    let mut bus = NesBus::new();
+    bus.write(0x0000, 0x11);
+    assert_eq!(bus.read(0x0800), 0x11);
+    assert_eq!(bus.read(0x1000), 0x11);
+    assert_eq!(bus.read(0x1800), 0x11);
+    bus.write(0x1fff, 0x22);
+    assert_eq!(bus.read(0x07ff), 0x22);
+    assert_eq!(bus.read(0x0fff), 0x22);
+    bus.write(0x0a34, 0x33);
+    assert_eq!(bus.read(0x0234), 0x33);
+    assert_eq!(bus.read(0x1234), 0x33);
+}
+#[test]
+fn test_ram_mirrors_do_not_overlap_within_2_kib() {
+    let mut bus = NesBus::new();
+    bus.write(0x0000, 0x11);
+    bus.write(0x07ff, 0x22);
+    assert_eq!(bus.read(0x0000), 0x11);
+    assert_eq!(bus.read(0x0400), 0x00);
+    assert_eq!(bus.read(0x07ff), 0x22);
+}
+#[test]
+fn test_ppu_registers_are_mirrored_every_8_bytes() {
+    let mut bus = NesBus::new();
+    // PPUADDR through $2006 mirrors, PPUDATA through $3FFF
+    bus.write(0x3456, 0x23);
+    bus.write(0x200e, 0x45);
+    bus.write(0x3fff, 0x22);
+    bus.write(0x2006, 0x23);
+    bus.write(0x3ffe, 0x45);
+    bus.read(0x2007);
+    assert_eq!(bus.read(0x200f), 0x22);
+    // write-only registers read back the PPU's open-bus latch
+    bus.write(0x2000, 0x11);
+    assert_eq!(bus.peek(0x2008), 0x11);
+    assert_eq!(bus.peek(0x3ff8), 0x11);
+}
+#[test]
+fn test_ppu_vblank_nmi_reaches_cpu() {
+    let mut bus = NesBus::new();
+    let mut lines = InterruptLines::new();
+    bus.ppu_mut().start_vblank();
+    bus.poll_interrupts(&mut lines);
+    assert!(!lines.is_nmi_pending());
+    bus.write(0x2000, 0x80);
+    bus.poll_interrupts(&mut lines);
+    assert!(lines.is_nmi_pending());
+    // reading PPUSTATUS ends the flag the NMI comes from
+    assert_eq!(bus.read(0x2002) & 0x80, 0x80);
+    assert_eq!(bus.peek(0x2002) & 0x80, 0x00);
+}
+#[test]
+fn test_ppu_register_window_is_separate_from_ram() {
+    let mut bus = NesBus::new();
+    bus.write(0x2000, 0x11);
+    assert_eq!(bus.peek(0x0000), 0x00);
+    assert_eq!(bus.peek(0x4000), 0x00);
+}
+#[test]
+fn test_apu_io_registers_are_not_mirrored() {
+    let mut bus = bus_with_ram_cartridge();
+    bus.write(0x4000, 0x11);
+    bus.write(0x4017, 0x22);
+    assert_eq!(bus.peek(0x4000), 0x11);
+    assert_eq!(bus.peek(0x4017), 0x22);
+    assert_eq!(bus.peek(0x4008), 0x00);
+    assert_eq!(bus.peek(0x4020), 0x00);
+}
+#[test]
+fn test_test_mode_range_returns_open_bus() {
+    let mut bus = NesBus::new();
+    bus.write(0x4018, 0x11);
+    bus.write(0x401f, 0x22);
+    // the last write is still on the data bus
+    assert_eq!(bus.read(0x4018), 0x22);
+    bus.write(0x0000, 0x33);
+    assert_eq!(bus.read(0x0000), 0x33);
+    assert_eq!(bus.read(0x401f), 0x33);
+}
+#[test]
+fn test_cartridge_space_starts_at_4020() {
+    let mut bus = bus_with_ram_cartridge();
+    bus.write(0x4020, 0x11);
+    bus.write(0x8000, 0x22);
+    bus.write(0xffff, 0x33);
+    assert_eq!(bus.peek(0x4020), 0x11);
+    assert_eq!(bus.peek(0x8000), 0x22);
+    assert_eq!(bus.peek(0xffff), 0x33);
+    assert_eq!(bus.peek(0x0020), 0x00);
+}
+#[test]
+fn test_empty_cartridge_slot_is_open_bus() {
+    let mut bus = NesBus::new();
+    bus.write(0x0000, 0x42);
+    assert_eq!(bus.read(0x0000), 0x42);
+    assert_eq!(bus.read(0x8000), 0x42);
+    bus.write(0x8000, 0x11);
+    assert_eq!(bus.read(0x8000), 0x11);
+    assert!(bus.remove_cartridge().is_none());
+}
+#[test]
+fn test_cartridge_irq_reaches_cpu() {
+    let mut bus = bus_with_ram_cartridge();
+    let mut lines = InterruptLines::new();
+    bus.poll_interrupts(&mut lines);
+    assert!(!lines.is_irq_asserted());
+    bus.write(0x4020, 0xff);
+    bus.poll_interrupts(&mut lines);
+    assert!(lines.is_irq_asserted());
+    bus.remove_cartridge();
+    bus.poll_interrupts(&mut lines);
+    assert!(!lines.is_irq_asserted());
+}
+#[test]
+fn test_expansion_audio_is_averaged_per_cpu_cycle() {
+    let mut bus = bus_with_ram_cartridge();
+    bus.write(0x4020, 2);
+    bus.tick(2);
+    bus.write(0x4020, 5);
+    bus.tick(1);
+    assert_eq!(bus.take_expansion_audio(), 3.0);
+    assert_eq!(bus.take_expansion_audio(), 0.0);
+}
+#[test]
+fn test_cpu_sees_mirrored_ram() {
+    let mut cpu = CPU::with_bus(Box::new(bus_with_ram_cartridge()));
+    cpu.load_and_run(vec![
+        0xa9, 0x42, // LDA #$42
+        0x8d, 0x00, 0x08, // STA $0800
+        0xae, 0x00, 0x00, // LDX $0000
+        0xac, 0x00, 0x18, // LDY $1800
+        0x00,
+    ])
+    .unwrap();
+    assert_eq!(cpu.register_x, 0x42);
+    assert_eq!(cpu.register_y, 0x42);
+}
+#[test]
+fn test_cpu_stack_lives_in_mirrored_ram() {
+    let mut cpu = CPU::with_bus(Box::new(bus_with_ram_cartridge()));
+    cpu.load_and_run(vec![
+        0xa9, 0x42, // LDA #$42
+        0x48, // PHA
+        0xae, 0xfd, 0x09, // LDX $09fd
+        0x00,
+    ])
+    .unwrap();
+    assert_eq!(cpu.register_x, 0x42);
+}
+#[test]
+fn test_host_reaches_cartridge_through_cpu_bus() {
+    let mut cpu = CPU::with_bus(Box::new(bus_with_ram_cartridge()));
+    cpu.load_and_run(vec![
+        0xa9, 0x42, // LDA #$42
+        0x8d, 0x00, 0x60, // STA $6000
+        0x00,
+    ])
+    .unwrap();
+    assert_eq!(cpu.bus.cartridge().unwrap().cpu_peek(0x6000), Some(0x42));
+    cpu.bus.cartridge_mut().unwrap().cpu_write(0x6000, 0x11);
+    assert_eq!(cpu.memory_read_byte(0x6000), 0x11);
+    assert!(CPU::new().bus.cartridge().is_none());
+    assert!(NesBus::new().cartridge_mut().is_none());
+}
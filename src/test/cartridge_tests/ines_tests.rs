@@ -0,0 +1,146 @@
+use crate::bus::*;
+use crate::cartridge::*;
+use crate::cpu::*;
+use crate::mapper::*;
+
+fn ines_image(prg_banks: u8, chr_banks: u8, flags_6: u8, flags_7: u8) -> Vec<u8> {
+    let mut image = vec![
+        b'N', b'E', b'S', 0x1a, prg_banks, chr_banks, flags_6, flags_7,
+    ];
+    image.resize(16, 0);
+    if flags_6 & 0b0000_0100 != 0 {
+        image.extend(vec![0x7e; 512]);
+    }
+    image.extend(vec![0xaa; prg_banks as usize * 0x4000]);
+    image.extend(vec![0xcc; chr_banks as usize * 0x2000]);
+    image
+}
+
+#[test]
+fn test_parses_sizes_and_sections() {
+    let cartridge = Cartridge::from_ines(&ines_image(2, 1, 0x00, 0x00)).unwrap();
+    assert_eq!(cartridge.prg_rom.len(), 0x8000);
+    assert_eq!(cartridge.chr_rom.len(), 0x2000);
+    assert!(cartridge.prg_rom.iter().all(|byte| *byte == 0xaa));
+    assert!(cartridge.chr_rom.iter().all(|byte| *byte == 0xcc));
+    assert_eq!(cartridge.trainer, None);
+    assert!(!cartridge.has_battery);
+}
+#[test]
+fn test_zero_chr_banks_means_chr_ram() {
+    let cartridge = Cartridge::from_ines(&ines_image(1, 0, 0x00, 0x00)).unwrap();
+    assert_eq!(cartridge.prg_rom.len(), 0x4000);
+    assert!(cartridge.chr_rom.is_empty());
+}
+#[test]
+fn test_mapper_number_from_both_nibbles() {
+    let cartridge = Cartridge::from_ines(&ines_image(1, 1, 0x40, 0x10)).unwrap();
+    assert_eq!(cartridge.mapper, 0x14);
+}
+#[test]
+fn test_upper_mapper_nibble_ignored_with_dirty_padding() {
+    let mut image = ines_image(1, 1, 0x10, 0x40);
+    image[7..16].copy_from_slice(b"DiskDude!");
+    let cartridge = Cartridge::from_ines(&image).unwrap();
+    assert_eq!(cartridge.mapper, 0x01);
+}
+#[test]
+fn test_mirroring_flags() {
+    let horizontal = Cartridge::from_ines(&ines_image(1, 1, 0x00, 0x00)).unwrap();
+    let vertical = Cartridge::from_ines(&ines_image(1, 1, 0x01, 0x00)).unwrap();
+    let four_screen = Cartridge::from_ines(&ines_image(1, 1, 0x09, 0x00)).unwrap();
+    assert_eq!(horizontal.mirroring, Mirroring::Horizontal);
+    assert_eq!(vertical.mirroring, Mirroring::Vertical);
+    assert_eq!(four_screen.mirroring, Mirroring::FourScreen);
+}
+#[test]
+fn test_battery_and_trainer_flags() {
+    let cartridge = Cartridge::from_ines(&ines_image(1, 1, 0x06, 0x00)).unwrap();
+    assert!(cartridge.has_battery);
+    assert_eq!(cartridge.trainer, Some(vec![0x7e; 512]));
+    // the trainer sits in front of PRG-ROM and is not part of it
+    assert_eq!(cartridge.prg_rom[0], 0xaa);
+}
+#[test]
+fn test_rejects_short_file() {
+    let error = Cartridge::from_ines(b"NES").unwrap_err();
+    assert_eq!(error, CartridgeError::MissingHeader { length: 3 });
+}
+#[test]
+fn test_rejects_bad_signature() {
+    let mut image = ines_image(1, 1, 0x00, 0x00);
+    image[3] = 0x00;
+    let error = Cartridge::from_ines(&image).unwrap_err();
+    assert_eq!(
+        error,
+        CartridgeError::InvalidSignature {
+            found: [b'N', b'E', b'S', 0x00]
+        }
+    );
+}
+#[test]
+fn test_rejects_missing_prg_rom() {
+    let error = Cartridge::from_ines(&ines_image(0, 1, 0x00, 0x00)).unwrap_err();
+    assert_eq!(error, CartridgeError::MissingPrgRom);
+}
+#[test]
+fn test_rejects_truncated_sections() {
+    let mut image = ines_image(2, 1, 0x00, 0x00);
+    image.truncate(16 + 0x8000 + 0x100);
+    let error = Cartridge::from_ines(&image).unwrap_err();
+    assert_eq!(
+        error,
+        CartridgeError::Truncated {
+            section: RomSection::ChrRom,
+            expected: 0x2000,
+            actual: 0x100
+        }
+    );
+    image.truncate(16 + 0x10);
+    let error = Cartridge::from_ines(&image).unwrap_err();
+    assert_eq!(
+        error,
+        CartridgeError::Truncated {
+            section: RomSection::PrgRom,
+            expected: 0x8000,
+            actual: 0x10
+        }
+    );
+    let error = Cartridge::from_ines(&ines_image(1, 0, 0x04, 0x00)[..100]).unwrap_err();
+    assert_eq!(
+        error,
+        CartridgeError::Truncated {
+            section: RomSection::Trainer,
+            expected: 512,
+            actual: 84
+        }
+    );
+}
+#[test]
+fn test_error_messages() {
+    assert_eq!(
+        CartridgeError::MissingPrgRom.to_string(),
+        "header declares no PRG-ROM"
+    );
+    assert_eq!(
+        CartridgeError::MissingHeader { length: 3 }.to_string(),
+        "file is 3 bytes long, too short for a 16 byte iNES header"
+    );
+}
+#[test]
+fn test_boots_cartridge_from_reset_vector() {
+    let mut image = ines_image(1, 0, 0x00, 0x00);
+    let prg_rom = &mut image[16..16 + 0x4000];
+    prg_rom[0..5].copy_from_slice(&[0xa2, 0x42, 0x00, 0x00, 0x00]); // LDX #$42, BRK
+                                                                    // reset vector at $FFFC, mirrored from $BFFC
+    prg_rom[0x3ffc] = 0x00;
+    prg_rom[0x3ffd] = 0xc0;
+    let cartridge = Cartridge::from_ines(&image).unwrap();
+    let mut bus = NesBus::new();
+    bus.insert_cartridge(create_mapper(cartridge).unwrap());
+    let mut cpu = CPU::with_bus(Box::new(bus));
+    cpu.reset();
+    assert_eq!(cpu.program_counter, 0xc000);
+    cpu.run().unwrap();
+    assert_eq!(cpu.register_x, 0x42);
+}
@@ -0,0 +1,2 @@
+mod ines_tests;
+mod nes_2_0_tests;
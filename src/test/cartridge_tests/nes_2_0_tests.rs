@@ -0,0 +1,160 @@
+use crate::cartridge::*;
+use crate::timing::*;
+
+fn nes_2_0_image(header: [u8; 16], prg_rom_size: usize, chr_rom_size: usize) -> Vec<u8> {
+    let mut image = header.to_vec();
+    image[0..4].copy_from_slice(b"NES\x1a");
+    image[7] = (image[7] & !0b0000_1100) | 0b0000_1000;
+    image.extend(vec![0xaa; prg_rom_size]);
+    image.extend(vec![0xcc; chr_rom_size]);
+    image
+}
+
+#[test]
+fn test_detects_nes_2_0_header() {
+    let mut header = [0; 16];
+    header[4] = 1;
+    let cartridge = Cartridge::from_ines(&nes_2_0_image(header, 0x4000, 0)).unwrap();
+    assert_eq!(cartridge.format, HeaderFormat::Nes20);
+}
+#[test]
+fn test_plain_ines_header_is_not_nes_2_0() {
+    let mut header = [0; 16];
+    header[4] = 1;
+    let mut image = nes_2_0_image(header, 0x4000, 0);
+    image[7] = 0x00;
+    let cartridge = Cartridge::from_ines(&image).unwrap();
+    assert_eq!(cartridge.format, HeaderFormat::INes);
+}
+#[test]
+fn test_12_bit_mapper_and_submapper() {
+    let mut header = [0; 16];
+    header[4] = 1;
+    header[6] = 0x50;
+    header[7] = 0xa0;
+    header[8] = 0x31;
+    let cartridge = Cartridge::from_ines(&nes_2_0_image(header, 0x4000, 0)).unwrap();
+    assert_eq!(cartridge.mapper, 0x1a5);
+    assert_eq!(cartridge.submapper, 3);
+}
+#[test]
+fn test_rom_size_msb_nibbles() {
+    let mut header = [0; 16];
+    header[4] = 0x00;
+    header[5] = 0x02;
+    // 256 PRG banks is 4 MiB, more than iNES can express
+    header[9] = 0x01;
+    let cartridge = Cartridge::from_ines(&nes_2_0_image(header, 0x40_0000, 0x4000)).unwrap();
+    assert_eq!(cartridge.prg_rom.len(), 0x40_0000);
+    assert_eq!(cartridge.chr_rom.len(), 0x4000);
+}
+#[test]
+fn test_rom_size_exponent_multiplier() {
+    let mut header = [0; 16];
+    // 2^10 * 3 = 3 KiB PRG, 2^9 * 1 = 512 bytes CHR
+    header[4] = (10 << 2) | 0b01;
+    header[5] = 9 << 2;
+    header[9] = 0xff;
+    let cartridge = Cartridge::from_ines(&nes_2_0_image(header, 3 * 1024, 512)).unwrap();
+    assert_eq!(cartridge.prg_rom.len(), 3 * 1024);
+    assert_eq!(cartridge.chr_rom.len(), 512);
+}
+#[test]
+fn test_rejects_overflowing_exponent() {
+    let mut header = [0; 16];
+    header[4] = (63 << 2) | 0b11;
+    header[9] = 0x0f;
+    let error = Cartridge::from_ines(&nes_2_0_image(header, 0, 0)).unwrap_err();
+    assert_eq!(
+        error,
+        CartridgeError::SizeOverflow {
+            section: RomSection::PrgRom
+        }
+    );
+}
+#[test]
+fn test_ram_sizes_from_shift_counts() {
+    let mut header = [0; 16];
+    header[4] = 1;
+    header[6] = 0b0000_0010;
+    // 64 << 7 = 8 KiB PRG-RAM, no PRG-NVRAM; 64 << 7 CHR-RAM, 64 << 1 CHR-NVRAM
+    header[10] = 0x07;
+    header[11] = 0x17;
+    let cartridge = Cartridge::from_ines(&nes_2_0_image(header, 0x4000, 0)).unwrap();
+    assert_eq!(cartridge.prg_ram_size, 0x2000);
+    assert_eq!(cartridge.prg_nvram_size, 0);
+    assert_eq!(cartridge.chr_ram_size, 0x2000);
+    assert_eq!(cartridge.chr_nvram_size, 128);
+    assert!(cartridge.has_battery);
+}
+#[test]
+fn test_battery_backed_prg_nvram() {
+    let mut header = [0; 16];
+    header[4] = 1;
+    header[6] = 0b0000_0010;
+    header[10] = 0x70;
+    let cartridge = Cartridge::from_ines(&nes_2_0_image(header, 0x4000, 0)).unwrap();
+    assert_eq!(cartridge.prg_ram_size, 0);
+    assert_eq!(cartridge.prg_nvram_size, 0x2000);
+}
+#[test]
+fn test_ines_assumes_8_kib_prg_ram_and_chr_ram() {
+    let mut header = [0; 16];
+    header[4] = 1;
+    let mut image = nes_2_0_image(header, 0x4000, 0);
+    image[7] = 0x00;
+    let cartridge = Cartridge::from_ines(&image).unwrap();
+    assert_eq!(cartridge.prg_ram_size, 0x2000);
+    assert_eq!(cartridge.chr_ram_size, 0x2000);
+    image[6] = 0b0000_0010;
+    let cartridge = Cartridge::from_ines(&image).unwrap();
+    assert_eq!(cartridge.prg_ram_size, 0);
+    assert_eq!(cartridge.prg_nvram_size, 0x2000);
+}
+#[test]
+fn test_timing_regions() {
+    let mut header = [0; 16];
+    header[4] = 1;
+    let regions = [
+        Region::Ntsc,
+        Region::Pal,
+        Region::MultiRegion,
+        Region::Dendy,
+    ];
+    for (timing, region) in regions.iter().enumerate() {
+        header[12] = timing as u8;
+        let cartridge = Cartridge::from_ines(&nes_2_0_image(header, 0x4000, 0)).unwrap();
+        assert_eq!(cartridge.region, *region);
+        assert_eq!(cartridge.timing_config().region, *region);
+    }
+}
+#[test]
+fn test_timing_config_clocks() {
+    let ntsc = TimingConfig::for_region(Region::Ntsc);
+    let pal = TimingConfig::for_region(Region::Pal);
+    let dendy = TimingConfig::for_region(Region::Dendy);
+    assert_eq!(ntsc.cpu_clock_hz(), 1_789_772);
+    assert_eq!(pal.cpu_clock_hz(), 1_662_607);
+    assert_eq!(dendy.cpu_clock_hz(), 1_773_447);
+    assert_eq!(ntsc.cpu_cycles_per_frame(), 29_780);
+    assert_eq!(pal.cpu_cycles_per_frame(), 33_247);
+    assert_eq!(dendy.cpu_cycles_per_frame(), 35_464);
+    assert_eq!(
+        TimingConfig::for_region(Region::MultiRegion).cpu_divider,
+        12
+    );
+}
+#[test]
+fn test_console_type_and_expansion_device() {
+    let mut header = [0; 16];
+    header[4] = 1;
+    header[7] = 0b0000_0001;
+    header[15] = 0x01;
+    let cartridge = Cartridge::from_ines(&nes_2_0_image(header, 0x4000, 0)).unwrap();
+    assert_eq!(cartridge.console_type, ConsoleType::VsSystem);
+    assert_eq!(cartridge.default_expansion_device, 0x01);
+    header[7] = 0b0000_0011;
+    header[13] = 0x03;
+    let cartridge = Cartridge::from_ines(&nes_2_0_image(header, 0x4000, 0)).unwrap();
+    assert_eq!(cartridge.console_type, ConsoleType::Extended(0x03));
+}
@@ -1,7 +1,79 @@
-use crate::cpu::*;
-#[test]
-fn test_adc_immediate() {
-    let mut cpu = CPU::new();
-    cpu.load_and_run(vec![0x69, 0x05, 0x69, 0x05, 0x00]);
-    assert_eq!(cpu.accumulator, 0x0a);
-}
+use crate::cpu::*;
+#[test]
+fn test_adc_immediate() {
+    let mut cpu = CPU::new();
+    cpu.load_and_run(vec![0x69, 0x05, 0x69, 0x05, 0x00])
+        .unwrap();
+    assert_eq!(cpu.accumulator, 0x0a);
+}
+#[test]
+fn test_adc_carry_in_and_carry_out() {
+    let mut cpu = CPU::new();
+    cpu.load_and_run(vec![0xa9, 0xff, 0x38, 0x69, 0x00, 0x00])
+        .unwrap();
+    assert_eq!(cpu.accumulator, 0x00);
+    assert_eq!(cpu.status, CARRY_FLAG | ZERO_FLAG | INTERRUPT_DISABLE_FLAG);
+}
+#[test]
+fn test_adc_signed_overflow() {
+    let mut cpu = CPU::new();
+    cpu.load_and_run(vec![0xa9, 0x50, 0x69, 0x50, 0x00])
+        .unwrap();
+    assert_eq!(cpu.accumulator, 0xa0);
+    assert_eq!(
+        cpu.status,
+        OVERFLOW_FLAG | NEGATIVE_FLAG | INTERRUPT_DISABLE_FLAG
+    );
+}
+#[test]
+fn test_adc_16_bit_addition() {
+    let mut cpu = CPU::new();
+    cpu.memory_write_2_byte(0x10, 0x01ff);
+    cpu.memory_write_2_byte(0x12, 0x0001);
+    cpu.load_and_run(vec![
+        0x18, // CLC
+        0xa5, 0x10, // LDA $10
+        0x65, 0x12, // ADC $12
+        0x85, 0x14, // STA $14
+        0xa5, 0x11, // LDA $11
+        0x65, 0x13, // ADC $13
+        0x85, 0x15, // STA $15
+        0x00,
+    ])
+    .unwrap();
+    assert_eq!(cpu.memory_read_2_byte(0x14), 0x0200);
+}
+#[test]
+fn test_adc_indirect_y() {
+    let mut cpu = CPU::new();
+    cpu.memory_write_2_byte(0x20, 0x0300);
+    cpu.memory_write_byte(0x0302, 0x07);
+    cpu.load_and_run(vec![0xa9, 0x01, 0xa0, 0x02, 0x71, 0x20, 0x00])
+        .unwrap();
+    assert_eq!(cpu.accumulator, 0x08);
+}
+#[test]
+fn test_adc_all_operands_against_reference() {
+    let mut cpu = CPU::new();
+    for accumulator in 0..=0xffu8 {
+        for operand in 0..=0xffu8 {
+            for carry in 0..=1u8 {
+                let set_carry = if carry == 1 { 0x38 } else { 0x18 };
+                cpu.load_and_run(vec![0xa9, accumulator, set_carry, 0x69, operand, 0x00])
+                    .unwrap();
+
+                let unsigned = accumulator as u16 + operand as u16 + carry as u16;
+                let signed = accumulator as i8 as i16 + operand as i8 as i16 + carry as i16;
+                let expected = unsigned as u8;
+                assert_eq!(cpu.accumulator, expected);
+                assert_eq!(cpu.status & CARRY_FLAG != 0, unsigned > 0xff);
+                assert_eq!(
+                    cpu.status & OVERFLOW_FLAG != 0,
+                    !(-128..=127).contains(&signed)
+                );
+                assert_eq!(cpu.status & ZERO_FLAG != 0, expected == 0);
+                assert_eq!(cpu.status & NEGATIVE_FLAG != 0, expected >= 0x80);
+            }
+        }
+    }
+}
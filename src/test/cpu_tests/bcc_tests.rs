@@ -3,12 +3,33 @@ use crate::cpu::*;
 #[test]
 fn test_bcc_positve_offset() {
     let mut cpu = CPU::default();
-    cpu.load_and_run(vec![0x90, 0x05]);
-    assert_eq!(cpu.program_counter, 0x8008)
+    cpu.load_and_run(vec![0x90, 0x05]).unwrap();
+    // BRK at $8002 + 5 pushed its address + 2
+    assert_eq!(cpu.memory_read_2_byte(0x01fc), 0x8009)
 }
 #[test]
 fn test_bcc_negative_offset() {
     let mut cpu = CPU::default();
-    cpu.load_and_run(vec![0x90, 0x05]);
-    assert_eq!(cpu.program_counter, 0x8008)
+    cpu.load_and_run(vec![
+        0x4c, 0x05, 0x80, // JMP $8005
+        0xe8, // INX
+        0x00, // BRK
+        0x90, 0xfc, // BCC -4
+    ])
+    .unwrap();
+    assert_eq!(cpu.register_x, 0x01);
+    assert_eq!(cpu.memory_read_2_byte(0x01fc), 0x8006)
+}
+#[test]
+fn test_bcc_zero_offset_continues_with_next_instruction() {
+    let mut cpu = CPU::default();
+    cpu.load_and_run(vec![0x90, 0x00, 0xe8, 0x00]).unwrap();
+    assert_eq!(cpu.register_x, 0x01);
+}
+#[test]
+fn test_bcc_not_taken_when_carry_set() {
+    let mut cpu = CPU::default();
+    cpu.load_and_run(vec![0x38, 0x90, 0x02, 0xe8, 0xe8, 0x00])
+        .unwrap();
+    assert_eq!(cpu.register_x, 0x02);
 }
@@ -0,0 +1,90 @@
+use crate::cpu::*;
+
+#[test]
+fn test_bne_backwards_loop() {
+    let mut cpu = CPU::new();
+    cpu.load_and_run(vec![
+        0xa2, 0xfb, // LDX #$fb
+        0xe8, // INX
+        0xd0, 0xfd, // BNE -3
+        0xa0, 0x01, // LDY #$01
+        0x00,
+    ])
+    .unwrap();
+    assert_eq!(cpu.register_x, 0x00);
+    assert_eq!(cpu.register_y, 0x01);
+}
+#[test]
+fn test_beq_taken_on_zero() {
+    let mut cpu = CPU::new();
+    cpu.load_and_run(vec![0xa9, 0x00, 0xf0, 0x01, 0xe8, 0xc8, 0x00])
+        .unwrap();
+    assert_eq!(cpu.register_x, 0x00);
+    assert_eq!(cpu.register_y, 0x01);
+}
+#[test]
+fn test_bmi_and_bpl() {
+    let mut cpu = CPU::new();
+    cpu.load_and_run(vec![
+        0xa9, 0x80, // LDA #$80
+        0x30, 0x01, // BMI +1
+        0xe8, // INX
+        0x10, 0x01, // BPL +1
+        0xc8, // INY
+        0x00,
+    ])
+    .unwrap();
+    assert_eq!(cpu.register_x, 0x00);
+    assert_eq!(cpu.register_y, 0x01);
+}
+#[test]
+fn test_bvs_and_bvc() {
+    let mut cpu = CPU::new();
+    cpu.load_and_run(vec![
+        0xa9, 0x50, // LDA #$50
+        0x69, 0x50, // ADC #$50
+        0x50, 0x01, // BVC +1
+        0xe8, // INX
+        0x70, 0x01, // BVS +1
+        0xe8, // INX
+        0x00,
+    ])
+    .unwrap();
+    assert_eq!(cpu.register_x, 0x01);
+}
+#[test]
+fn test_bcs_maximum_forward_offset() {
+    let mut cpu = CPU::new();
+    let mut program = vec![0x38, 0xb0, 0x7f];
+    program.resize(0x82, 0xe8);
+    program.push(0x00);
+    cpu.load_and_run(program).unwrap();
+    // $8003 + $7f = $8082 skips every INX
+    assert_eq!(cpu.register_x, 0x00);
+    assert_eq!(cpu.memory_read_2_byte(0x01fc), 0x8084);
+}
+#[test]
+fn test_bcs_forward_across_page() {
+    let mut cpu = CPU::new();
+    let mut program = vec![0x38];
+    program.resize(0xfc, 0xea);
+    program.extend_from_slice(&[0xb0, 0x05]);
+    program.resize(0x103, 0xe8);
+    program.push(0x00);
+    cpu.load_and_run(program).unwrap();
+    // $80FE + $05 = $8103 lands on the next page, past every INX
+    assert_eq!(cpu.register_x, 0x00);
+    assert_eq!(cpu.memory_read_2_byte(0x01fc), 0x8105);
+}
+#[test]
+fn test_bne_backward_across_page() {
+    let mut cpu = CPU::new();
+    let mut program = vec![0x4c, 0x00, 0x81]; // JMP $8100
+    program.resize(0xfe, 0xea);
+    program.extend_from_slice(&[0xc8, 0x00]); // INY, BRK
+    program.extend_from_slice(&[0xa2, 0x01, 0xd0, 0xfa]); // LDX #$01, BNE -6
+    cpu.load_and_run(program).unwrap();
+    // $8104 - $06 = $80FE is back on the previous page
+    assert_eq!(cpu.register_y, 0x01);
+    assert_eq!(cpu.memory_read_2_byte(0x01fc), 0x8101);
+}
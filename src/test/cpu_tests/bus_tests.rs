@@ -0,0 +1,178 @@
+use crate::bus::*;
+use crate::cpu::*;
+use crate::interrupt::*;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+// $2002 behaves like a status register whose bit 7 is cleared by reading it
+struct ReadClearsBus {
+    ram: FlatRamBus,
+    status: u8,
+}
+impl Bus for ReadClearsBus {
+    fn read(&mut self, address: u16) -> u8 {
+        if address == 0x2002 {
+            let status = self.status;
+            self.status &= 0x7f;
+            return status;
+        }
+        self.ram.read(address)
+    }
+    fn write(&mut self, address: u16, data: u8) {
+        self.ram.write(address, data);
+    }
+    fn peek(&self, address: u16) -> u8 {
+        if address == 0x2002 {
+            return self.status;
+        }
+        self.ram.peek(address)
+    }
+}
+// raises NMI as soon as anything is written to the stack page
+struct NmiOnStackWriteBus {
+    ram: FlatRamBus,
+    nmi: bool,
+}
+impl Bus for NmiOnStackWriteBus {
+    fn read(&mut self, address: u16) -> u8 {
+        self.ram.read(address)
+    }
+    fn write(&mut self, address: u16, data: u8) {
+        if (0x0100..=0x01ff).contains(&address) {
+            self.nmi = true;
+        }
+        self.ram.write(address, data);
+    }
+    fn peek(&self, address: u16) -> u8 {
+        self.ram.peek(address)
+    }
+    fn poll_interrupts(&mut self, lines: &mut InterruptLines) {
+        lines.set_nmi_line(self.nmi);
+    }
+}
+// remembers every write and the cycles reported back by the CPU
+struct RecordingBus {
+    ram: FlatRamBus,
+    writes: Rc<RefCell<Vec<(u16, u8)>>>,
+    ticks: Rc<RefCell<Vec<u8>>>,
+}
+impl Bus for RecordingBus {
+    fn read(&mut self, address: u16) -> u8 {
+        self.ram.read(address)
+    }
+    fn write(&mut self, address: u16, data: u8) {
+        self.writes.borrow_mut().push((address, data));
+        self.ram.write(address, data);
+    }
+    fn peek(&self, address: u16) -> u8 {
+        self.ram.peek(address)
+    }
+    fn tick(&mut self, cycles: u8) {
+        self.ticks.borrow_mut().push(cycles);
+    }
+}
+
+#[test]
+fn test_flat_ram_bus_peek_matches_read() {
+    let mut bus = FlatRamBus::new();
+    bus.write(0xffff, 0x42);
+    assert_eq!(bus.peek(0xffff), 0x42);
+    assert_eq!(bus.read(0xffff), 0x42);
+    assert_eq!(bus.peek(0x0000), 0x00);
+}
+#[test]
+fn test_cpu_reads_have_side_effects() {
+    let mut cpu = CPU::with_bus(Box::new(ReadClearsBus {
+        ram: FlatRamBus::new(),
+        status: 0x80,
+    }));
+    cpu.load_and_run(vec![
+        0xad, 0x02, 0x20, // LDA $2002
+        0xae, 0x02, 0x20, // LDX $2002
+        0x00,
+    ])
+    .unwrap();
+    assert_eq!(cpu.accumulator, 0x80);
+    assert_eq!(cpu.register_x, 0x00);
+}
+#[test]
+fn test_peek_has_no_side_effects() {
+    let mut cpu = CPU::with_bus(Box::new(ReadClearsBus {
+        ram: FlatRamBus::new(),
+        status: 0x80,
+    }));
+    assert_eq!(cpu.memory_peek_byte(0x2002), 0x80);
+    assert_eq!(cpu.memory_peek_byte(0x2002), 0x80);
+    assert_eq!(cpu.memory_read_byte(0x2002), 0x80);
+    assert_eq!(cpu.memory_peek_byte(0x2002), 0x00);
+}
+#[test]
+fn test_step_result_operands_do_not_touch_registers() {
+    let mut cpu = CPU::with_bus(Box::new(ReadClearsBus {
+        ram: FlatRamBus::new(),
+        status: 0x80,
+    }));
+    // a NOP right before the register: the trailing bytes are only peeked for the StepResult
+    cpu.program_counter = 0x2000;
+    cpu.memory_write_byte(0x2000, 0xea);
+    let step = cpu.step().unwrap();
+    assert_eq!(step.operand_bytes, [0x00, 0x80]);
+    assert_eq!(cpu.memory_peek_byte(0x2002), 0x80);
+}
+#[test]
+fn test_nmi_raised_during_brk_hijacks_vector() {
+    let mut cpu = CPU::with_bus(Box::new(NmiOnStackWriteBus {
+        ram: FlatRamBus::new(),
+        nmi: false,
+    }));
+    cpu.memory_write_2_byte(0xfffa, 0xa000);
+    cpu.memory_write_2_byte(0xfffe, 0x9000);
+    cpu.load_and_run(vec![0x00]).unwrap();
+    assert_eq!(cpu.program_counter, 0xa000);
+    // the pushed status still says BRK
+    assert_eq!(
+        cpu.memory_read_byte(0x01fb),
+        INTERRUPT_DISABLE_FLAG | BREAK_FLAG | ALWAYS_1_FLAG
+    );
+    assert!(!cpu.interrupt_lines.is_nmi_pending());
+}
+#[test]
+fn test_bus_can_raise_nmi() {
+    let mut cpu = CPU::with_bus(Box::new(NmiOnStackWriteBus {
+        ram: FlatRamBus::new(),
+        nmi: false,
+    }));
+    cpu.memory_write_2_byte(0xfffa, 0xa000);
+    cpu.load(vec![0x48, 0xea, 0x00]); // PHA
+    cpu.reset();
+    cpu.step().unwrap();
+    let step = cpu.step().unwrap();
+    assert_eq!(step.interrupt, Some(Interrupt::Nmi));
+    assert_eq!(step.program_counter, 0xa000);
+}
+#[test]
+fn test_read_modify_write_writes_old_value_first() {
+    let writes = Rc::new(RefCell::new(Vec::new()));
+    let ticks = Rc::new(RefCell::new(Vec::new()));
+    let mut cpu = CPU::with_bus(Box::new(RecordingBus {
+        ram: FlatRamBus::new(),
+        writes: writes.clone(),
+        ticks: ticks.clone(),
+    }));
+    cpu.load(vec![0xee, 0x00, 0x02, 0x0e, 0x00, 0x02]); // INC $0200, ASL $0200
+    cpu.memory_write_byte(0x0200, 0x41);
+    cpu.reset();
+    writes.borrow_mut().clear();
+    cpu.step().unwrap();
+    cpu.step().unwrap();
+    assert_eq!(
+        *writes.borrow(),
+        vec![
+            (0x0200, 0x41),
+            (0x0200, 0x42),
+            (0x0200, 0x42),
+            (0x0200, 0x84)
+        ]
+    );
+    assert_eq!(*ticks.borrow(), vec![6, 6]);
+}
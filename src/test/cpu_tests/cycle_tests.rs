@@ -0,0 +1,85 @@
+use super::cpu_with_program;
+
+#[test]
+fn test_reset_takes_seven_cycles() {
+    let cpu = cpu_with_program(vec![0xea]);
+    assert_eq!(cpu.cycles, 7);
+}
+#[test]
+fn test_step_returns_opcode_cycles() {
+    let mut cpu = cpu_with_program(vec![0xa9, 0x01, 0xad, 0x00, 0x02, 0xe6, 0x10, 0x00]);
+    assert_eq!(cpu.step().unwrap().cycles, 2);
+    assert_eq!(cpu.step().unwrap().cycles, 4);
+    assert_eq!(cpu.step().unwrap().cycles, 5);
+    assert_eq!(cpu.step().unwrap().cycles, 7);
+    assert_eq!(cpu.cycles, 7 + 2 + 4 + 5 + 7);
+}
+#[test]
+fn test_absolute_x_page_cross_penalty() {
+    let mut cpu = cpu_with_program(vec![
+        0xa2, 0x01, // LDX #$01
+        0xbd, 0xfe, 0x02, // LDA $02fe,X
+        0xbd, 0xff, 0x02, // LDA $02ff,X
+    ]);
+    cpu.step().unwrap();
+    assert_eq!(cpu.step().unwrap().cycles, 4);
+    assert_eq!(cpu.step().unwrap().cycles, 5);
+}
+#[test]
+fn test_absolute_y_page_cross_penalty() {
+    let mut cpu = cpu_with_program(vec![
+        0xa0, 0x10, // LDY #$10
+        0xb9, 0xf0, 0x02, // LDA $02f0,Y
+        0x79, 0xef, 0x02, // ADC $02ef,Y
+    ]);
+    cpu.step().unwrap();
+    assert_eq!(cpu.step().unwrap().cycles, 5);
+    assert_eq!(cpu.step().unwrap().cycles, 4);
+}
+#[test]
+fn test_indirect_y_page_cross_penalty() {
+    let mut cpu = cpu_with_program(vec![
+        0xa0, 0x01, // LDY #$01
+        0xb1, 0x10, // LDA ($10),Y
+        0xb1, 0x12, // LDA ($12),Y
+    ]);
+    cpu.memory_write_2_byte(0x10, 0x02ff);
+    cpu.memory_write_2_byte(0x12, 0x0200);
+    cpu.step().unwrap();
+    assert_eq!(cpu.step().unwrap().cycles, 6);
+    assert_eq!(cpu.step().unwrap().cycles, 5);
+}
+#[test]
+fn test_stores_have_no_page_cross_penalty() {
+    let mut cpu = cpu_with_program(vec![
+        0xa2, 0x01, // LDX #$01
+        0x9d, 0xff, 0x02, // STA $02ff,X
+    ]);
+    cpu.step().unwrap();
+    assert_eq!(cpu.step().unwrap().cycles, 5);
+}
+#[test]
+fn test_branch_cycles() {
+    let mut cpu = cpu_with_program(vec![
+        0x38, // SEC
+        0x90, 0x10, // BCC, not taken
+        0xb0, 0x00, // BCS, taken to the same page
+    ]);
+    cpu.step().unwrap();
+    assert_eq!(cpu.step().unwrap().cycles, 2);
+    assert_eq!(cpu.step().unwrap().cycles, 3);
+}
+#[test]
+fn test_branch_page_cross_cycles() {
+    let mut cpu = cpu_with_program(vec![0x38, 0xb0, 0x80]);
+    cpu.step().unwrap();
+    assert_eq!(cpu.step().unwrap().cycles, 4);
+    assert_eq!(cpu.program_counter, 0x7f83);
+}
+#[test]
+fn test_interrupt_adds_seven_cycles() {
+    let mut cpu = cpu_with_program(vec![0xea]);
+    cpu.memory_write_2_byte(0xfffa, 0x8000);
+    cpu.interrupt_lines.set_nmi_line(true);
+    assert_eq!(cpu.step().unwrap().cycles, 7 + 2);
+}
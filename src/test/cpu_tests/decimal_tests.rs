@@ -0,0 +1,105 @@
+use crate::cpu::*;
+
+fn to_bcd(value: u8) -> u8 {
+    ((value / 10) << 4) | (value % 10)
+}
+#[test]
+fn test_nes_variant_ignores_decimal_flag() {
+    let mut cpu = CPU::new();
+    cpu.load_and_run(vec![0xf8, 0xa9, 0x09, 0x69, 0x01, 0x00])
+        .unwrap();
+    assert_eq!(cpu.accumulator, 0x0a);
+}
+#[test]
+fn test_decimal_adc() {
+    let mut cpu = CPU::with_variant(CpuVariant::Nmos6502);
+    cpu.load_and_run(vec![0xf8, 0xa9, 0x09, 0x69, 0x01, 0x00])
+        .unwrap();
+    assert_eq!(cpu.accumulator, 0x10);
+    assert_eq!(cpu.status & CARRY_FLAG, 0);
+}
+#[test]
+fn test_decimal_adc_carry_and_binary_zero_flag() {
+    let mut cpu = CPU::with_variant(CpuVariant::Nmos6502);
+    cpu.load_and_run(vec![0xf8, 0xa9, 0x99, 0x69, 0x01, 0x00])
+        .unwrap();
+    assert_eq!(cpu.accumulator, 0x00);
+    assert_eq!(cpu.status & CARRY_FLAG, CARRY_FLAG);
+    // the NMOS 6502 takes Z from the binary sum $9a
+    assert_eq!(cpu.status & ZERO_FLAG, 0);
+}
+#[test]
+fn test_decimal_adc_negative_and_overflow_from_intermediate() {
+    let mut cpu = CPU::with_variant(CpuVariant::Nmos6502);
+    cpu.load_and_run(vec![0xf8, 0x38, 0xa9, 0x79, 0x69, 0x00, 0x00])
+        .unwrap();
+    assert_eq!(cpu.accumulator, 0x80);
+    assert_eq!(cpu.status & NEGATIVE_FLAG, NEGATIVE_FLAG);
+    assert_eq!(cpu.status & OVERFLOW_FLAG, OVERFLOW_FLAG);
+}
+#[test]
+fn test_decimal_sbc_borrow() {
+    let mut cpu = CPU::with_variant(CpuVariant::Nmos6502);
+    cpu.load_and_run(vec![0xf8, 0x38, 0xa9, 0x00, 0xe9, 0x01, 0x00])
+        .unwrap();
+    assert_eq!(cpu.accumulator, 0x99);
+    assert_eq!(cpu.status & CARRY_FLAG, 0);
+    assert_eq!(cpu.status & NEGATIVE_FLAG, NEGATIVE_FLAG);
+}
+#[test]
+fn test_decimal_cld_returns_to_binary() {
+    let mut cpu = CPU::with_variant(CpuVariant::Nmos6502);
+    cpu.load_and_run(vec![0xf8, 0xd8, 0xa9, 0x09, 0x69, 0x01, 0x00])
+        .unwrap();
+    assert_eq!(cpu.accumulator, 0x0a);
+}
+#[test]
+fn test_decimal_adc_all_valid_bcd_operands() {
+    let mut cpu = CPU::with_variant(CpuVariant::Nmos6502);
+    for left in 0..100u8 {
+        for right in 0..100u8 {
+            for carry in 0..=1u8 {
+                let set_carry = if carry == 1 { 0x38 } else { 0x18 };
+                cpu.load_and_run(vec![
+                    0xf8,
+                    set_carry,
+                    0xa9,
+                    to_bcd(left),
+                    0x69,
+                    to_bcd(right),
+                    0x00,
+                ])
+                .unwrap();
+
+                let sum = left as u16 + right as u16 + carry as u16;
+                assert_eq!(cpu.accumulator, to_bcd((sum % 100) as u8));
+                assert_eq!(cpu.status & CARRY_FLAG != 0, sum >= 100);
+            }
+        }
+    }
+}
+#[test]
+fn test_decimal_sbc_all_valid_bcd_operands() {
+    let mut cpu = CPU::with_variant(CpuVariant::Nmos6502);
+    for left in 0..100u8 {
+        for right in 0..100u8 {
+            for carry in 0..=1u8 {
+                let set_carry = if carry == 1 { 0x38 } else { 0x18 };
+                cpu.load_and_run(vec![
+                    0xf8,
+                    set_carry,
+                    0xa9,
+                    to_bcd(left),
+                    0xe9,
+                    to_bcd(right),
+                    0x00,
+                ])
+                .unwrap();
+
+                let difference = left as i16 - right as i16 - (1 - carry as i16);
+                assert_eq!(cpu.accumulator, to_bcd(difference.rem_euclid(100) as u8));
+                assert_eq!(cpu.status & CARRY_FLAG != 0, difference >= 0);
+            }
+        }
+    }
+}
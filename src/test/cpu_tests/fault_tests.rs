@@ -0,0 +1,136 @@
+use super::cpu_with_program;
+use crate::cpu::*;
+
+#[test]
+fn test_illegal_opcode_halts_by_default() {
+    let mut cpu = cpu_with_program(vec![0xe8, 0xff]);
+    cpu.step().unwrap();
+    assert_eq!(
+        cpu.step().unwrap_err(),
+        CpuError::IllegalOpcode {
+            code: 0xff,
+            program_counter: 0x8001
+        }
+    );
+    assert_eq!(cpu.program_counter, 0x8001);
+}
+#[test]
+fn test_illegal_opcode_as_nop() {
+    let mut cpu = cpu_with_program(vec![0xff, 0xe8, 0x00]);
+    cpu.fault_policy.illegal_opcode = IllegalOpcodePolicy::TreatAsNop;
+    let step = cpu.step().unwrap();
+    assert_eq!(step.cycles, 2);
+    assert_eq!(cpu.program_counter, 0x8001);
+    cpu.run().unwrap();
+    assert_eq!(cpu.register_x, 0x01);
+}
+#[test]
+fn test_jam_opcode() {
+    let mut cpu = cpu_with_program(vec![0x02]);
+    assert_eq!(
+        cpu.run().unwrap_err(),
+        CpuError::Jam {
+            code: 0x02,
+            program_counter: 0x8000
+        }
+    );
+}
+#[test]
+fn test_interrupt_cycles_count_when_the_next_opcode_faults() {
+    let mut cpu = cpu_with_program(vec![0x02]);
+    cpu.memory_write_2_byte(0xfffa, 0x8000);
+    cpu.interrupt_lines.set_nmi_line(true);
+    let cycles = cpu.cycles;
+    assert!(cpu.step().is_err());
+    assert_eq!(cpu.cycles, cycles + 7);
+}
+#[test]
+fn test_stack_wraps_by_default() {
+    let mut cpu = cpu_with_program(vec![0xa9, 0x42, 0x48, 0x00]);
+    cpu.stack_pointer = 0x00;
+    cpu.step().unwrap();
+    cpu.step().unwrap();
+    assert_eq!(cpu.memory_read_byte(0x0100), 0x42);
+    assert_eq!(cpu.stack_pointer, 0xff);
+}
+#[test]
+fn test_stack_overflow_halts_with_policy() {
+    let mut cpu = cpu_with_program(vec![0x48]);
+    cpu.fault_policy.stack_wrap = StackWrapPolicy::Halt;
+    cpu.stack_pointer = 0x00;
+    assert_eq!(
+        cpu.step().unwrap_err(),
+        CpuError::StackWrap {
+            stack_pointer: 0x00
+        }
+    );
+}
+#[test]
+fn test_stack_underflow_halts_with_policy() {
+    let mut cpu = cpu_with_program(vec![0x68]);
+    cpu.fault_policy.stack_wrap = StackWrapPolicy::Halt;
+    cpu.stack_pointer = 0xff;
+    assert_eq!(
+        cpu.step().unwrap_err(),
+        CpuError::StackWrap {
+            stack_pointer: 0xff
+        }
+    );
+}
+#[test]
+fn test_compare_below_does_not_panic() {
+    let mut cpu = CPU::new();
+    cpu.load_and_run(vec![0xa9, 0x05, 0xc9, 0x10, 0x00])
+        .unwrap();
+    assert_eq!(cpu.status & CARRY_FLAG, 0);
+    assert_eq!(cpu.status & ZERO_FLAG, 0);
+    assert_eq!(cpu.status & NEGATIVE_FLAG, NEGATIVE_FLAG);
+}
+#[test]
+fn test_compare_equal() {
+    let mut cpu = CPU::new();
+    cpu.load_and_run(vec![0xa2, 0x10, 0xe0, 0x10, 0x00])
+        .unwrap();
+    assert_eq!(
+        cpu.status & (CARRY_FLAG | ZERO_FLAG),
+        CARRY_FLAG | ZERO_FLAG
+    );
+}
+#[test]
+fn test_error_display() {
+    let error = CpuError::IllegalOpcode {
+        code: 0x03,
+        program_counter: 0xc000,
+    };
+    assert_eq!(error.to_string(), "illegal opcode 03 at C000");
+}
+
+fn fill_with_random_bytes(cpu: &mut CPU, mut seed: u32) {
+    for address in 0..=0xffffu16 {
+        // xorshift32
+        seed ^= seed << 13;
+        seed ^= seed >> 17;
+        seed ^= seed << 5;
+        cpu.memory_write_byte(address, seed as u8);
+    }
+}
+#[test]
+fn test_fuzzed_memory_never_panics() {
+    for seed in 1..=16 {
+        let mut cpu = CPU::new();
+        fill_with_random_bytes(&mut cpu, seed);
+        cpu.reset();
+        cpu.fault_policy.illegal_opcode = IllegalOpcodePolicy::TreatAsNop;
+        cpu.run_for_cycles(100_000).unwrap();
+
+        let mut cpu = CPU::new();
+        fill_with_random_bytes(&mut cpu, seed);
+        cpu.reset();
+        cpu.fault_policy.stack_wrap = StackWrapPolicy::Halt;
+        for _ in 0..10_000 {
+            if cpu.step().is_err() {
+                break;
+            }
+        }
+    }
+}
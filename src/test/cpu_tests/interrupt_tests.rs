@@ -0,0 +1,135 @@
+use crate::cpu::*;
+use crate::interrupt::*;
+
+#[test]
+fn test_reset_loads_vector_and_disables_interrupts() {
+    let mut cpu = CPU::new();
+    cpu.memory_write_2_byte(0xfffc, 0x1234);
+    cpu.reset();
+    assert_eq!(cpu.program_counter, 0x1234);
+    assert_eq!(cpu.stack_pointer, 0xfd);
+    assert_eq!(cpu.status, INTERRUPT_DISABLE_FLAG);
+}
+#[test]
+fn test_brk_pushes_return_address_and_status_with_break_flag() {
+    let mut cpu = CPU::new();
+    cpu.memory_write_2_byte(0xfffe, 0x9000);
+    cpu.load_and_run(vec![0xea, 0x00, 0xff]).unwrap();
+    assert_eq!(cpu.program_counter, 0x9000);
+    assert_eq!(cpu.memory_read_2_byte(0x01fc), 0x8003);
+    assert_eq!(
+        cpu.memory_read_byte(0x01fb),
+        INTERRUPT_DISABLE_FLAG | BREAK_FLAG | ALWAYS_1_FLAG
+    );
+    assert_eq!(cpu.stack_pointer, 0xfa);
+    assert_eq!(cpu.status & BREAK_FLAG, 0);
+}
+#[test]
+fn test_rti_resumes_after_brk_padding_byte() {
+    let mut cpu = CPU::new();
+    cpu.memory_write_2_byte(0xfffe, 0x9000);
+    cpu.memory_write_byte(0x9000, 0x40);
+    cpu.load_and_run(vec![0x00, 0xff, 0xa2, 0x05, 0x00])
+        .unwrap();
+    cpu.run().unwrap();
+    assert_eq!(cpu.register_x, 0x05);
+    assert_eq!(cpu.memory_read_2_byte(0x01fc), 0x8006);
+}
+#[test]
+fn test_irq_is_serviced_when_enabled() {
+    let mut cpu = CPU::new();
+    cpu.memory_write_2_byte(0xfffe, 0x9000);
+    cpu.interrupt_lines.assert_irq(IrqSource::Cartridge);
+    cpu.load_and_run(vec![0x58, 0xea, 0x00]).unwrap();
+    // the IRQ handler at $9000 is a BRK, which ends the run
+    assert_eq!(cpu.memory_read_2_byte(0x01fc), 0x8001);
+    assert_eq!(cpu.memory_read_byte(0x01fb), ALWAYS_1_FLAG);
+}
+#[test]
+fn test_irq_is_ignored_while_interrupts_disabled() {
+    let mut cpu = CPU::new();
+    cpu.memory_write_2_byte(0xfffe, 0x9000);
+    cpu.interrupt_lines.assert_irq(IrqSource::ApuFrameCounter);
+    cpu.load_and_run(vec![0xa2, 0x01, 0x00]).unwrap();
+    assert_eq!(cpu.register_x, 0x01);
+    assert_eq!(cpu.memory_read_2_byte(0x01fc), 0x8004);
+}
+#[test]
+fn test_released_irq_is_not_serviced() {
+    let mut cpu = CPU::new();
+    cpu.memory_write_2_byte(0xfffe, 0x9000);
+    cpu.interrupt_lines.assert_irq(IrqSource::Cartridge);
+    cpu.interrupt_lines.assert_irq(IrqSource::ApuDmc);
+    cpu.interrupt_lines.release_irq(IrqSource::Cartridge);
+    assert!(cpu.interrupt_lines.is_irq_asserted());
+    cpu.interrupt_lines.release_irq(IrqSource::ApuDmc);
+    cpu.load_and_run(vec![0x58, 0xea, 0x00]).unwrap();
+    assert_eq!(cpu.memory_read_2_byte(0x01fc), 0x8004);
+}
+#[test]
+fn test_nmi_is_edge_triggered() {
+    let mut cpu = CPU::new();
+    cpu.memory_write_2_byte(0xfffa, 0x9000);
+    cpu.memory_write_2_byte(0xfffe, 0xa000);
+    cpu.memory_write_byte(0x9000, 0xe8);
+    cpu.memory_write_byte(0x9001, 0x40);
+    cpu.load(vec![0xea, 0xea, 0x00]);
+    cpu.reset();
+    cpu.interrupt_lines.set_nmi_line(true);
+    cpu.run().unwrap();
+    assert_eq!(cpu.register_x, 0x01);
+    assert_eq!(cpu.program_counter, 0xa000);
+
+    // the line is still asserted, so there is no new edge
+    cpu.program_counter = 0x8000;
+    cpu.run().unwrap();
+    assert_eq!(cpu.register_x, 0x01);
+
+    cpu.interrupt_lines.set_nmi_line(false);
+    cpu.interrupt_lines.set_nmi_line(true);
+    cpu.program_counter = 0x8000;
+    cpu.run().unwrap();
+    assert_eq!(cpu.register_x, 0x02);
+}
+#[test]
+fn test_nmi_pushes_status_without_break_flag() {
+    let mut cpu = CPU::new();
+    cpu.memory_write_2_byte(0xfffa, 0x9000);
+    cpu.load(vec![0xea, 0x00]);
+    cpu.reset();
+    cpu.interrupt_lines.set_nmi_line(true);
+    cpu.run().unwrap();
+    // the NMI handler at $9000 is a BRK
+    assert_eq!(cpu.memory_read_2_byte(0x01fc), 0x8000);
+    assert_eq!(
+        cpu.memory_read_byte(0x01fb),
+        INTERRUPT_DISABLE_FLAG | ALWAYS_1_FLAG
+    );
+}
+#[test]
+fn test_php_pushes_break_and_bit_5() {
+    let mut cpu = CPU::new();
+    cpu.load_and_run(vec![
+        0x38, // SEC
+        0x08, // PHP
+        0x68, // PLA
+        0x00,
+    ])
+    .unwrap();
+    assert_eq!(
+        cpu.accumulator,
+        INTERRUPT_DISABLE_FLAG | CARRY_FLAG | BREAK_FLAG | ALWAYS_1_FLAG
+    );
+    assert_eq!(cpu.status & (BREAK_FLAG | ALWAYS_1_FLAG), 0);
+}
+#[test]
+fn test_plp_drops_break_and_bit_5() {
+    let mut cpu = CPU::new();
+    cpu.load_and_run(vec![
+        0x08, // PHP
+        0x28, // PLP
+        0x00,
+    ])
+    .unwrap();
+    assert_eq!(cpu.status & (BREAK_FLAG | ALWAYS_1_FLAG), 0);
+}
@@ -21,7 +21,8 @@ fn test_inx_overflow() {
         0xe8, 0xe8, 0xe8, 0xe8, 0xe8, 0xe8, 0xe8, 0xe8, 0xe8, 0xe8, 0xe8, 0xe8, 0xe8, 0xe8, 0xe8,
         0xe8, 0xe8, 0xe8, 0xe8, 0xe8, 0xe8, 0xe8, 0xe8, 0xe8, 0xe8, 0xe8, 0xe8, 0xe8, 0xe8, 0xe8,
         0xe8, 0xe8, 0x00,
-    ]);
+    ])
+    .unwrap();
 
     assert_eq!(cpu.register_x, 1)
 }
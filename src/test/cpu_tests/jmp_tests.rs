@@ -0,0 +1,45 @@
+use crate::cpu::*;
+
+#[test]
+fn test_jmp_absolute_jumps_directly() {
+    let mut cpu = CPU::new();
+    cpu.load_and_run(vec![
+        0x4c, 0x05, 0x80, // JMP $8005
+        0xa9, 0x01, // LDA #$01
+        0xa2, 0x02, // LDX #$02
+        0x00,
+    ])
+    .unwrap();
+    assert_eq!(cpu.accumulator, 0x00);
+    assert_eq!(cpu.register_x, 0x02);
+}
+#[test]
+fn test_jmp_indirect() {
+    let mut cpu = CPU::new();
+    cpu.memory_write_2_byte(0x0120, 0x8005);
+    cpu.load_and_run(vec![
+        0x6c, 0x20, 0x01, // JMP ($0120)
+        0xa9, 0x01, // LDA #$01
+        0xa2, 0x02, // LDX #$02
+        0x00,
+    ])
+    .unwrap();
+    assert_eq!(cpu.accumulator, 0x00);
+    assert_eq!(cpu.register_x, 0x02);
+}
+#[test]
+fn test_jmp_indirect_page_boundary_bug() {
+    let mut cpu = CPU::new();
+    cpu.memory_write_byte(0x02ff, 0x05);
+    cpu.memory_write_byte(0x0200, 0x80);
+    cpu.memory_write_byte(0x0300, 0x90);
+    cpu.load_and_run(vec![
+        0x6c, 0xff, 0x02, // JMP ($02ff)
+        0xa9, 0x01, // LDA #$01
+        0xa2, 0x02, // LDX #$02
+        0x00,
+    ])
+    .unwrap();
+    assert_eq!(cpu.accumulator, 0x00);
+    assert_eq!(cpu.register_x, 0x02);
+}
@@ -0,0 +1,46 @@
+use crate::cpu::*;
+
+#[test]
+fn test_jsr_pushes_address_of_last_operand_byte() {
+    let mut cpu = CPU::new();
+    cpu.memory_write_2_byte(0xfffe, 0xa000);
+    cpu.memory_write_byte(0x9000, 0x00);
+    cpu.load_and_run(vec![0xea, 0x20, 0x00, 0x90]).unwrap();
+    assert_eq!(cpu.memory_read_2_byte(0x01fc), 0x8003);
+    assert_eq!(cpu.program_counter, 0xa000);
+}
+#[test]
+fn test_jsr_rts_returns_after_jsr() {
+    let mut cpu = CPU::new();
+    cpu.memory_write_byte(0x9000, 0xe8); // INX
+    cpu.memory_write_byte(0x9001, 0x60); // RTS
+    cpu.load_and_run(vec![
+        0x20, 0x00, 0x90, // JSR $9000
+        0x20, 0x00, 0x90, // JSR $9000
+        0xa0, 0x03, // LDY #$03
+        0x00,
+    ])
+    .unwrap();
+    assert_eq!(cpu.register_x, 0x02);
+    assert_eq!(cpu.register_y, 0x03);
+}
+#[test]
+fn test_nested_jsr_rts() {
+    let mut cpu = CPU::new();
+    cpu.memory_write_byte(0x9000, 0x20); // JSR $9100
+    cpu.memory_write_2_byte(0x9001, 0x9100);
+    cpu.memory_write_byte(0x9003, 0xc8); // INY
+    cpu.memory_write_byte(0x9004, 0x60); // RTS
+    cpu.memory_write_byte(0x9100, 0xe8); // INX
+    cpu.memory_write_byte(0x9101, 0x60); // RTS
+    cpu.load_and_run(vec![
+        0x20, 0x00, 0x90, // JSR $9000
+        0xa9, 0x07, // LDA #$07
+        0x00,
+    ])
+    .unwrap();
+    assert_eq!(cpu.register_x, 0x01);
+    assert_eq!(cpu.register_y, 0x01);
+    assert_eq!(cpu.accumulator, 0x07);
+    assert_eq!(cpu.stack_pointer, 0xfa);
+}
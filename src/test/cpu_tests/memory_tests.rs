@@ -0,0 +1,103 @@
+use super::cpu_with_program;
+use crate::cpu::*;
+
+#[test]
+fn test_last_address_is_addressable() {
+    let mut cpu = CPU::new();
+    cpu.memory_write_byte(0xffff, 0x42);
+    assert_eq!(cpu.memory_read_byte(0xffff), 0x42);
+}
+#[test]
+fn test_vector_area() {
+    let mut cpu = CPU::new();
+    cpu.memory_write_2_byte(0xfffa, 0x1111);
+    cpu.memory_write_2_byte(0xfffc, 0x2222);
+    cpu.memory_write_2_byte(0xfffe, 0x3333);
+    assert_eq!(cpu.memory_read_2_byte(0xfffa), 0x1111);
+    assert_eq!(cpu.memory_read_2_byte(0xfffc), 0x2222);
+    assert_eq!(cpu.memory_read_2_byte(0xfffe), 0x3333);
+    assert_eq!(cpu.memory_read_byte(0xffff), 0x33);
+}
+#[test]
+fn test_2_byte_access_wraps_at_end_of_memory() {
+    let mut cpu = CPU::new();
+    cpu.memory_write_2_byte(0xffff, 0xabcd);
+    assert_eq!(cpu.memory_read_byte(0xffff), 0xcd);
+    assert_eq!(cpu.memory_read_byte(0x0000), 0xab);
+    assert_eq!(cpu.memory_read_2_byte(0xffff), 0xabcd);
+}
+#[test]
+fn test_irq_vector_at_top_of_memory() {
+    let mut cpu = CPU::new();
+    cpu.memory_write_2_byte(0xfffe, 0xc123);
+    cpu.load_and_run(vec![0x00]).unwrap();
+    assert_eq!(cpu.program_counter, 0xc123);
+}
+#[test]
+fn test_program_counter_wraps_to_zero_page() {
+    let mut cpu = CPU::new();
+    cpu.memory_write_byte(0xffff, 0xe8); // INX
+    cpu.memory_write_byte(0x0000, 0xe8); // INX
+    cpu.program_counter = 0xffff;
+    cpu.step().unwrap();
+    assert_eq!(cpu.program_counter, 0x0000);
+    cpu.step().unwrap();
+    assert_eq!(cpu.register_x, 0x02);
+}
+#[test]
+fn test_operand_wraps_to_zero_page() {
+    let mut cpu = CPU::new();
+    cpu.memory_write_byte(0xfffe, 0xad); // LDA $1234
+    cpu.memory_write_byte(0xffff, 0x34);
+    cpu.memory_write_byte(0x0000, 0x12);
+    cpu.memory_write_byte(0x1234, 0x77);
+    cpu.program_counter = 0xfffe;
+    cpu.step().unwrap();
+    assert_eq!(cpu.accumulator, 0x77);
+    assert_eq!(cpu.program_counter, 0x0001);
+}
+#[test]
+fn test_push_wraps_within_stack_page() {
+    let mut cpu = cpu_with_program(vec![
+        0xa9, 0x11, // LDA #$11
+        0x48, // PHA
+        0xa9, 0x22, // LDA #$22
+        0x48, // PHA
+    ]);
+    cpu.stack_pointer = 0x00;
+    for _ in 0..4 {
+        cpu.step().unwrap();
+    }
+    assert_eq!(cpu.memory_read_byte(0x0100), 0x11);
+    assert_eq!(cpu.memory_read_byte(0x01ff), 0x22);
+    assert_eq!(cpu.memory_read_byte(0x0200), 0x00);
+    assert_eq!(cpu.stack_pointer, 0xfe);
+}
+#[test]
+fn test_pull_wraps_within_stack_page() {
+    let mut cpu = cpu_with_program(vec![0x68, 0x68]);
+    cpu.memory_write_byte(0x0100, 0x33);
+    cpu.memory_write_byte(0x01ff, 0x44);
+    cpu.stack_pointer = 0xfe;
+    cpu.step().unwrap();
+    assert_eq!(cpu.accumulator, 0x44);
+    cpu.step().unwrap();
+    assert_eq!(cpu.accumulator, 0x33);
+    assert_eq!(cpu.stack_pointer, 0x00);
+}
+#[test]
+fn test_jsr_rts_across_stack_wrap() {
+    let mut cpu = cpu_with_program(vec![
+        0x20, 0x00, 0x90, // JSR $9000
+        0xa2, 0x05, // LDX #$05
+        0x00,
+    ]);
+    cpu.memory_write_byte(0x9000, 0x60); // RTS
+    cpu.stack_pointer = 0x00;
+    cpu.step().unwrap();
+    assert_eq!(cpu.memory_read_byte(0x0100), 0x80);
+    assert_eq!(cpu.memory_read_byte(0x01ff), 0x02);
+    assert_eq!(cpu.stack_pointer, 0xfe);
+    cpu.run().unwrap();
+    assert_eq!(cpu.register_x, 0x05);
+}
@@ -1,7 +1,29 @@
+use crate::cpu::*;
+
 mod adc_tests;
 mod and_tests;
 mod asl_tests;
 mod bcc_tests;
+mod branch_tests;
+mod bus_tests;
+mod cycle_tests;
+mod decimal_tests;
+mod fault_tests;
+mod interrupt_tests;
 mod inx_tests;
+mod jmp_tests;
+mod jsr_tests;
 mod lda_tests;
+mod memory_tests;
+mod program_tests;
+mod sbc_tests;
+mod step_tests;
 mod tax_tests;
+
+// a CPU reset into a program loaded at $8000
+pub fn cpu_with_program(program: Vec<u8>) -> CPU {
+    let mut cpu = CPU::new();
+    cpu.load(program);
+    cpu.reset();
+    cpu
+}
@@ -0,0 +1,248 @@
+use crate::cpu::*;
+
+#[test]
+fn test_operand_bytes_are_not_executed() {
+    let mut cpu = CPU::new();
+    cpu.load_and_run(vec![0xa9, 0x00, 0xa2, 0x05, 0x00])
+        .unwrap();
+    assert_eq!(cpu.accumulator, 0x00);
+    assert_eq!(cpu.register_x, 0x05);
+}
+#[test]
+fn test_immediate_zeropage_absolute_program() {
+    let mut cpu = CPU::new();
+    cpu.load_and_run(vec![
+        0xa9, 0x42, // LDA #$42
+        0x85, 0x10, // STA $10
+        0xa5, 0x10, // LDA $10
+        0x8d, 0x00, 0x02, // STA $0200
+        0xad, 0x00, 0x02, // LDA $0200
+        0xaa, // TAX
+        0x00,
+    ])
+    .unwrap();
+    assert_eq!(cpu.memory_read_byte(0x10), 0x42);
+    assert_eq!(cpu.memory_read_byte(0x0200), 0x42);
+    assert_eq!(cpu.register_x, 0x42);
+}
+#[test]
+fn test_indexed_program() {
+    let mut cpu = CPU::new();
+    cpu.memory_write_byte(0x15, 0x11);
+    cpu.memory_write_byte(0x0305, 0x22);
+    cpu.memory_write_byte(0x0307, 0x33);
+    cpu.memory_write_byte(0x27, 0x44);
+    cpu.load_and_run(vec![
+        0xa2, 0x05, // LDX #$05
+        0xb5, 0x10, // LDA $10,X
+        0x85, 0x40, // STA $40
+        0xbd, 0x00, 0x03, // LDA $0300,X
+        0x85, 0x41, // STA $41
+        0xa0, 0x07, // LDY #$07
+        0xb9, 0x00, 0x03, // LDA $0300,Y
+        0x85, 0x42, // STA $42
+        0xb6, 0x20, // LDX $20,Y
+        0x00,
+    ])
+    .unwrap();
+    assert_eq!(cpu.memory_read_byte(0x40), 0x11);
+    assert_eq!(cpu.memory_read_byte(0x41), 0x22);
+    assert_eq!(cpu.memory_read_byte(0x42), 0x33);
+    assert_eq!(cpu.register_x, 0x44);
+}
+#[test]
+fn test_indirect_indexed_program() {
+    let mut cpu = CPU::new();
+    cpu.memory_write_2_byte(0x24, 0x0400);
+    cpu.memory_write_byte(0x0400, 0x55);
+    cpu.memory_write_2_byte(0x30, 0x0500);
+    cpu.memory_write_byte(0x0503, 0x66);
+    cpu.load_and_run(vec![
+        0xa2, 0x04, // LDX #$04
+        0xa1, 0x20, // LDA ($20,X)
+        0x85, 0x40, // STA $40
+        0xa0, 0x03, // LDY #$03
+        0xb1, 0x30, // LDA ($30),Y
+        0x85, 0x41, // STA $41
+        0x00,
+    ])
+    .unwrap();
+    assert_eq!(cpu.memory_read_byte(0x40), 0x55);
+    assert_eq!(cpu.memory_read_byte(0x41), 0x66);
+}
+#[test]
+fn test_accumulator_implied_program() {
+    let mut cpu = CPU::new();
+    cpu.load_and_run(vec![
+        0xa9, 0x21, // LDA #$21
+        0x0a, // ASL A
+        0xaa, // TAX
+        0xe8, // INX
+        0x00,
+    ])
+    .unwrap();
+    assert_eq!(cpu.accumulator, 0x42);
+    assert_eq!(cpu.register_x, 0x43);
+}
+#[test]
+fn test_relative_taken_branch_skips_instruction() {
+    let mut cpu = CPU::new();
+    cpu.load_and_run(vec![
+        0x90, 0x04, // BCC
+        0xa9, 0x01, // LDA #$01
+        0xea, 0xea, 0xea, // NOP sled
+        0xa2, 0x07, // LDX #$07
+        0x00,
+    ])
+    .unwrap();
+    assert_eq!(cpu.accumulator, 0x00);
+    assert_eq!(cpu.register_x, 0x07);
+}
+#[test]
+fn test_relative_not_taken_branch_continues() {
+    let mut cpu = CPU::new();
+    cpu.load_and_run(vec![
+        0xb0, 0x04, // BCS
+        0xa9, 0x01, // LDA #$01
+        0xea, 0xea, 0xea, // NOP sled
+        0xa2, 0x07, // LDX #$07
+        0x00,
+    ])
+    .unwrap();
+    assert_eq!(cpu.accumulator, 0x01);
+    assert_eq!(cpu.register_x, 0x07);
+}
+#[test]
+fn test_indirect_jump_program() {
+    let mut cpu = CPU::new();
+    cpu.memory_write_2_byte(0x0300, 0x8006);
+    cpu.load_and_run(vec![
+        0x6c, 0x00, 0x03, // JMP ($0300)
+        0xa9, 0x01, // LDA #$01
+        0x00, // BRK
+        0xa2, 0x09, // LDX #$09
+        0x00,
+    ])
+    .unwrap();
+    assert_eq!(cpu.accumulator, 0x00);
+    assert_eq!(cpu.register_x, 0x09);
+}
+#[test]
+fn test_store_x_and_y_program() {
+    let mut cpu = CPU::new();
+    cpu.load_and_run(vec![
+        0xa2, 0x11, // LDX #$11
+        0xa0, 0x22, // LDY #$22
+        0x86, 0x10, // STX $10
+        0x84, 0x11, // STY $11
+        0x8e, 0x00, 0x02, // STX $0200
+        0x8c, 0x01, 0x02, // STY $0201
+        0x96, 0x20, // STX $20,Y
+        0x94, 0x30, // STY $30,X
+        0x00,
+    ])
+    .unwrap();
+    assert_eq!(cpu.memory_read_byte(0x10), 0x11);
+    assert_eq!(cpu.memory_read_byte(0x11), 0x22);
+    assert_eq!(cpu.memory_read_byte(0x0200), 0x11);
+    assert_eq!(cpu.memory_read_byte(0x0201), 0x22);
+    assert_eq!(cpu.memory_read_byte(0x42), 0x11);
+    assert_eq!(cpu.memory_read_byte(0x41), 0x22);
+    assert_eq!(cpu.register_x, 0x11);
+    assert_eq!(cpu.register_y, 0x22);
+}
+#[test]
+fn test_zero_page_indexed_stores_wrap_in_zero_page() {
+    let mut cpu = CPU::new();
+    cpu.load_and_run(vec![
+        0xa2, 0x33, // LDX #$33
+        0xa0, 0x44, // LDY #$44
+        0x96, 0xf0, // STX $F0,Y
+        0x94, 0xf0, // STY $F0,X
+        0x00,
+    ])
+    .unwrap();
+    assert_eq!(cpu.memory_read_byte(0x34), 0x33);
+    assert_eq!(cpu.memory_read_byte(0x23), 0x44);
+}
+#[test]
+fn test_lsr_program() {
+    let mut cpu = CPU::new();
+    cpu.memory_write_byte(0x10, 0x01);
+    cpu.load_and_run(vec![
+        0xa9, 0x03, // LDA #$03
+        0x4a, // LSR A
+        0x00,
+    ])
+    .unwrap();
+    assert_eq!(cpu.accumulator, 0x01);
+    assert_eq!(cpu.status & CARRY_FLAG, CARRY_FLAG);
+    assert_eq!(cpu.status & ZERO_FLAG, 0);
+
+    let mut cpu = CPU::new();
+    cpu.memory_write_byte(0x10, 0x01);
+    cpu.load_and_run(vec![
+        0x46, 0x10, // LSR $10
+        0x00,
+    ])
+    .unwrap();
+    assert_eq!(cpu.memory_read_byte(0x10), 0x00);
+    assert_eq!(
+        cpu.status & (CARRY_FLAG | ZERO_FLAG | NEGATIVE_FLAG),
+        CARRY_FLAG | ZERO_FLAG
+    );
+}
+#[test]
+fn test_ror_program() {
+    let mut cpu = CPU::new();
+    cpu.load_and_run(vec![
+        0x38, // SEC
+        0xa9, 0x02, // LDA #$02
+        0x6a, // ROR A
+        0x00,
+    ])
+    .unwrap();
+    assert_eq!(cpu.accumulator, 0x81);
+    assert_eq!(cpu.status & (CARRY_FLAG | NEGATIVE_FLAG), NEGATIVE_FLAG);
+
+    let mut cpu = CPU::new();
+    cpu.memory_write_byte(0x0200, 0x01);
+    cpu.load_and_run(vec![
+        0x18, // CLC
+        0x6e, 0x00, 0x02, // ROR $0200
+        0x00,
+    ])
+    .unwrap();
+    assert_eq!(cpu.memory_read_byte(0x0200), 0x00);
+    assert_eq!(
+        cpu.status & (CARRY_FLAG | ZERO_FLAG | NEGATIVE_FLAG),
+        CARRY_FLAG | ZERO_FLAG
+    );
+}
+#[test]
+fn test_rol_program() {
+    let mut cpu = CPU::new();
+    cpu.load_and_run(vec![
+        0x38, // SEC
+        0xa9, 0x40, // LDA #$40
+        0x2a, // ROL A
+        0x00,
+    ])
+    .unwrap();
+    assert_eq!(cpu.accumulator, 0x81);
+    assert_eq!(cpu.status & (CARRY_FLAG | NEGATIVE_FLAG), NEGATIVE_FLAG);
+
+    let mut cpu = CPU::new();
+    cpu.memory_write_byte(0x10, 0x80);
+    cpu.load_and_run(vec![
+        0x18, // CLC
+        0x26, 0x10, // ROL $10
+        0x00,
+    ])
+    .unwrap();
+    assert_eq!(cpu.memory_read_byte(0x10), 0x00);
+    assert_eq!(
+        cpu.status & (CARRY_FLAG | ZERO_FLAG | NEGATIVE_FLAG),
+        CARRY_FLAG | ZERO_FLAG
+    );
+}
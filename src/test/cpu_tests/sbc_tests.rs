@@ -0,0 +1,72 @@
+use crate::cpu::*;
+#[test]
+fn test_sbc_immediate_with_carry_set() {
+    let mut cpu = CPU::new();
+    cpu.load_and_run(vec![0xa9, 0x0a, 0x38, 0xe9, 0x03, 0x00])
+        .unwrap();
+    assert_eq!(cpu.accumulator, 0x07);
+    assert_eq!(cpu.status, CARRY_FLAG | INTERRUPT_DISABLE_FLAG);
+}
+#[test]
+fn test_sbc_borrow() {
+    let mut cpu = CPU::new();
+    cpu.load_and_run(vec![0xa9, 0x00, 0x38, 0xe9, 0x01, 0x00])
+        .unwrap();
+    assert_eq!(cpu.accumulator, 0xff);
+    assert_eq!(cpu.status, NEGATIVE_FLAG | INTERRUPT_DISABLE_FLAG);
+}
+#[test]
+fn test_sbc_signed_overflow() {
+    let mut cpu = CPU::new();
+    cpu.load_and_run(vec![0xa9, 0x80, 0x38, 0xe9, 0x01, 0x00])
+        .unwrap();
+    assert_eq!(cpu.accumulator, 0x7f);
+    assert_eq!(
+        cpu.status,
+        CARRY_FLAG | OVERFLOW_FLAG | INTERRUPT_DISABLE_FLAG
+    );
+}
+#[test]
+fn test_sbc_16_bit_subtraction() {
+    let mut cpu = CPU::new();
+    cpu.memory_write_2_byte(0x10, 0x0200);
+    cpu.memory_write_2_byte(0x12, 0x0001);
+    cpu.load_and_run(vec![
+        0x38, // SEC
+        0xa5, 0x10, // LDA $10
+        0xe5, 0x12, // SBC $12
+        0x85, 0x14, // STA $14
+        0xa5, 0x11, // LDA $11
+        0xe5, 0x13, // SBC $13
+        0x85, 0x15, // STA $15
+        0x00,
+    ])
+    .unwrap();
+    assert_eq!(cpu.memory_read_2_byte(0x14), 0x01ff);
+}
+#[test]
+fn test_sbc_all_operands_against_reference() {
+    let mut cpu = CPU::new();
+    for accumulator in 0..=0xffu8 {
+        for operand in 0..=0xffu8 {
+            for carry in 0..=1u8 {
+                let set_carry = if carry == 1 { 0x38 } else { 0x18 };
+                cpu.load_and_run(vec![0xa9, accumulator, set_carry, 0xe9, operand, 0x00])
+                    .unwrap();
+
+                let borrow = 1 - carry as i16;
+                let unsigned = accumulator as i16 - operand as i16 - borrow;
+                let signed = accumulator as i8 as i16 - operand as i8 as i16 - borrow;
+                let expected = unsigned as u8;
+                assert_eq!(cpu.accumulator, expected);
+                assert_eq!(cpu.status & CARRY_FLAG != 0, unsigned >= 0);
+                assert_eq!(
+                    cpu.status & OVERFLOW_FLAG != 0,
+                    !(-128..=127).contains(&signed)
+                );
+                assert_eq!(cpu.status & ZERO_FLAG != 0, expected == 0);
+                assert_eq!(cpu.status & NEGATIVE_FLAG != 0, expected >= 0x80);
+            }
+        }
+    }
+}
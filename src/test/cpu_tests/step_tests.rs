@@ -0,0 +1,78 @@
+use super::cpu_with_program;
+use crate::interrupt::*;
+use crate::opcode::*;
+
+#[test]
+fn test_step_executes_exactly_one_instruction() {
+    let mut cpu = cpu_with_program(vec![0xa9, 0x05, 0xaa, 0x00]);
+    let step = cpu.step().unwrap();
+    assert_eq!(cpu.accumulator, 0x05);
+    assert_eq!(cpu.register_x, 0x00);
+    assert_eq!(cpu.program_counter, 0x8002);
+    assert_eq!(step.program_counter, 0x8000);
+    assert_eq!(*step.opcode.get_instruction(), Instruction::LDA);
+    assert!(matches!(step.addressing_mode(), AddressingMode::Immediate));
+    assert_eq!(step.operands(), &[0x05]);
+    assert_eq!(step.cycles, 2);
+    assert_eq!(step.interrupt, None);
+}
+#[test]
+fn test_step_reports_absolute_operands() {
+    let mut cpu = cpu_with_program(vec![0x8d, 0x34, 0x12]);
+    let step = cpu.step().unwrap();
+    assert_eq!(step.operands(), &[0x34, 0x12]);
+    assert_eq!(cpu.memory_read_byte(0x1234), 0x00);
+}
+#[test]
+fn test_step_reports_implied_without_operands() {
+    let mut cpu = cpu_with_program(vec![0xe8]);
+    let step = cpu.step().unwrap();
+    assert!(step.operands().is_empty());
+}
+#[test]
+fn test_step_reports_taken_interrupt() {
+    let mut cpu = cpu_with_program(vec![0xea]);
+    cpu.memory_write_2_byte(0xfffa, 0x9000);
+    cpu.memory_write_byte(0x9000, 0xe8);
+    cpu.interrupt_lines.set_nmi_line(true);
+    let step = cpu.step().unwrap();
+    assert_eq!(step.interrupt, Some(Interrupt::Nmi));
+    assert_eq!(step.program_counter, 0x9000);
+    assert_eq!(*step.opcode.get_instruction(), Instruction::INX);
+    assert_eq!(step.cycles, 7 + 2);
+    assert_eq!(cpu.step().unwrap().interrupt, None);
+}
+#[test]
+fn test_run_until_predicate() {
+    let mut cpu = cpu_with_program(vec![
+        0xe8, // INX
+        0x4c, 0x00, 0x80, // JMP $8000
+    ]);
+    let cycles = cpu.run_until(|cpu, _| cpu.register_x == 0x10).unwrap();
+    assert_eq!(cpu.register_x, 0x10);
+    // 16 INX and 15 JMP
+    assert_eq!(cycles, 16 * 2 + 15 * 3);
+}
+#[test]
+fn test_run_until_sees_step_result() {
+    let mut cpu = cpu_with_program(vec![0xe8, 0xe8, 0xc8, 0xe8]);
+    cpu.run_until(|_, step| *step.opcode.get_instruction() == Instruction::INY)
+        .unwrap();
+    assert_eq!(cpu.register_x, 0x02);
+    assert_eq!(cpu.program_counter, 0x8003);
+}
+#[test]
+fn test_run_for_cycles_stops_at_instruction_boundary() {
+    let mut cpu = cpu_with_program(vec![
+        0xe8, // INX
+        0x4c, 0x00, 0x80, // JMP $8000
+    ]);
+    let cycles = cpu.run_for_cycles(10).unwrap();
+    // INX JMP INX JMP = 10 cycles
+    assert_eq!(cycles, 10);
+    assert_eq!(cpu.register_x, 0x02);
+
+    let cycles = cpu.run_for_cycles(1).unwrap();
+    assert_eq!(cycles, 2);
+    assert_eq!(cpu.register_x, 0x03);
+}
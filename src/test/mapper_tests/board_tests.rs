@@ -0,0 +1,98 @@
+use super::{mapper_from, small_prg_mapper};
+use crate::mapper::*;
+
+// boards keeping their battery-backed memory in PRG-RAM alone
+const PRG_RAM_BATTERY_MAPPERS: [u8; 12] = [0, 1, 4, 5, 9, 10, 21, 23, 24, 25, 69, 85];
+
+// bank register writes pointing every switchable PRG window at bank 3, which isn't there
+const VRC_PRG_BANK_WRITES: &[(u16, u8)] = &[(0x8000, 3), (0x9008, 3), (0xa000, 3), (0xc000, 3)];
+const SMALL_PRG_BANK_WRITES: [(u8, &[(u16, u8)]); 13] = [
+    (2, &[(0x8000, 3)]),
+    (
+        4,
+        &[(0x8000, 0x46), (0x8001, 3), (0x8000, 0x47), (0x8001, 3)],
+    ),
+    (9, &[(0xa000, 3)]),
+    (10, &[(0xa000, 3)]),
+    (19, &[(0xe000, 3), (0xe800, 3), (0xf000, 3)]),
+    (21, VRC_PRG_BANK_WRITES),
+    (22, VRC_PRG_BANK_WRITES),
+    (23, VRC_PRG_BANK_WRITES),
+    (24, VRC_PRG_BANK_WRITES),
+    (25, VRC_PRG_BANK_WRITES),
+    (26, VRC_PRG_BANK_WRITES),
+    (
+        69,
+        &[
+            (0x8000, 9),
+            (0xa000, 3),
+            (0x8000, 0x0a),
+            (0xa000, 3),
+            (0x8000, 0x0b),
+            (0xa000, 3),
+        ],
+    ),
+    (85, VRC_PRG_BANK_WRITES),
+];
+
+// NES 2.0 board with two 16 KiB PRG banks, CHR-RAM and 8 KiB of PRG-RAM, battery-backed or not
+fn prg_ram_mapper(mapper: u8, has_battery: bool) -> Box<dyn Mapper> {
+    let flags_6 = (mapper << 4) | if has_battery { 0x02 } else { 0x00 };
+    let prg_ram = if has_battery { 0x70 } else { 0x07 };
+    mapper_from(
+        &[2, 0, flags_6, (mapper & 0xf0) | 0x08, 0, 0, prg_ram, 0x07],
+        &[0; 0x8000],
+        &[],
+    )
+}
+#[test]
+fn test_battery_prg_ram_round_trip() {
+    for number in PRG_RAM_BATTERY_MAPPERS {
+        let mut mapper = prg_ram_mapper(number, true);
+        assert_eq!(mapper.battery_ram(), vec![0; 0x2000], "mapper {number}");
+        let saved: Vec<u8> = (0..0x2000).map(|offset| offset as u8).collect();
+        mapper.load_battery_ram(&saved);
+        assert_eq!(mapper.battery_ram(), saved, "mapper {number}");
+    }
+}
+#[test]
+fn test_no_battery_saves_nothing() {
+    for number in PRG_RAM_BATTERY_MAPPERS {
+        let mut mapper = prg_ram_mapper(number, false);
+        mapper.load_battery_ram(&[0x5a; 0x2000]);
+        assert!(mapper.battery_ram().is_empty(), "mapper {number}");
+    }
+}
+#[test]
+fn test_battery_ram_of_another_size_is_ignored() {
+    let mut mapper = prg_ram_mapper(1, true);
+    mapper.load_battery_ram(&[0x5a; 0x1000]);
+    assert_eq!(mapper.battery_ram(), vec![0; 0x2000]);
+}
+#[test]
+fn test_loaded_battery_ram_is_visible_to_the_cpu() {
+    let mut mapper = prg_ram_mapper(0, true);
+    mapper.load_battery_ram(&[0x5a; 0x2000]);
+    assert_eq!(mapper.cpu_read(0x6000), Some(0x5a));
+    assert_eq!(mapper.cpu_read(0x7fff), Some(0x5a));
+}
+// every 8 KiB PRG window shows the one bank
+fn assert_prg_mirrors_single_bank(mapper: &dyn Mapper) {
+    for address in (0x8000..=0xffffu16).step_by(0x0100) {
+        assert_eq!(
+            mapper.cpu_peek(address),
+            Some(((address >> 8) & 0x1f) as u8)
+        );
+    }
+}
+#[test]
+fn test_8_kib_prg_fills_every_slot() {
+    for (number, writes) in SMALL_PRG_BANK_WRITES {
+        let mut mapper = small_prg_mapper(number);
+        assert_prg_mirrors_single_bank(mapper.as_ref());
+        for &(address, data) in writes {
+            mapper.cpu_write(address, data);
+        }
+        assert_prg_mirrors_single_bank(mapper.as_ref());
+    }
+}
@@ -0,0 +1,183 @@
+use super::{ines_image, numbered_banks};
+use crate::bus::*;
+use crate::cartridge::*;
+use crate::cpu::*;
+use crate::mapper::*;
+
+// hand-assembled, placed at $F000 of every 32 KiB window: switches to two banks through
+// the bank table at $FF00 (each byte holds its own offset, so bus conflicts are harmless)
+// and stores the bank id found at $8000 after each switch to $00 and $01
+fn bank_switch_program(first: u8, second: u8) -> Vec<u8> {
+    vec![
+        0xa9, first, // LDA #first
+        0x8d, first, 0xff, // STA $FF00+first
+        0xad, 0x00, 0x80, // LDA $8000
+        0x85, 0x00, // STA $00
+        0xa9, second, // LDA #second
+        0x8d, second, 0xff, // STA $FF00+second
+        0xad, 0x00, 0x80, // LDA $8000
+        0x85, 0x01, // STA $01
+        0x00, // BRK
+    ]
+}
+// NES 2.0 image, PRG filled with the number of each prg_bank_size bank, CHR with the 8 KiB bank
+fn fixture_image(
+    mapper: u8,
+    submapper: u8,
+    prg_banks: u8,
+    chr_banks: u8,
+    prg_bank_size: usize,
+    program: &[u8],
+) -> Vec<u8> {
+    let header = [
+        prg_banks,
+        chr_banks,
+        mapper << 4,
+        (mapper & 0xf0) | 0x08,
+        submapper << 4,
+        0,
+        0,
+        if chr_banks == 0 { 0x07 } else { 0 },
+    ];
+    let mut prg_rom = numbered_banks(prg_banks as usize * 0x4000 / prg_bank_size, prg_bank_size);
+    for window in prg_rom.chunks_mut(0x8000) {
+        window[0x7000..0x7000 + program.len()].copy_from_slice(program);
+        for (offset, byte) in window[0x7f00..].iter_mut().enumerate() {
+            *byte = offset as u8;
+        }
+        window[0x7ffc] = 0x00;
+        window[0x7ffd] = 0xf0;
+    }
+    ines_image(
+        &header,
+        &prg_rom,
+        &numbered_banks(chr_banks as usize, 0x2000),
+    )
+}
+fn mapper_from_image(image: &[u8]) -> Box<dyn Mapper> {
+    create_mapper(Cartridge::from_ines(image).unwrap()).unwrap()
+}
+// runs the fixture and returns the two bank ids it stored
+fn run_fixture(image: &[u8]) -> (u8, u8) {
+    let mut bus = NesBus::new();
+    bus.insert_cartridge(mapper_from_image(image));
+    let mut cpu = CPU::with_bus(Box::new(bus));
+    cpu.reset();
+    cpu.run().unwrap();
+    (cpu.memory_read_byte(0x00), cpu.memory_read_byte(0x01))
+}
+
+#[test]
+fn test_uxrom_fixture_switches_8000() {
+    let image = fixture_image(2, 0, 8, 0, 0x4000, &bank_switch_program(1, 2));
+    assert_eq!(run_fixture(&image), (1, 2));
+}
+#[test]
+fn test_uxrom_last_bank_is_fixed() {
+    let mut mapper = mapper_from_image(&fixture_image(2, 1, 8, 0, 0x4000, &[]));
+    assert_eq!(mapper.cpu_peek(0xc000), Some(7));
+    mapper.cpu_write(0x8000, 3);
+    assert_eq!(mapper.cpu_peek(0x8000), Some(3));
+    assert_eq!(mapper.cpu_peek(0xc000), Some(7));
+    assert_eq!(mapper.cpu_peek(0x6000), None);
+}
+#[test]
+fn test_uxrom_bus_conflicts_from_submapper() {
+    let mut clean = mapper_from_image(&fixture_image(2, 1, 8, 0, 0x4000, &[]));
+    let mut conflicting = mapper_from_image(&fixture_image(2, 2, 8, 0, 0x4000, &[]));
+    assert!(!clean.has_bus_conflicts());
+    assert!(conflicting.has_bus_conflicts());
+    // $8000 holds bank id 0, the conflicting board can never leave bank 0 through it
+    clean.cpu_write(0x8000, 3);
+    conflicting.cpu_write(0x8000, 3);
+    assert_eq!(clean.cpu_peek(0x8000), Some(3));
+    assert_eq!(conflicting.cpu_peek(0x8000), Some(0));
+    // $FF06 holds 6 in the bank table
+    conflicting.cpu_write(0xff06, 0xff);
+    assert_eq!(conflicting.cpu_peek(0x8000), Some(6));
+}
+#[test]
+fn test_uxrom_fixture_survives_bus_conflicts() {
+    let image = fixture_image(2, 2, 8, 0, 0x4000, &bank_switch_program(1, 2));
+    assert_eq!(run_fixture(&image), (1, 2));
+}
+#[test]
+fn test_cnrom_switches_chr() {
+    let mut mapper = mapper_from_image(&fixture_image(3, 1, 2, 4, 0x4000, &[]));
+    assert_eq!(mapper.ppu_peek(0x0000), 0);
+    mapper.cpu_write(0x8000, 2);
+    assert_eq!(mapper.ppu_peek(0x0000), 2);
+    assert_eq!(mapper.ppu_peek(0x1fff), 2);
+    // CHR-ROM stays read-only and PRG is fixed
+    mapper.ppu_write(0x0000, 0xff);
+    assert_eq!(mapper.ppu_peek(0x0000), 2);
+    assert_eq!(mapper.cpu_peek(0xc000), Some(1));
+}
+#[test]
+fn test_cnrom_bus_conflicts() {
+    let mut mapper = mapper_from_image(&fixture_image(3, 2, 2, 4, 0x4000, &[]));
+    // $FF02 holds 2 in the bank table, $8000 holds bank id 0
+    mapper.cpu_write(0xff02, 3);
+    assert_eq!(mapper.ppu_peek(0x0000), 2);
+    mapper.cpu_write(0x8000, 3);
+    assert_eq!(mapper.ppu_peek(0x0000), 0);
+}
+#[test]
+fn test_axrom_fixture_switches_32_kib() {
+    let image = fixture_image(7, 0, 8, 0, 0x8000, &bank_switch_program(1, 2));
+    assert_eq!(run_fixture(&image), (1, 2));
+}
+#[test]
+fn test_axrom_single_screen_selection() {
+    let mut mapper = mapper_from_image(&fixture_image(7, 1, 8, 0, 0x8000, &[]));
+    assert_eq!(mapper.mirroring(), Mirroring::SingleScreenLower);
+    mapper.cpu_write(0x8000, 0x13);
+    assert_eq!(mapper.mirroring(), Mirroring::SingleScreenUpper);
+    assert_eq!(mapper.cpu_peek(0x8000), Some(3));
+    mapper.cpu_write(0x8000, 0x03);
+    assert_eq!(mapper.mirroring(), Mirroring::SingleScreenLower);
+}
+#[test]
+fn test_axrom_chr_ram() {
+    let mut mapper = mapper_from_image(&fixture_image(7, 1, 8, 0, 0x8000, &[]));
+    mapper.ppu_write(0x0123, 0x42);
+    assert_eq!(mapper.ppu_read(0x0123), 0x42);
+}
+#[test]
+fn test_gxrom_fixture_switches_prg() {
+    let image = fixture_image(66, 0, 8, 4, 0x8000, &bank_switch_program(0x10, 0x20));
+    assert_eq!(run_fixture(&image), (1, 2));
+}
+#[test]
+fn test_gxrom_switches_chr_and_prg_together() {
+    let mut mapper = mapper_from_image(&fixture_image(66, 0, 8, 4, 0x8000, &[]));
+    mapper.cpu_write(0xff23, 0x23);
+    assert_eq!(mapper.cpu_peek(0x8000), Some(2));
+    assert_eq!(mapper.ppu_peek(0x0000), 3);
+}
+#[test]
+fn test_color_dreams_fixture_switches_prg() {
+    let image = fixture_image(11, 0, 8, 4, 0x8000, &bank_switch_program(1, 2));
+    assert_eq!(run_fixture(&image), (1, 2));
+}
+#[test]
+fn test_color_dreams_switches_chr_and_prg_together() {
+    let mut mapper = mapper_from_image(&fixture_image(11, 0, 8, 16, 0x8000, &[]));
+    mapper.cpu_write(0xffa3, 0xa3);
+    assert_eq!(mapper.cpu_peek(0x8000), Some(3));
+    assert_eq!(mapper.ppu_peek(0x0000), 10);
+}
+#[test]
+fn test_gxrom_and_color_dreams_always_have_bus_conflicts() {
+    for mapper in [66, 11] {
+        for submapper in 0..=2 {
+            let mut mapper =
+                mapper_from_image(&fixture_image(mapper, submapper, 8, 4, 0x8000, &[]));
+            assert!(mapper.has_bus_conflicts());
+            // $8000 holds bank id 0, so the write selects bank 0
+            mapper.cpu_write(0x8000, 0x33);
+            assert_eq!(mapper.cpu_peek(0x8000), Some(0));
+            assert_eq!(mapper.ppu_peek(0x0000), 0);
+        }
+    }
+}
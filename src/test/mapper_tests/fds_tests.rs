@@ -0,0 +1,296 @@
+use super::mapper_from;
+use crate::bus::*;
+use crate::cartridge::*;
+use crate::mapper::fds::*;
+use crate::mapper::*;
+
+// a side holding the disk info block and one 4-byte file
+fn disk_side() -> Vec<u8> {
+    let mut side = vec![0x01];
+    side.extend_from_slice(b"*NINTENDO-HVC*");
+    side.resize(56, 0x11);
+    side.extend_from_slice(&[0x02, 0x01]);
+    side.extend_from_slice(&[0x03, 0x00, 0x00]);
+    side.extend_from_slice(b"FILENAME");
+    side.extend_from_slice(&[0x00, 0x60, 0x04, 0x00, 0x00]);
+    side.extend_from_slice(&[0x04, 0xde, 0xad, 0xbe, 0xef]);
+    side.resize(DISK_SIDE_SIZE, 0);
+    side
+}
+fn fds_image(side_count: u8, has_header: bool) -> Vec<u8> {
+    let mut image = Vec::new();
+    if has_header {
+        image.extend_from_slice(&[b'F', b'D', b'S', 0x1a, side_count]);
+        image.resize(16, 0);
+    }
+    for _ in 0..side_count {
+        image.extend(disk_side());
+    }
+    image
+}
+fn fds(side_count: u8) -> Fds {
+    let mut bios = vec![0; BIOS_SIZE];
+    bios[BIOS_SIZE - 4] = 0x24;
+    let disk = FdsDisk::from_bytes(&fds_image(side_count, true)).unwrap();
+    Fds::new(bios, disk).unwrap()
+}
+// runs the drive and collects every transferred byte, like the BIOS polling $4030
+fn read_bytes(fds: &mut Fds, count: usize) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    for _ in 0..1_000_000 {
+        fds.cpu_clock();
+        if fds.cpu_peek(0x4030).unwrap() & 0x02 == 0x02 {
+            bytes.push(fds.cpu_read(0x4031).unwrap());
+            if bytes.len() == count {
+                break;
+            }
+        }
+    }
+    bytes
+}
+fn write_bytes(fds: &mut Fds, bytes: &[u8]) {
+    fds.cpu_write(0x4024, bytes[0]);
+    let mut remaining = bytes[1..].iter();
+    for _ in 0..1_000_000 {
+        fds.cpu_clock();
+        if fds.cpu_peek(0x4030).unwrap() & 0x02 == 0x02 {
+            match remaining.next() {
+                Some(byte) => fds.cpu_write(0x4024, *byte),
+                None => break,
+            }
+        }
+    }
+}
+
+#[test]
+fn test_parse_with_and_without_header() {
+    let with_header = FdsDisk::from_bytes(&fds_image(2, true)).unwrap();
+    let without_header = FdsDisk::from_bytes(&fds_image(2, false)).unwrap();
+    assert!(with_header.has_header);
+    assert!(!without_header.has_header);
+    assert_eq!(with_header.sides, without_header.sides);
+    assert_eq!(with_header.sides.len(), 2);
+    assert_eq!(with_header.to_bytes(), fds_image(2, true));
+    assert_eq!(without_header.to_bytes(), fds_image(2, false));
+}
+#[test]
+fn test_headerless_image_ignores_trailing_bytes() {
+    let mut image = fds_image(2, false);
+    image.extend_from_slice(&[0xff; 0x100]);
+    let disk = FdsDisk::from_bytes(&image).unwrap();
+    assert_eq!(disk.sides.len(), 2);
+    assert_eq!(disk.to_bytes(), fds_image(2, false));
+    assert_eq!(
+        FdsDisk::from_bytes(&[0xff; 0x100]),
+        Err(FdsError::NoDiskSides)
+    );
+}
+#[test]
+fn test_parse_errors() {
+    let mut image = fds_image(2, true);
+    image.truncate(16 + DISK_SIDE_SIZE);
+    assert_eq!(
+        FdsDisk::from_bytes(&image),
+        Err(FdsError::Truncated {
+            expected: 2 * DISK_SIDE_SIZE,
+            actual: DISK_SIDE_SIZE
+        })
+    );
+    assert_eq!(FdsDisk::from_bytes(&[]), Err(FdsError::NoDiskSides));
+    let disk = FdsDisk::from_bytes(&fds_image(1, false)).unwrap();
+    assert_eq!(
+        Fds::new(vec![0; 0x1000], disk).err(),
+        Some(FdsError::InvalidBiosSize { length: 0x1000 })
+    );
+}
+#[test]
+fn test_memory_map() {
+    let mut fds = fds(1);
+    assert_eq!(fds.cpu_peek(0xfffc), Some(0x24));
+    fds.cpu_write(0x6000, 0x12);
+    fds.cpu_write(0xdfff, 0x34);
+    fds.cpu_write(0xfffc, 0x56);
+    assert_eq!(fds.cpu_peek(0x6000), Some(0x12));
+    assert_eq!(fds.cpu_peek(0xdfff), Some(0x34));
+    assert_eq!(fds.cpu_peek(0xfffc), Some(0x24));
+    fds.ppu_write(0x1234, 0x78);
+    assert_eq!(fds.ppu_peek(0x1234), 0x78);
+    assert_eq!(fds.mirroring(), Mirroring::Vertical);
+    fds.cpu_write(0x4025, 0x08);
+    assert_eq!(fds.mirroring(), Mirroring::Horizontal);
+}
+#[test]
+fn test_timer_irq() {
+    let mut fds = fds(1);
+    fds.cpu_write(0x4020, 0x02);
+    fds.cpu_write(0x4021, 0x00);
+    fds.cpu_write(0x4022, 0x03);
+    fds.cpu_clock();
+    fds.cpu_clock();
+    assert!(!fds.irq_asserted());
+    fds.cpu_clock();
+    assert!(fds.irq_asserted());
+    assert_eq!(fds.cpu_read(0x4030).unwrap() & 0x01, 0x01);
+    assert!(!fds.irq_asserted());
+    // repeating: reloaded and counting again
+    for _ in 0..3 {
+        fds.cpu_clock();
+    }
+    assert!(fds.irq_asserted());
+    // disabling the disk registers stops the timer
+    fds.cpu_write(0x4023, 0x00);
+    assert!(!fds.irq_asserted());
+    assert_eq!(fds.cpu_peek(0x4030), None);
+}
+#[test]
+fn test_reading_the_first_block() {
+    let mut fds = fds(1);
+    // motor on, read mode, transfer started
+    fds.cpu_write(0x4025, 0x65);
+    let bytes = read_bytes(&mut fds, 16);
+    // the start mark comes through as well
+    assert_eq!(bytes[0], 0x80);
+    assert_eq!(bytes[1], 0x01);
+    assert_eq!(&bytes[2..], b"*NINTENDO-HVC*");
+    assert_eq!(fds.cpu_peek(0x4032).unwrap() & 0x03, 0x00);
+}
+#[test]
+fn test_transfer_irq() {
+    let mut fds = fds(1);
+    fds.cpu_write(0x4025, 0xe5);
+    let mut cycles = 0;
+    while !fds.irq_asserted() && cycles < 1_000_000 {
+        fds.cpu_clock();
+        cycles += 1;
+    }
+    assert_eq!(fds.cpu_read(0x4031), Some(0x01));
+    assert!(!fds.irq_asserted());
+}
+#[test]
+fn test_eject_and_insert() {
+    let mut fds = fds(2);
+    assert_eq!(fds.side_count(), 2);
+    assert_eq!(fds.inserted_side(), Some(0));
+    fds.eject_disk();
+    assert_eq!(fds.inserted_side(), None);
+    assert_eq!(fds.cpu_peek(0x4032).unwrap() & 0x07, 0x07);
+    assert!(!fds.insert_disk(2));
+    assert!(fds.insert_disk(1));
+    assert_eq!(fds.inserted_side(), Some(1));
+    assert_eq!(fds.cpu_peek(0x4032).unwrap() & 0x01, 0x00);
+}
+#[test]
+fn test_writes_are_saved_to_the_image() {
+    let mut fds = fds(1);
+    let mut block = vec![0x01];
+    block.extend_from_slice(b"*NINTENDO-HVC*");
+    block.resize(56, 0x22);
+    let mut written = vec![0x80];
+    written.extend_from_slice(&block);
+    // motor on, write mode, transfer started
+    fds.cpu_write(0x4025, 0x61);
+    write_bytes(&mut fds, &written);
+    // then the CRC
+    fds.cpu_write(0x4025, 0x71);
+    for _ in 0..1000 {
+        fds.cpu_clock();
+    }
+    fds.cpu_write(0x4025, 0x00);
+    let disk = fds.disk();
+    assert_eq!(&disk.sides[0][..56], &block[..]);
+    let saved = fds.battery_ram();
+    assert_eq!(saved, disk.to_bytes());
+    // a fresh adapter reads the saved image back
+    let mut restored = self::fds(1);
+    restored.load_battery_ram(&saved);
+    restored.cpu_write(0x4025, 0x65);
+    assert_eq!(read_bytes(&mut restored, 57)[1..], block[..]);
+}
+#[test]
+fn test_wave_output() {
+    let mut fds = fds(1);
+    fds.cpu_write(0x4089, 0x80);
+    for index in 0..64 {
+        fds.cpu_write(0x4040 + index, if index < 32 { 0x3f } else { 0x00 });
+    }
+    fds.cpu_write(0x4089, 0x00);
+    assert_eq!(fds.cpu_peek(0x4040), Some(0x3f));
+    // direct volume 32, wave running
+    fds.cpu_write(0x4080, 0xa0);
+    fds.cpu_write(0x4082, 0x00);
+    fds.cpu_write(0x4083, 0x04);
+    let levels: Vec<f32> = (0..5000)
+        .map(|_| {
+            fds.cpu_clock();
+            fds.audio_output()
+        })
+        .collect();
+    assert!(levels.iter().any(|level| *level > 0.3));
+    assert!(levels.contains(&0.0));
+    assert_eq!(fds.cpu_peek(0x4090), Some(0x20));
+}
+#[test]
+fn test_wave_table_is_write_protected() {
+    let mut fds = fds(1);
+    fds.cpu_write(0x4040, 0x3f);
+    assert_eq!(fds.cpu_peek(0x4040), Some(0x00));
+    // the sound registers can be switched off
+    fds.cpu_write(0x4023, 0x01);
+    assert_eq!(fds.cpu_peek(0x4040), None);
+}
+#[test]
+fn test_volume_envelope() {
+    let mut fds = fds(1);
+    fds.cpu_write(0x408a, 0x01);
+    fds.cpu_write(0x4083, 0x00);
+    // increasing, speed 0: a step every 8 cycles
+    fds.cpu_write(0x4080, 0x40);
+    for _ in 0..9 * 10 {
+        fds.cpu_clock();
+    }
+    assert_eq!(fds.cpu_peek(0x4090), Some(10));
+}
+#[test]
+fn test_modulation_bends_pitch() {
+    let positions_after = |modulate: bool| {
+        let mut fds = fds(1);
+        fds.cpu_write(0x4089, 0x80);
+        for index in 0..64 {
+            fds.cpu_write(0x4040 + index, index as u8);
+        }
+        fds.cpu_write(0x4089, 0x00);
+        fds.cpu_write(0x4080, 0xa0);
+        fds.cpu_write(0x4082, 0x00);
+        fds.cpu_write(0x4083, 0x01);
+        if modulate {
+            // a table of +4 steps at full modulation depth
+            for _ in 0..32 {
+                fds.cpu_write(0x4088, 0x03);
+            }
+            fds.cpu_write(0x4084, 0xa0);
+            fds.cpu_write(0x4086, 0x00);
+            fds.cpu_write(0x4087, 0x08);
+        }
+        for _ in 0..2000 {
+            fds.cpu_clock();
+        }
+        fds.audio_output()
+    };
+    assert_ne!(positions_after(true), positions_after(false));
+}
+#[test]
+fn test_host_swaps_disk_through_bus() {
+    let mut bus = NesBus::new();
+    bus.insert_cartridge(Box::new(fds(2)));
+    let drive = bus.cartridge_mut().unwrap().disk_drive().unwrap();
+    drive.eject_disk();
+    assert_eq!(drive.inserted_side(), None);
+    assert!(drive.insert_disk(1));
+    assert_eq!(drive.inserted_side(), Some(1));
+    assert_eq!(bus.cartridge().unwrap().battery_ram(), fds_image(2, true));
+}
+#[test]
+fn test_cartridges_have_no_disk_drive() {
+    let mut mapper = mapper_from(&[1, 1], &[0; 0x4000], &[0; 0x2000]);
+    assert!(mapper.disk_drive().is_none());
+}
@@ -0,0 +1,162 @@
+use super::{mapper_from, numbered_banks, small_prg_mapper};
+use crate::cartridge::*;
+use crate::mapper::*;
+
+// 16 PRG banks of 8 KiB and 32 CHR banks of 1 KiB, each filled with its number, 8 KiB PRG-RAM
+fn fme7_mapper() -> Box<dyn Mapper> {
+    mapper_from(
+        &[8, 4, 0x50, 0x48, 0, 0, 0x07],
+        &numbered_banks(16, 0x2000),
+        &numbered_banks(32, 0x0400),
+    )
+}
+fn write_command(mapper: &mut Box<dyn Mapper>, command: u8, parameter: u8) {
+    mapper.cpu_write(0x8000, command);
+    mapper.cpu_write(0xa000, parameter);
+}
+fn write_audio(mapper: &mut Box<dyn Mapper>, register: u8, data: u8) {
+    mapper.cpu_write(0xc000, register);
+    mapper.cpu_write(0xe000, data);
+}
+fn output_levels(mapper: &mut Box<dyn Mapper>, cycles: usize) -> Vec<f32> {
+    (0..cycles)
+        .map(|_| {
+            mapper.cpu_clock();
+            mapper.audio_output()
+        })
+        .collect()
+}
+
+#[test]
+fn test_prg_banks() {
+    let mut mapper = fme7_mapper();
+    write_command(&mut mapper, 0x9, 3);
+    write_command(&mut mapper, 0xa, 4);
+    write_command(&mut mapper, 0xb, 5);
+    assert_eq!(mapper.cpu_peek(0x8000), Some(3));
+    assert_eq!(mapper.cpu_peek(0xa000), Some(4));
+    assert_eq!(mapper.cpu_peek(0xc000), Some(5));
+    assert_eq!(mapper.cpu_peek(0xe000), Some(15));
+}
+#[test]
+fn test_6000_window_maps_rom_or_ram() {
+    let mut mapper = fme7_mapper();
+    write_command(&mut mapper, 0x8, 7);
+    assert_eq!(mapper.cpu_peek(0x6000), Some(7));
+    mapper.cpu_write(0x6000, 0x42);
+    assert_eq!(mapper.cpu_peek(0x6000), Some(7));
+    // RAM selected but disabled is open bus
+    write_command(&mut mapper, 0x8, 0x40);
+    assert_eq!(mapper.cpu_peek(0x6000), None);
+    write_command(&mut mapper, 0x8, 0xc0);
+    mapper.cpu_write(0x6000, 0x42);
+    assert_eq!(mapper.cpu_peek(0x6000), Some(0x42));
+}
+#[test]
+fn test_chr_banks_and_mirroring() {
+    let mut mapper = fme7_mapper();
+    for command in 0..8 {
+        write_command(&mut mapper, command, 31 - command);
+    }
+    assert_eq!(mapper.ppu_peek(0x0000), 31);
+    assert_eq!(mapper.ppu_peek(0x1c00), 24);
+    write_command(&mut mapper, 0xc, 3);
+    assert_eq!(mapper.mirroring(), Mirroring::SingleScreenUpper);
+}
+#[test]
+fn test_irq_fires_when_counter_wraps() {
+    let mut mapper = fme7_mapper();
+    write_command(&mut mapper, 0xe, 0x02);
+    write_command(&mut mapper, 0xf, 0x00);
+    write_command(&mut mapper, 0xd, 0x81);
+    mapper.cpu_clock();
+    mapper.cpu_clock();
+    assert!(!mapper.irq_asserted());
+    mapper.cpu_clock();
+    assert!(mapper.irq_asserted());
+    // writing the control register acknowledges
+    write_command(&mut mapper, 0xd, 0x81);
+    assert!(!mapper.irq_asserted());
+}
+#[test]
+fn test_irq_counter_can_run_without_irq() {
+    let mut mapper = fme7_mapper();
+    write_command(&mut mapper, 0xe, 0x00);
+    write_command(&mut mapper, 0xf, 0x00);
+    write_command(&mut mapper, 0xd, 0x80);
+    mapper.cpu_clock();
+    assert!(!mapper.irq_asserted());
+    // halted counter
+    write_command(&mut mapper, 0xd, 0x01);
+    for _ in 0..0x10000 {
+        mapper.cpu_clock();
+    }
+    assert!(!mapper.irq_asserted());
+}
+#[test]
+fn test_5b_square_wave() {
+    let mut mapper = fme7_mapper();
+    // channel A: period 2 (toggles every 32 cycles), full volume, noise off
+    write_audio(&mut mapper, 0, 2);
+    write_audio(&mut mapper, 7, 0b11_1110);
+    write_audio(&mut mapper, 8, 0x0f);
+    let levels = output_levels(&mut mapper, 128);
+    assert!(levels[..31].iter().all(|level| *level == levels[0]));
+    assert_ne!(levels[31], levels[0]);
+    assert!(levels.iter().any(|level| *level > 0.1));
+}
+#[test]
+fn test_5b_disabled_channel_is_constant() {
+    let mut mapper = fme7_mapper();
+    write_audio(&mut mapper, 7, 0b11_1111);
+    write_audio(&mut mapper, 8, 0x0f);
+    let levels = output_levels(&mut mapper, 128);
+    assert!(levels
+        .iter()
+        .all(|level| *level == levels[0] && *level > 0.1));
+    write_audio(&mut mapper, 8, 0x00);
+    assert_eq!(mapper.audio_output(), 0.0);
+}
+#[test]
+fn test_5b_noise() {
+    let mut mapper = fme7_mapper();
+    write_audio(&mut mapper, 6, 1);
+    write_audio(&mut mapper, 7, 0b11_0111);
+    write_audio(&mut mapper, 8, 0x0f);
+    let levels = output_levels(&mut mapper, 16 * 64);
+    assert!(levels.contains(&0.0));
+    assert!(levels.iter().any(|level| *level > 0.1));
+}
+#[test]
+fn test_5b_envelope_decays_and_holds() {
+    let mut mapper = fme7_mapper();
+    write_audio(&mut mapper, 7, 0b11_1111);
+    write_audio(&mut mapper, 8, 0x10);
+    // period 1: a step every 8 cycles, shape 0 decays once then stays silent
+    write_audio(&mut mapper, 11, 1);
+    write_audio(&mut mapper, 13, 0x00);
+    let levels = output_levels(&mut mapper, 8 * 40);
+    assert!(levels[0] > 0.1);
+    assert!(levels.windows(2).all(|pair| pair[1] <= pair[0]));
+    assert_eq!(*levels.last().unwrap(), 0.0);
+}
+#[test]
+fn test_5b_envelope_sawtooth_repeats() {
+    let mut mapper = fme7_mapper();
+    write_audio(&mut mapper, 7, 0b11_1111);
+    write_audio(&mut mapper, 8, 0x10);
+    write_audio(&mut mapper, 11, 1);
+    // continue + attack: rising saw
+    write_audio(&mut mapper, 13, 0b1100);
+    let levels = output_levels(&mut mapper, 8 * 64);
+    assert!(levels[8 * 31 - 1] > 0.1);
+    assert_eq!(levels[8 * 32 - 1], 0.0);
+    assert!(levels[8 * 63 - 1] > 0.1);
+}
+#[test]
+fn test_8_kib_prg_as_rom_at_6000() {
+    let mut mapper = small_prg_mapper(69);
+    write_command(&mut mapper, 8, 3);
+    assert_eq!(mapper.cpu_peek(0x6000), Some(0x00));
+    assert_eq!(mapper.cpu_peek(0x7f00), Some(0x1f));
+}
@@ -0,0 +1,198 @@
+use super::{cartridge_from, numbered_banks};
+use crate::bus::*;
+use crate::cartridge::*;
+use crate::cpu::*;
+use crate::mapper::*;
+
+// every byte of a 16 KiB PRG bank holds the bank number, every 4 KiB CHR bank likewise
+fn mmc1_cartridge(prg_banks: u8, chr_banks: u8) -> Cartridge {
+    cartridge_from(
+        &[prg_banks, chr_banks, 0x10],
+        &numbered_banks(prg_banks as usize, 0x4000),
+        &numbered_banks(chr_banks as usize * 2, 0x1000),
+    )
+}
+fn write_register(mapper: &mut Box<dyn Mapper>, address: u16, value: u8) {
+    for bit in 0..5 {
+        mapper.cpu_write(address, (value >> bit) & 1);
+        mapper.cpu_clock();
+    }
+}
+
+#[test]
+fn test_power_on_fixes_last_bank_at_c000() {
+    let mapper = create_mapper(mmc1_cartridge(8, 1)).unwrap();
+    assert_eq!(mapper.cpu_peek(0x8000), Some(0));
+    assert_eq!(mapper.cpu_peek(0xc000), Some(7));
+    assert_eq!(mapper.cpu_peek(0xffff), Some(7));
+}
+#[test]
+fn test_register_is_written_on_fifth_write() {
+    let mut mapper = create_mapper(mmc1_cartridge(8, 1)).unwrap();
+    for _ in 0..4 {
+        mapper.cpu_write(0xe000, 1);
+        mapper.cpu_clock();
+        assert_eq!(mapper.cpu_peek(0x8000), Some(0));
+    }
+    mapper.cpu_write(0xe000, 0);
+    mapper.cpu_clock();
+    assert_eq!(mapper.cpu_peek(0x8000), Some(7));
+}
+#[test]
+fn test_bit_7_resets_shift_register_and_prg_mode() {
+    let mut mapper = create_mapper(mmc1_cartridge(8, 1)).unwrap();
+    // PRG mode 2: first bank fixed at $8000
+    write_register(&mut mapper, 0x8000, 0b0_1000);
+    write_register(&mut mapper, 0xe000, 5);
+    assert_eq!(mapper.cpu_peek(0x8000), Some(0));
+    assert_eq!(mapper.cpu_peek(0xc000), Some(5));
+    mapper.cpu_write(0x8000, 1);
+    mapper.cpu_clock();
+    mapper.cpu_write(0x8000, 0x80);
+    mapper.cpu_clock();
+    assert_eq!(mapper.cpu_peek(0x8000), Some(5));
+    assert_eq!(mapper.cpu_peek(0xc000), Some(7));
+    // the pending bit was dropped, a full sequence lands in the PRG register again
+    write_register(&mut mapper, 0xe000, 3);
+    assert_eq!(mapper.cpu_peek(0x8000), Some(3));
+}
+#[test]
+fn test_consecutive_writes_are_ignored() {
+    let mut mapper = create_mapper(mmc1_cartridge(8, 1)).unwrap();
+    for _ in 0..4 {
+        mapper.cpu_write(0xe000, 1);
+        mapper.cpu_write(0xe000, 0);
+        mapper.cpu_clock();
+    }
+    mapper.cpu_write(0xe000, 0);
+    mapper.cpu_write(0xe000, 1);
+    mapper.cpu_clock();
+    // only the first write of each pair counted: $0F, wrapped to the 8 banks present
+    assert_eq!(mapper.cpu_peek(0x8000), Some(7));
+}
+#[test]
+fn test_prg_mode_0_switches_32_kib() {
+    let mut mapper = create_mapper(mmc1_cartridge(8, 1)).unwrap();
+    write_register(&mut mapper, 0x8000, 0b0_0000);
+    write_register(&mut mapper, 0xe000, 5);
+    assert_eq!(mapper.cpu_peek(0x8000), Some(4));
+    assert_eq!(mapper.cpu_peek(0xc000), Some(5));
+    write_register(&mut mapper, 0x8000, 0b0_0100);
+    assert_eq!(mapper.cpu_peek(0x8000), Some(4));
+    assert_eq!(mapper.cpu_peek(0xc000), Some(5));
+}
+#[test]
+fn test_prg_mode_3_switches_8000() {
+    let mut mapper = create_mapper(mmc1_cartridge(8, 1)).unwrap();
+    write_register(&mut mapper, 0xe000, 6);
+    assert_eq!(mapper.cpu_peek(0x8000), Some(6));
+    assert_eq!(mapper.cpu_peek(0xc000), Some(7));
+}
+#[test]
+fn test_chr_8k_mode_ignores_low_bit() {
+    let mut mapper = create_mapper(mmc1_cartridge(2, 4)).unwrap();
+    write_register(&mut mapper, 0xa000, 3);
+    write_register(&mut mapper, 0xc000, 7);
+    assert_eq!(mapper.ppu_peek(0x0000), 2);
+    assert_eq!(mapper.ppu_peek(0x1000), 3);
+}
+#[test]
+fn test_chr_4k_mode_switches_both_halves() {
+    let mut mapper = create_mapper(mmc1_cartridge(2, 4)).unwrap();
+    write_register(&mut mapper, 0x8000, 0b1_1100);
+    write_register(&mut mapper, 0xa000, 3);
+    write_register(&mut mapper, 0xc000, 6);
+    assert_eq!(mapper.ppu_peek(0x0000), 3);
+    assert_eq!(mapper.ppu_peek(0x0fff), 3);
+    assert_eq!(mapper.ppu_peek(0x1000), 6);
+}
+#[test]
+fn test_chr_ram_is_writable() {
+    let mut mapper = create_mapper(mmc1_cartridge(2, 0)).unwrap();
+    mapper.ppu_write(0x1234, 0x42);
+    assert_eq!(mapper.ppu_read(0x1234), 0x42);
+}
+#[test]
+fn test_switchable_mirroring() {
+    let mut mapper = create_mapper(mmc1_cartridge(2, 1)).unwrap();
+    let modes = [
+        Mirroring::SingleScreenLower,
+        Mirroring::SingleScreenUpper,
+        Mirroring::Vertical,
+        Mirroring::Horizontal,
+    ];
+    for (value, mirroring) in modes.iter().enumerate() {
+        write_register(&mut mapper, 0x8000, 0b0_1100 | value as u8);
+        assert_eq!(mapper.mirroring(), *mirroring);
+    }
+}
+#[test]
+fn test_prg_ram_enable() {
+    let mut mapper = create_mapper(mmc1_cartridge(2, 1)).unwrap();
+    mapper.cpu_write(0x6000, 0x42);
+    assert_eq!(mapper.cpu_peek(0x6000), Some(0x42));
+    write_register(&mut mapper, 0xe000, 0b1_0000);
+    assert_eq!(mapper.cpu_peek(0x6000), None);
+    mapper.cpu_write(0x6000, 0x11);
+    write_register(&mut mapper, 0xe000, 0b0_0000);
+    assert_eq!(mapper.cpu_peek(0x6000), Some(0x42));
+}
+#[test]
+fn test_surom_selects_outer_256_kib_bank() {
+    let mut cartridge = mmc1_cartridge(16, 0);
+    let upper_half: Vec<u8> = cartridge.prg_rom.iter().map(|bank| bank + 16).collect();
+    cartridge.prg_rom.extend(upper_half);
+    let mut mapper = create_mapper(cartridge).unwrap();
+    assert_eq!(mapper.cpu_peek(0xc000), Some(15));
+    write_register(&mut mapper, 0xa000, 0b1_0000);
+    assert_eq!(mapper.cpu_peek(0x8000), Some(16));
+    assert_eq!(mapper.cpu_peek(0xc000), Some(31));
+    write_register(&mut mapper, 0xe000, 2);
+    assert_eq!(mapper.cpu_peek(0x8000), Some(18));
+}
+#[test]
+fn test_sxrom_banks_32_kib_prg_ram() {
+    let mut cartridge = mmc1_cartridge(2, 0);
+    cartridge.prg_ram_size = 0;
+    cartridge.prg_nvram_size = 0x8000;
+    let mut mapper = create_mapper(cartridge).unwrap();
+    for bank in 0..4 {
+        write_register(&mut mapper, 0xa000, bank << 2);
+        mapper.cpu_write(0x6000, bank);
+        mapper.cpu_clock();
+    }
+    for bank in 0..4 {
+        write_register(&mut mapper, 0xa000, bank << 2);
+        assert_eq!(mapper.cpu_peek(0x6000), Some(bank));
+    }
+}
+#[test]
+fn test_read_modify_write_only_shifts_dummy_write() {
+    let mut cartridge = mmc1_cartridge(16, 0);
+    let program = [
+        0xa9, 0x01, // LDA #$01
+        0x8d, 0x00, 0xe0, // STA $E000
+        0x8d, 0x00, 0xe0, // STA $E000
+        0x8d, 0x00, 0xe0, // STA $E000
+        0x8d, 0x00, 0xe0, // STA $E000
+        0xee, 0xf0, 0xe0, // INC $E0F0 writes $00, then $01 on the next cycle
+        0xa9, 0x42, // LDA #$42
+        0x8d, 0x00, 0x60, // STA $6000
+        0xac, 0x00, 0x60, // LDY $6000
+        0xae, 0x00, 0x81, // LDX $8100
+        0x00,
+    ];
+    // the last bank is fixed at $C000
+    cartridge.prg_rom[0x3c000..0x3c000 + program.len()].copy_from_slice(&program);
+    cartridge.prg_rom[0x3e0f0] = 0x00;
+    cartridge.prg_rom[0x3fffc] = 0x00;
+    cartridge.prg_rom[0x3fffd] = 0xc0;
+    let mut bus = NesBus::new();
+    bus.insert_cartridge(create_mapper(cartridge).unwrap());
+    let mut cpu = CPU::with_bus(Box::new(bus));
+    cpu.reset();
+    cpu.run().unwrap();
+    // bank $0F with PRG-RAM enabled: the $01 of the second write was dropped
+    assert_eq!(cpu.register_y, 0x42);
+    assert_eq!(cpu.register_x, 15);
+}
@@ -0,0 +1,85 @@
+use super::{mapper_from, numbered_banks};
+use crate::cartridge::*;
+use crate::mapper::*;
+
+// 8 PRG banks of 16 KiB with each 8 KiB half numbered, 32 CHR banks of 4 KiB
+fn latch_mapper(mapper: u8) -> Box<dyn Mapper> {
+    let mut mapper = mapper_from(
+        &[8, 16, mapper << 4, 0x08, 0, 0, 0x07],
+        &numbered_banks(16, 0x2000),
+        &numbered_banks(32, 0x1000),
+    );
+    // FD banks 1 and 3, FE banks 2 and 4
+    mapper.cpu_write(0xb000, 1);
+    mapper.cpu_write(0xc000, 2);
+    mapper.cpu_write(0xd000, 3);
+    mapper.cpu_write(0xe000, 4);
+    mapper
+}
+
+#[test]
+fn test_mmc2_prg_banks() {
+    let mut mapper = latch_mapper(9);
+    mapper.cpu_write(0xa000, 5);
+    assert_eq!(mapper.cpu_peek(0x8000), Some(5));
+    assert_eq!(mapper.cpu_peek(0xa000), Some(13));
+    assert_eq!(mapper.cpu_peek(0xc000), Some(14));
+    assert_eq!(mapper.cpu_peek(0xe000), Some(15));
+}
+#[test]
+fn test_mmc4_prg_banks() {
+    let mut mapper = latch_mapper(10);
+    mapper.cpu_write(0xa000, 2);
+    assert_eq!(mapper.cpu_peek(0x8000), Some(4));
+    assert_eq!(mapper.cpu_peek(0xa000), Some(5));
+    assert_eq!(mapper.cpu_peek(0xc000), Some(14));
+    assert_eq!(mapper.cpu_peek(0xe000), Some(15));
+    mapper.cpu_write(0x6000, 0x42);
+    assert_eq!(mapper.cpu_peek(0x6000), Some(0x42));
+}
+#[test]
+fn test_latches_start_on_fe() {
+    let mapper = latch_mapper(9);
+    assert_eq!(mapper.ppu_peek(0x0000), 2);
+    assert_eq!(mapper.ppu_peek(0x1000), 4);
+}
+#[test]
+fn test_latch_switches_after_the_triggering_fetch() {
+    let mut mapper = latch_mapper(9);
+    assert_eq!(mapper.ppu_read(0x0fd8), 2);
+    assert_eq!(mapper.ppu_read(0x0fd9), 1);
+    assert_eq!(mapper.ppu_read(0x1000), 4);
+    assert_eq!(mapper.ppu_read(0x0fe8), 1);
+    assert_eq!(mapper.ppu_read(0x0000), 2);
+}
+#[test]
+fn test_mmc2_left_table_only_triggers_on_the_first_row() {
+    let mut mapper = latch_mapper(9);
+    mapper.ppu_read(0x0fdb);
+    assert_eq!(mapper.ppu_peek(0x0000), 2);
+    // the right pattern table watches the whole tile row range
+    mapper.ppu_read(0x1fdb);
+    assert_eq!(mapper.ppu_peek(0x1000), 3);
+    mapper.ppu_read(0x1fef);
+    assert_eq!(mapper.ppu_peek(0x1000), 4);
+}
+#[test]
+fn test_mmc4_left_table_triggers_on_the_tile_range() {
+    let mut mapper = latch_mapper(10);
+    mapper.ppu_read(0x0fdb);
+    assert_eq!(mapper.ppu_peek(0x0000), 1);
+}
+#[test]
+fn test_peek_does_not_trigger_latch() {
+    let mapper = latch_mapper(9);
+    mapper.ppu_peek(0x0fd8);
+    assert_eq!(mapper.ppu_peek(0x0000), 2);
+}
+#[test]
+fn test_mirroring_register() {
+    let mut mapper = latch_mapper(10);
+    mapper.cpu_write(0xf000, 1);
+    assert_eq!(mapper.mirroring(), Mirroring::Horizontal);
+    mapper.cpu_write(0xf000, 0);
+    assert_eq!(mapper.mirroring(), Mirroring::Vertical);
+}
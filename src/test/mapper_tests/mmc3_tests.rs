@@ -0,0 +1,186 @@
+use super::{cartridge_from, numbered_banks};
+use crate::cartridge::*;
+use crate::mapper::mmc3::*;
+use crate::mapper::*;
+
+// 8 PRG banks of 8 KiB and 16 CHR banks of 1 KiB, each filled with its bank number
+fn mmc3_cartridge(flags_6: u8, submapper: u8) -> Cartridge {
+    cartridge_from(
+        &[4, 2, 0x40 | flags_6, 0x08, submapper << 4, 0, 0x07],
+        &numbered_banks(8, 0x2000),
+        &numbered_banks(16, 0x0400),
+    )
+}
+fn write_bank(mapper: &mut Box<dyn Mapper>, bank_select: u8, bank: u8) {
+    mapper.cpu_write(0x8000, bank_select);
+    mapper.cpu_write(0x8001, bank);
+}
+// background patterns come from $0000 and sprite patterns from $1000: one A12 rise per line
+fn render_scanline(mapper: &mut Box<dyn Mapper>) {
+    mapper.ppu_address_bus(0x0000);
+    mapper.ppu_address_bus(0x2000);
+    for _ in 0..100 {
+        mapper.cpu_clock();
+    }
+    mapper.ppu_address_bus(0x1000);
+    mapper.ppu_address_bus(0x2000);
+    mapper.ppu_address_bus(0x1008);
+    for _ in 0..13 {
+        mapper.cpu_clock();
+    }
+}
+fn mapper_with_irq(revision: Mmc3Revision, latch: u8) -> Box<dyn Mapper> {
+    let mut mapper: Box<dyn Mapper> =
+        Box::new(Mmc3::with_revision(mmc3_cartridge(0x00, 0), revision));
+    mapper.cpu_write(0xc000, latch);
+    mapper.cpu_write(0xc001, 0);
+    mapper.cpu_write(0xe001, 0);
+    mapper
+}
+
+#[test]
+fn test_power_on_fixes_last_two_banks() {
+    let mapper = create_mapper(mmc3_cartridge(0x00, 0)).unwrap();
+    assert_eq!(mapper.cpu_peek(0xc000), Some(6));
+    assert_eq!(mapper.cpu_peek(0xe000), Some(7));
+    assert_eq!(mapper.cpu_peek(0xffff), Some(7));
+}
+#[test]
+fn test_prg_banks_and_mode() {
+    let mut mapper = create_mapper(mmc3_cartridge(0x00, 0)).unwrap();
+    write_bank(&mut mapper, 6, 3);
+    write_bank(&mut mapper, 7, 4);
+    assert_eq!(mapper.cpu_peek(0x8000), Some(3));
+    assert_eq!(mapper.cpu_peek(0xa000), Some(4));
+    assert_eq!(mapper.cpu_peek(0xc000), Some(6));
+    // PRG mode 1 swaps $8000 and $C000
+    mapper.cpu_write(0x8000, 0x46);
+    assert_eq!(mapper.cpu_peek(0x8000), Some(6));
+    assert_eq!(mapper.cpu_peek(0xa000), Some(4));
+    assert_eq!(mapper.cpu_peek(0xc000), Some(3));
+    assert_eq!(mapper.cpu_peek(0xe000), Some(7));
+}
+#[test]
+fn test_chr_banks() {
+    let mut mapper = create_mapper(mmc3_cartridge(0x00, 0)).unwrap();
+    for (register, bank) in [(0, 5), (1, 8), (2, 12), (3, 13), (4, 14), (5, 15)] {
+        write_bank(&mut mapper, register, bank);
+    }
+    // R0 and R1 select 2 KiB, the low bit is ignored
+    assert_eq!(mapper.ppu_peek(0x0000), 4);
+    assert_eq!(mapper.ppu_peek(0x0400), 5);
+    assert_eq!(mapper.ppu_peek(0x0800), 8);
+    assert_eq!(mapper.ppu_peek(0x0c00), 9);
+    assert_eq!(mapper.ppu_peek(0x1000), 12);
+    assert_eq!(mapper.ppu_peek(0x1c00), 15);
+}
+#[test]
+fn test_chr_a12_inversion() {
+    let mut mapper = create_mapper(mmc3_cartridge(0x00, 0)).unwrap();
+    write_bank(&mut mapper, 0x80, 6);
+    write_bank(&mut mapper, 0x82, 1);
+    assert_eq!(mapper.ppu_peek(0x1000), 6);
+    assert_eq!(mapper.ppu_peek(0x1400), 7);
+    assert_eq!(mapper.ppu_peek(0x0000), 1);
+}
+#[test]
+fn test_mirroring_register() {
+    let mut mapper = create_mapper(mmc3_cartridge(0x00, 0)).unwrap();
+    mapper.cpu_write(0xa000, 1);
+    assert_eq!(mapper.mirroring(), Mirroring::Horizontal);
+    mapper.cpu_write(0xa000, 0);
+    assert_eq!(mapper.mirroring(), Mirroring::Vertical);
+    let mut four_screen = create_mapper(mmc3_cartridge(0x08, 0)).unwrap();
+    four_screen.cpu_write(0xa000, 1);
+    assert_eq!(four_screen.mirroring(), Mirroring::FourScreen);
+}
+#[test]
+fn test_prg_ram_protection() {
+    let mut mapper = create_mapper(mmc3_cartridge(0x00, 0)).unwrap();
+    mapper.cpu_write(0x6000, 0x42);
+    assert_eq!(mapper.cpu_peek(0x6000), Some(0x42));
+    mapper.cpu_write(0xa001, 0xc0);
+    mapper.cpu_write(0x6000, 0x11);
+    assert_eq!(mapper.cpu_peek(0x6000), Some(0x42));
+    mapper.cpu_write(0xa001, 0x00);
+    assert_eq!(mapper.cpu_peek(0x6000), None);
+    mapper.cpu_write(0xa001, 0x80);
+    mapper.cpu_write(0x6000, 0x11);
+    assert_eq!(mapper.cpu_peek(0x6000), Some(0x11));
+}
+#[test]
+fn test_irq_after_latch_plus_one_scanlines() {
+    let mut mapper = mapper_with_irq(Mmc3Revision::Sharp, 2);
+    render_scanline(&mut mapper);
+    render_scanline(&mut mapper);
+    assert!(!mapper.irq_asserted());
+    render_scanline(&mut mapper);
+    assert!(mapper.irq_asserted());
+    // $E000 acknowledges and disables
+    mapper.cpu_write(0xe000, 0);
+    assert!(!mapper.irq_asserted());
+    for _ in 0..3 {
+        render_scanline(&mut mapper);
+    }
+    assert!(!mapper.irq_asserted());
+}
+#[test]
+fn test_a12_rises_without_low_time_are_filtered() {
+    let mut mapper = mapper_with_irq(Mmc3Revision::Sharp, 1);
+    render_scanline(&mut mapper);
+    // 8 sprite fetches toggling A12 within the same few cycles count once
+    for _ in 0..8 {
+        mapper.ppu_address_bus(0x0000);
+        mapper.ppu_address_bus(0x1000);
+        mapper.cpu_clock();
+    }
+    assert!(!mapper.irq_asserted());
+    render_scanline(&mut mapper);
+    assert!(mapper.irq_asserted());
+}
+#[test]
+fn test_sharp_revision_fires_every_line_with_latch_0() {
+    let mut mapper = mapper_with_irq(Mmc3Revision::Sharp, 0);
+    for _ in 0..3 {
+        render_scanline(&mut mapper);
+        assert!(mapper.irq_asserted());
+        mapper.cpu_write(0xe000, 0);
+        mapper.cpu_write(0xe001, 0);
+    }
+}
+#[test]
+fn test_nec_revision_fires_only_on_forced_reload_with_latch_0() {
+    let mut mapper = mapper_with_irq(Mmc3Revision::Nec, 0);
+    render_scanline(&mut mapper);
+    assert!(mapper.irq_asserted());
+    mapper.cpu_write(0xe000, 0);
+    mapper.cpu_write(0xe001, 0);
+    for _ in 0..3 {
+        render_scanline(&mut mapper);
+        assert!(!mapper.irq_asserted());
+    }
+}
+#[test]
+fn test_nec_revision_fires_on_decrement_to_0() {
+    let mut mapper = mapper_with_irq(Mmc3Revision::Nec, 1);
+    render_scanline(&mut mapper);
+    assert!(!mapper.irq_asserted());
+    render_scanline(&mut mapper);
+    assert!(mapper.irq_asserted());
+}
+#[test]
+fn test_revision_from_submapper() {
+    let mut sharp = create_mapper(mmc3_cartridge(0x00, 0)).unwrap();
+    let mut nec = create_mapper(mmc3_cartridge(0x00, 4)).unwrap();
+    for mapper in [&mut sharp, &mut nec] {
+        mapper.cpu_write(0xc000, 0);
+        mapper.cpu_write(0xc001, 0);
+        mapper.cpu_write(0xe001, 0);
+        render_scanline(mapper);
+        mapper.cpu_write(0xe000, 0);
+        mapper.cpu_write(0xe001, 0);
+        render_scanline(mapper);
+    }
+    assert!(sharp.irq_asserted());
+    assert!(!nec.irq_asserted());
+}
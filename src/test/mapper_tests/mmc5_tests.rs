@@ -0,0 +1,336 @@
+use super::{cartridge_from, numbered_banks};
+use crate::cartridge::*;
+use crate::mapper::*;
+
+// 16 PRG banks of 8 KiB, 64 CHR banks of 1 KiB, each filled with its bank number, 32 KiB PRG-RAM
+fn mmc5_cartridge() -> Cartridge {
+    cartridge_from(
+        &[8, 8, 0x50, 0x08, 0, 0, 0x09],
+        &numbered_banks(16, 0x2000),
+        &numbered_banks(64, 0x0400),
+    )
+}
+fn unlock_prg_ram(mapper: &mut Box<dyn Mapper>) {
+    mapper.cpu_write(0x5102, 0b10);
+    mapper.cpu_write(0x5103, 0b01);
+}
+// the dummy nametable fetches at dots 337 and 339, the next line's first fetch repeats them
+fn end_scanline(mapper: &mut Box<dyn Mapper>) {
+    mapper.ppu_address_bus(0x2002);
+    mapper.ppu_address_bus(0x2002);
+}
+// nametable, attribute and low pattern byte of one background tile
+fn fetch_tile(mapper: &mut Box<dyn Mapper>, column: u16) -> (Option<u8>, Option<u8>, u8) {
+    let nametable_address = 0x2000 + column % 32;
+    mapper.ppu_address_bus(nametable_address);
+    let tile = mapper.nametable_read(nametable_address);
+    mapper.ppu_address_bus(0x23c0);
+    let attribute = mapper.nametable_read(0x23c0);
+    mapper.ppu_address_bus(0x0010);
+    let pattern = mapper.ppu_read(0x0010);
+    mapper.ppu_address_bus(0x0018);
+    (tile, attribute, pattern)
+}
+fn fetch_sprite(mapper: &mut Box<dyn Mapper>) -> u8 {
+    mapper.ppu_address_bus(0x2000);
+    mapper.ppu_address_bus(0x2000);
+    mapper.ppu_address_bus(0x0010);
+    let pattern = mapper.ppu_read(0x0010);
+    mapper.ppu_address_bus(0x0018);
+    pattern
+}
+fn render_scanline(mapper: &mut Box<dyn Mapper>) {
+    end_scanline(mapper);
+    for column in 2..34 {
+        fetch_tile(mapper, column);
+    }
+    for _ in 0..8 {
+        fetch_sprite(mapper);
+    }
+    fetch_tile(mapper, 0);
+    fetch_tile(mapper, 1);
+}
+
+#[test]
+fn test_power_on_maps_last_bank_at_e000() {
+    let mapper = create_mapper(mmc5_cartridge()).unwrap();
+    assert_eq!(mapper.cpu_peek(0xe000), Some(15));
+    assert_eq!(mapper.cpu_peek(0xffff), Some(15));
+}
+#[test]
+fn test_prg_mode_0_maps_32_kib() {
+    let mut mapper = create_mapper(mmc5_cartridge()).unwrap();
+    mapper.cpu_write(0x5100, 0);
+    mapper.cpu_write(0x5117, 0x05);
+    assert_eq!(mapper.cpu_peek(0x8000), Some(4));
+    assert_eq!(mapper.cpu_peek(0xa000), Some(5));
+    assert_eq!(mapper.cpu_peek(0xc000), Some(6));
+    assert_eq!(mapper.cpu_peek(0xe000), Some(7));
+}
+#[test]
+fn test_prg_mode_1_maps_two_16_kib_banks() {
+    let mut mapper = create_mapper(mmc5_cartridge()).unwrap();
+    mapper.cpu_write(0x5100, 1);
+    mapper.cpu_write(0x5115, 0x83);
+    mapper.cpu_write(0x5117, 0x09);
+    assert_eq!(mapper.cpu_peek(0x8000), Some(2));
+    assert_eq!(mapper.cpu_peek(0xa000), Some(3));
+    assert_eq!(mapper.cpu_peek(0xc000), Some(8));
+    assert_eq!(mapper.cpu_peek(0xe000), Some(9));
+}
+#[test]
+fn test_prg_mode_2_maps_16_kib_and_two_8_kib_banks() {
+    let mut mapper = create_mapper(mmc5_cartridge()).unwrap();
+    mapper.cpu_write(0x5100, 2);
+    mapper.cpu_write(0x5115, 0x83);
+    mapper.cpu_write(0x5116, 0x89);
+    mapper.cpu_write(0x5117, 0x0b);
+    assert_eq!(mapper.cpu_peek(0x8000), Some(2));
+    assert_eq!(mapper.cpu_peek(0xa000), Some(3));
+    assert_eq!(mapper.cpu_peek(0xc000), Some(9));
+    assert_eq!(mapper.cpu_peek(0xe000), Some(11));
+}
+#[test]
+fn test_prg_mode_3_maps_prg_ram_into_rom_space() {
+    let mut mapper = create_mapper(mmc5_cartridge()).unwrap();
+    unlock_prg_ram(&mut mapper);
+    mapper.cpu_write(0x5114, 0x01);
+    mapper.cpu_write(0x5115, 0x86);
+    mapper.cpu_write(0x8000, 0x42);
+    assert_eq!(mapper.cpu_peek(0x8000), Some(0x42));
+    assert_eq!(mapper.cpu_peek(0xa000), Some(6));
+    mapper.cpu_write(0x5113, 0x01);
+    assert_eq!(mapper.cpu_peek(0x6000), Some(0x42));
+    mapper.cpu_write(0x5113, 0x00);
+    assert_eq!(mapper.cpu_peek(0x6000), Some(0x00));
+}
+#[test]
+fn test_prg_ram_is_write_protected_until_unlocked() {
+    let mut mapper = create_mapper(mmc5_cartridge()).unwrap();
+    mapper.cpu_write(0x6000, 0x42);
+    assert_eq!(mapper.cpu_peek(0x6000), Some(0x00));
+    unlock_prg_ram(&mut mapper);
+    mapper.cpu_write(0x6000, 0x42);
+    assert_eq!(mapper.cpu_peek(0x6000), Some(0x42));
+    mapper.cpu_write(0x5102, 0);
+    mapper.cpu_write(0x6000, 0x11);
+    assert_eq!(mapper.cpu_peek(0x6000), Some(0x42));
+}
+#[test]
+fn test_chr_modes() {
+    let mut mapper = create_mapper(mmc5_cartridge()).unwrap();
+    for register in 0..8 {
+        mapper.cpu_write(0x5120 + register, 8 + register as u8);
+    }
+    assert_eq!(mapper.ppu_peek(0x0000), 8);
+    assert_eq!(mapper.ppu_peek(0x1c00), 15);
+    mapper.cpu_write(0x5101, 1);
+    assert_eq!(mapper.ppu_peek(0x0000), 44);
+    assert_eq!(mapper.ppu_peek(0x1400), 61);
+    mapper.cpu_write(0x5101, 0);
+    mapper.cpu_write(0x5127, 2);
+    assert_eq!(mapper.ppu_peek(0x0000), 16);
+    assert_eq!(mapper.ppu_peek(0x1c00), 23);
+}
+#[test]
+fn test_chr_upper_bits_are_latched_with_the_register() {
+    let mut cartridge = mmc5_cartridge();
+    cartridge.chr_rom = (0..0x100000).map(|offset| (offset >> 18) as u8).collect();
+    let mut mapper = create_mapper(cartridge).unwrap();
+    mapper.cpu_write(0x5130, 0b10);
+    mapper.cpu_write(0x5120, 0);
+    mapper.cpu_write(0x5130, 0b01);
+    assert_eq!(mapper.ppu_peek(0x0000), 2);
+}
+#[test]
+fn test_8x16_sprites_use_separate_background_banks() {
+    let mut mapper = create_mapper(mmc5_cartridge()).unwrap();
+    for register in 0..12 {
+        mapper.cpu_write(0x5120 + register, register as u8);
+    }
+    mapper.snoop_cpu_write(0x2000, 0x20);
+    end_scanline(&mut mapper);
+    assert_eq!(fetch_tile(&mut mapper, 2).2, 8);
+    for column in 3..34 {
+        fetch_tile(&mut mapper, column);
+    }
+    assert_eq!(fetch_sprite(&mut mapper), 0);
+    for _ in 1..8 {
+        fetch_sprite(&mut mapper);
+    }
+    assert_eq!(fetch_tile(&mut mapper, 0).2, 8);
+    // outside of rendering the last written set wins
+    mapper.snoop_cpu_write(0x2001, 0x00);
+    assert_eq!(mapper.ppu_peek(0x0010), 8);
+    mapper.cpu_write(0x5120, 3);
+    assert_eq!(mapper.ppu_peek(0x0010), 3);
+}
+#[test]
+fn test_ppu_register_mirrors_are_snooped() {
+    let mut mapper = create_mapper(mmc5_cartridge()).unwrap();
+    for register in 0..12 {
+        mapper.cpu_write(0x5120 + register, register as u8);
+    }
+    // with 8x8 sprites the background would follow this last write to the sprite set
+    mapper.cpu_write(0x5120, 0);
+    mapper.snoop_cpu_write(0x3ff8, 0x20);
+    end_scanline(&mut mapper);
+    assert_eq!(fetch_tile(&mut mapper, 2).2, 8);
+    assert_eq!(mapper.cpu_peek(0x5204), Some(0x40));
+    mapper.snoop_cpu_write(0x2009, 0x00);
+    assert_eq!(mapper.cpu_peek(0x5204), Some(0x00));
+}
+#[test]
+fn test_multiplier() {
+    let mut mapper = create_mapper(mmc5_cartridge()).unwrap();
+    mapper.cpu_write(0x5205, 200);
+    mapper.cpu_write(0x5206, 150);
+    assert_eq!(mapper.cpu_peek(0x5205), Some((30000u16 & 0xff) as u8));
+    assert_eq!(mapper.cpu_peek(0x5206), Some((30000u16 >> 8) as u8));
+}
+#[test]
+fn test_nametable_mapping_with_exram_and_fill_mode() {
+    let mut mapper = create_mapper(mmc5_cartridge()).unwrap();
+    mapper.cpu_write(0x5105, 0b11_10_01_00);
+    assert_eq!(mapper.nametable_page(0x2000), 0);
+    assert_eq!(mapper.nametable_page(0x2400), 1);
+    assert_eq!(mapper.nametable_peek(0x2000), None);
+    assert!(!mapper.nametable_write(0x2400, 0x11));
+    // ExRAM as third nametable
+    mapper.cpu_write(0x5104, 2);
+    mapper.cpu_write(0x5c05, 0x42);
+    assert_eq!(mapper.cpu_peek(0x5c05), Some(0x42));
+    mapper.cpu_write(0x5104, 0);
+    assert_eq!(mapper.cpu_peek(0x5c05), None);
+    assert_eq!(mapper.nametable_peek(0x2805), Some(0x42));
+    assert!(mapper.nametable_write(0x2806, 0x11));
+    assert_eq!(mapper.nametable_peek(0x2806), Some(0x11));
+    // fill mode
+    mapper.cpu_write(0x5106, 0x33);
+    mapper.cpu_write(0x5107, 0b10);
+    assert_eq!(mapper.nametable_peek(0x2c00), Some(0x33));
+    assert_eq!(mapper.nametable_peek(0x2fc0), Some(0b1010_1010));
+}
+#[test]
+fn test_scanline_irq() {
+    let mut mapper = create_mapper(mmc5_cartridge()).unwrap();
+    mapper.cpu_write(0x5203, 2);
+    mapper.cpu_write(0x5204, 0x80);
+    render_scanline(&mut mapper);
+    assert_eq!(mapper.cpu_peek(0x5204), Some(0x40));
+    render_scanline(&mut mapper);
+    assert!(!mapper.irq_asserted());
+    render_scanline(&mut mapper);
+    assert!(mapper.irq_asserted());
+    // reading the status acknowledges
+    assert_eq!(mapper.cpu_read(0x5204), Some(0xc0));
+    assert!(!mapper.irq_asserted());
+}
+#[test]
+fn test_in_frame_ends_when_ppu_stops_reading() {
+    let mut mapper = create_mapper(mmc5_cartridge()).unwrap();
+    render_scanline(&mut mapper);
+    mapper.cpu_clock();
+    assert_eq!(mapper.cpu_peek(0x5204), Some(0x40));
+    for _ in 0..3 {
+        mapper.cpu_clock();
+    }
+    assert_eq!(mapper.cpu_peek(0x5204), Some(0x00));
+    render_scanline(&mut mapper);
+    mapper.cpu_read(0xfffa);
+    assert_eq!(mapper.cpu_peek(0x5204), Some(0x00));
+}
+#[test]
+fn test_extended_attributes() {
+    let mut mapper = create_mapper(mmc5_cartridge()).unwrap();
+    // tile 2: palette 2, 4 KiB CHR bank 5
+    mapper.cpu_write(0x5104, 2);
+    mapper.cpu_write(0x5c02, 0b10_000101);
+    mapper.cpu_write(0x5104, 1);
+    end_scanline(&mut mapper);
+    let (_, attribute, pattern) = fetch_tile(&mut mapper, 2);
+    assert_eq!(attribute, Some(0b1010_1010));
+    assert_eq!(pattern, 20);
+}
+#[test]
+fn test_vertical_split() {
+    let mut mapper = create_mapper(mmc5_cartridge()).unwrap();
+    mapper.cpu_write(0x5104, 2);
+    mapper.cpu_write(0x5c22, 0x09);
+    mapper.cpu_write(0x5fc0, 0b11_00_00_00);
+    mapper.cpu_write(0x5104, 0);
+    // left 4 tiles from 4 KiB bank 1, scrolled 8 lines down
+    mapper.cpu_write(0x5200, 0x84);
+    mapper.cpu_write(0x5201, 8);
+    mapper.cpu_write(0x5202, 1);
+    end_scanline(&mut mapper);
+    assert_eq!(fetch_tile(&mut mapper, 2), (Some(0x09), Some(0), 4));
+    fetch_tile(&mut mapper, 3);
+    assert_eq!(fetch_tile(&mut mapper, 4), (None, None, 0));
+}
+#[test]
+fn test_vertical_split_uses_exram_attributes() {
+    let mut mapper = create_mapper(mmc5_cartridge()).unwrap();
+    mapper.cpu_write(0x5104, 2);
+    mapper.cpu_write(0x5fc0, 0b00_00_11_00);
+    mapper.cpu_write(0x5104, 0);
+    mapper.cpu_write(0x5200, 0xc0);
+    end_scanline(&mut mapper);
+    assert_eq!(fetch_tile(&mut mapper, 2).1, Some(0xff));
+}
+#[test]
+fn test_pulse_channel() {
+    let mut mapper = create_mapper(mmc5_cartridge()).unwrap();
+    mapper.cpu_write(0x5015, 0b01);
+    mapper.cpu_write(0x5000, 0b1011_1111);
+    mapper.cpu_write(0x5002, 0x10);
+    mapper.cpu_write(0x5003, 0x08);
+    assert_eq!(mapper.cpu_peek(0x5015), Some(0b01));
+    let mut loudest: f32 = 0.0;
+    for _ in 0..300 {
+        mapper.cpu_clock();
+        loudest = loudest.max(mapper.audio_output());
+    }
+    assert!(loudest > 0.0);
+    mapper.cpu_write(0x5015, 0);
+    assert_eq!(mapper.cpu_peek(0x5015), Some(0));
+    assert_eq!(mapper.audio_output(), 0.0);
+}
+#[test]
+fn test_pulse_length_counter_runs_at_240_hz() {
+    let mut mapper = create_mapper(mmc5_cartridge()).unwrap();
+    mapper.cpu_write(0x5015, 0b10);
+    mapper.cpu_write(0x5004, 0b1001_1111);
+    // length index 3: 2 clocks
+    mapper.cpu_write(0x5007, 0b0001_1000);
+    for _ in 0..7457 {
+        mapper.cpu_clock();
+    }
+    assert_eq!(mapper.cpu_peek(0x5015), Some(0b10));
+    for _ in 0..7457 {
+        mapper.cpu_clock();
+    }
+    assert_eq!(mapper.cpu_peek(0x5015), Some(0));
+}
+#[test]
+fn test_pcm_write_mode() {
+    let mut mapper = create_mapper(mmc5_cartridge()).unwrap();
+    mapper.cpu_write(0x5011, 0x80);
+    assert!(mapper.audio_output() > 0.0);
+    mapper.cpu_write(0x5011, 0x00);
+    assert!(mapper.audio_output() > 0.0);
+}
+#[test]
+fn test_pcm_read_mode_irq_on_zero() {
+    let mut mapper = create_mapper(mmc5_cartridge()).unwrap();
+    mapper.cpu_write(0x5010, 0x81);
+    mapper.cpu_write(0x5114, 0x83);
+    mapper.cpu_read(0x8000);
+    assert!(!mapper.irq_asserted());
+    assert!(mapper.audio_output() > 0.0);
+    mapper.cpu_write(0x5114, 0x80);
+    mapper.cpu_read(0x8000);
+    assert!(mapper.irq_asserted());
+    assert_eq!(mapper.cpu_read(0x5010), Some(0x80));
+    assert!(!mapper.irq_asserted());
+}
@@ -0,0 +1,54 @@
+use crate::cartridge::*;
+use crate::mapper::*;
+
+mod board_tests;
+mod discrete_tests;
+mod fds_tests;
+mod fme7_tests;
+mod mmc1_tests;
+mod mmc2_tests;
+mod mmc3_tests;
+mod mmc5_tests;
+mod namco163_tests;
+mod nrom_tests;
+mod vrc_tests;
+
+// an iNES image: `header` holds bytes 4-15, missing ones are 0
+pub fn ines_image(header: &[u8], prg_rom: &[u8], chr_rom: &[u8]) -> Vec<u8> {
+    let mut image = vec![b'N', b'E', b'S', 0x1a];
+    image.extend_from_slice(header);
+    image.resize(16, 0);
+    image.extend_from_slice(prg_rom);
+    image.extend_from_slice(chr_rom);
+    image
+}
+// `count` banks of `bank_size` bytes, each filled with its bank number
+pub fn numbered_banks(count: usize, bank_size: usize) -> Vec<u8> {
+    (0..count * bank_size)
+        .map(|offset| (offset / bank_size) as u8)
+        .collect()
+}
+pub fn cartridge_from(header: &[u8], prg_rom: &[u8], chr_rom: &[u8]) -> Cartridge {
+    Cartridge::from_ines(&ines_image(header, prg_rom, chr_rom)).unwrap()
+}
+pub fn mapper_from(header: &[u8], prg_rom: &[u8], chr_rom: &[u8]) -> Box<dyn Mapper> {
+    create_mapper(cartridge_from(header, prg_rom, chr_rom)).unwrap()
+}
+// NES 2.0 board with CHR-RAM and a single 8 KiB PRG-ROM bank, each page filled with its number
+pub fn small_prg_mapper(mapper: u8) -> Box<dyn Mapper> {
+    let prg_rom: Vec<u8> = (0..0x2000).map(|offset| (offset >> 8) as u8).collect();
+    mapper_from(
+        &[
+            0x34,
+            0,
+            mapper << 4,
+            (mapper & 0xf0) | 0x08,
+            0,
+            0x0f,
+            0,
+            0x07,
+        ],
+        &prg_rom,
+        &[],
+    )
+}
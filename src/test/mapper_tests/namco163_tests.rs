@@ -0,0 +1,196 @@
+use super::{cartridge_from, numbered_banks};
+use crate::cartridge::*;
+use crate::mapper::namco163::*;
+use crate::mapper::*;
+
+// 16 PRG banks of 8 KiB and 256 CHR banks of 1 KiB, each filled with its number, 8 KiB PRG-RAM
+fn namco163_cartridge(has_battery: bool) -> Cartridge {
+    let flags_6 = 0x30 | if has_battery { 0x02 } else { 0x00 };
+    let prg_ram = if has_battery { 0x70 } else { 0x07 };
+    cartridge_from(
+        &[8, 32, flags_6, 0x18, 0, 0, prg_ram],
+        &numbered_banks(16, 0x2000),
+        &numbered_banks(256, 0x0400),
+    )
+}
+fn namco163_mapper() -> Box<dyn Mapper> {
+    create_mapper(namco163_cartridge(false)).unwrap()
+}
+fn write_sound_ram(mapper: &mut dyn Mapper, address: u8, data: &[u8]) {
+    mapper.cpu_write(0xf800, 0x80 | address);
+    for byte in data {
+        mapper.cpu_write(0x4800, *byte);
+    }
+}
+// channel 7 playing a wave of 4 samples (0, 15, 0, 15) at the given frequency and full volume
+fn play_channel_7(mapper: &mut dyn Mapper, frequency: u32, channel_count: u8) {
+    write_sound_ram(mapper, 0x00, &[0xf0, 0xf0]);
+    let registers = [
+        frequency as u8,
+        0,
+        (frequency >> 8) as u8,
+        0,
+        (256 - 4) as u8 | ((frequency >> 16) as u8 & 0b11),
+        0,
+        0,
+        ((channel_count - 1) << 4) | 0x0f,
+    ];
+    write_sound_ram(mapper, 0x78, &registers);
+}
+fn output_levels(mapper: &mut dyn Mapper, cycles: usize) -> Vec<f32> {
+    (0..cycles)
+        .map(|_| {
+            mapper.cpu_clock();
+            mapper.audio_output()
+        })
+        .collect()
+}
+
+#[test]
+fn test_prg_and_chr_banks() {
+    let mut mapper = namco163_mapper();
+    mapper.cpu_write(0xe000, 3);
+    mapper.cpu_write(0xe800, 4);
+    mapper.cpu_write(0xf000, 5);
+    assert_eq!(mapper.cpu_peek(0x8000), Some(3));
+    assert_eq!(mapper.cpu_peek(0xa000), Some(4));
+    assert_eq!(mapper.cpu_peek(0xc000), Some(5));
+    assert_eq!(mapper.cpu_peek(0xe000), Some(15));
+    for register in 0..8 {
+        mapper.cpu_write(0x8000 + register * 0x0800, 100 + register as u8);
+    }
+    assert_eq!(mapper.ppu_peek(0x0000), 100);
+    assert_eq!(mapper.ppu_peek(0x1fff), 107);
+}
+#[test]
+fn test_nametables_map_chr_rom_or_vram() {
+    let mut mapper = namco163_mapper();
+    mapper.cpu_write(0xc000, 0xe0);
+    mapper.cpu_write(0xc800, 0xe1);
+    mapper.cpu_write(0xd000, 0x12);
+    mapper.cpu_write(0xd800, 0xe1);
+    assert_eq!(mapper.nametable_peek(0x2000), None);
+    assert_eq!(mapper.nametable_page(0x2000), 0);
+    assert_eq!(mapper.nametable_page(0x2400), 1);
+    assert_eq!(mapper.nametable_page(0x2c00), 1);
+    assert_eq!(mapper.nametable_peek(0x2800), Some(0x12));
+    // CHR-ROM nametables swallow writes
+    assert!(mapper.nametable_write(0x2800, 0x55));
+    assert!(!mapper.nametable_write(0x2000, 0x55));
+}
+#[test]
+fn test_prg_ram_write_protect() {
+    let mut mapper = namco163_mapper();
+    mapper.cpu_write(0x6000, 0x11);
+    assert_eq!(mapper.cpu_peek(0x6000), Some(0x00));
+    // enabled, but the second 2 KiB protected
+    mapper.cpu_write(0xf800, 0x42);
+    mapper.cpu_write(0x6000, 0x11);
+    mapper.cpu_write(0x6800, 0x22);
+    assert_eq!(mapper.cpu_peek(0x6000), Some(0x11));
+    assert_eq!(mapper.cpu_peek(0x6800), Some(0x00));
+}
+#[test]
+fn test_sound_ram_port_auto_increments() {
+    let mut mapper = namco163_mapper();
+    write_sound_ram(mapper.as_mut(), 0x7e, &[0xaa, 0xbb, 0xcc]);
+    mapper.cpu_write(0xf800, 0x80 | 0x7e);
+    assert_eq!(mapper.cpu_read(0x4800), Some(0xaa));
+    assert_eq!(mapper.cpu_read(0x4800), Some(0xbb));
+    // wraps around to $00
+    assert_eq!(mapper.cpu_read(0x4800), Some(0xcc));
+    // without auto-increment the address stays put
+    mapper.cpu_write(0xf800, 0x00);
+    assert_eq!(mapper.cpu_read(0x4800), Some(0xcc));
+    assert_eq!(mapper.cpu_read(0x4800), Some(0xcc));
+}
+#[test]
+fn test_irq_counts_up_to_7fff() {
+    let mut mapper = namco163_mapper();
+    mapper.cpu_write(0x5000, 0xfd);
+    mapper.cpu_write(0x5800, 0x80 | 0x7f);
+    mapper.cpu_clock();
+    assert!(!mapper.irq_asserted());
+    mapper.cpu_clock();
+    assert!(mapper.irq_asserted());
+    assert_eq!(mapper.cpu_peek(0x5000), Some(0xff));
+    // the counter stops at $7FFF
+    mapper.cpu_clock();
+    assert_eq!(mapper.cpu_peek(0x5000), Some(0xff));
+    assert_eq!(mapper.cpu_peek(0x5800), Some(0xff));
+    mapper.cpu_write(0x5000, 0x00);
+    assert!(!mapper.irq_asserted());
+}
+#[test]
+fn test_irq_disabled_counter_holds() {
+    let mut mapper = namco163_mapper();
+    mapper.cpu_write(0x5000, 0xfe);
+    mapper.cpu_write(0x5800, 0x7f);
+    mapper.cpu_clock();
+    assert!(!mapper.irq_asserted());
+    assert_eq!(mapper.cpu_peek(0x5000), Some(0xfe));
+}
+#[test]
+fn test_channel_updates_every_15_cycles() {
+    let mut mapper = namco163_mapper();
+    // one sample per update
+    play_channel_7(mapper.as_mut(), 0x10000, 1);
+    let levels = output_levels(mapper.as_mut(), 60);
+    assert_ne!(levels[14], levels[13]);
+    assert!(levels[14..29].iter().all(|level| *level == levels[14]));
+    assert_ne!(levels[29], levels[14]);
+    assert_eq!(levels[44], levels[14]);
+}
+#[test]
+fn test_multiplexed_channels_take_turns() {
+    let mut mapper = namco163_mapper();
+    // channel 7 loud, channel 6 silent, two channels enabled
+    play_channel_7(mapper.as_mut(), 0, 2);
+    let levels = output_levels(mapper.as_mut(), 15 * 4);
+    assert_ne!(levels[14], 0.0);
+    assert_eq!(levels[29], 0.0);
+    assert_eq!(levels[44], levels[14]);
+}
+#[test]
+fn test_averaged_mixing() {
+    let mut mapper: Box<dyn Mapper> = Box::new(Namco163::with_mixing(
+        namco163_cartridge(false),
+        Namco163Mixing::Averaged,
+    ));
+    play_channel_7(mapper.as_mut(), 0, 2);
+    let levels = output_levels(mapper.as_mut(), 15 * 4);
+    assert!(levels[29..].iter().all(|level| *level == levels[29]));
+    assert_ne!(levels[29], 0.0);
+    // the silent channel halves the level compared to playing alone
+    let mut alone: Box<dyn Mapper> = Box::new(Namco163::with_mixing(
+        namco163_cartridge(false),
+        Namco163Mixing::Averaged,
+    ));
+    play_channel_7(alone.as_mut(), 0, 1);
+    let alone_levels = output_levels(alone.as_mut(), 15 * 2);
+    assert_eq!(levels[29] * 2.0, alone_levels[29]);
+}
+#[test]
+fn test_sound_disable_bit() {
+    let mut mapper = namco163_mapper();
+    play_channel_7(mapper.as_mut(), 0, 1);
+    output_levels(mapper.as_mut(), 15);
+    assert_ne!(mapper.audio_output(), 0.0);
+    mapper.cpu_write(0xe000, 0x40);
+    assert_eq!(mapper.audio_output(), 0.0);
+}
+#[test]
+fn test_battery_ram_holds_sound_ram() {
+    let mut mapper = create_mapper(namco163_cartridge(true)).unwrap();
+    mapper.cpu_write(0xf800, 0x40);
+    mapper.cpu_write(0x6000, 0x11);
+    write_sound_ram(mapper.as_mut(), 0x7f, &[0x22]);
+    let saved = mapper.battery_ram();
+    assert_eq!(saved.len(), 0x2000 + 0x80);
+    let mut restored = create_mapper(namco163_cartridge(true)).unwrap();
+    restored.load_battery_ram(&saved);
+    assert_eq!(restored.cpu_peek(0x6000), Some(0x11));
+    restored.cpu_write(0xf800, 0x7f);
+    assert_eq!(restored.cpu_peek(0x4800), Some(0x22));
+    assert!(namco163_mapper().battery_ram().is_empty());
+}
@@ -0,0 +1,116 @@
+use super::cartridge_from;
+use crate::bus::*;
+use crate::cartridge::*;
+use crate::cpu::*;
+use crate::mapper::*;
+
+// PRG bytes hold the low byte of their offset plus the bank number in the upper bits
+fn nrom_cartridge(prg_banks: u8, chr_banks: u8, flags_6: u8) -> Cartridge {
+    let prg_rom: Vec<u8> = (0..prg_banks as usize * 0x4000)
+        .map(|offset| (offset as u8 & 0x3f) | ((offset / 0x4000) as u8) << 6)
+        .collect();
+    let chr_rom: Vec<u8> = (0..chr_banks as usize * 0x2000)
+        .map(|offset| (offset >> 8) as u8)
+        .collect();
+    cartridge_from(&[prg_banks, chr_banks, flags_6], &prg_rom, &chr_rom)
+}
+
+#[test]
+fn test_nrom_128_is_mirrored_at_c000() {
+    let mapper = create_mapper(nrom_cartridge(1, 1, 0x00)).unwrap();
+    assert_eq!(mapper.cpu_peek(0x8000), Some(0x00));
+    assert_eq!(mapper.cpu_peek(0xc000), Some(0x00));
+    assert_eq!(mapper.cpu_peek(0x8005), mapper.cpu_peek(0xc005));
+    assert_eq!(mapper.cpu_peek(0xffff), mapper.cpu_peek(0xbfff));
+}
+#[test]
+fn test_nrom_256_maps_both_banks() {
+    let mapper = create_mapper(nrom_cartridge(2, 1, 0x00)).unwrap();
+    assert_eq!(mapper.cpu_peek(0x8001), Some(0x01));
+    assert_eq!(mapper.cpu_peek(0xc001), Some(0x41));
+}
+#[test]
+fn test_prg_rom_is_read_only() {
+    let mut mapper = create_mapper(nrom_cartridge(1, 1, 0x00)).unwrap();
+    mapper.cpu_write(0x8001, 0xff);
+    assert_eq!(mapper.cpu_peek(0x8001), Some(0x01));
+}
+#[test]
+fn test_prg_ram_at_6000() {
+    let mut mapper = create_mapper(nrom_cartridge(1, 1, 0x00)).unwrap();
+    mapper.cpu_write(0x6000, 0x42);
+    mapper.cpu_write(0x7fff, 0x43);
+    assert_eq!(mapper.cpu_read(0x6000), Some(0x42));
+    assert_eq!(mapper.cpu_read(0x7fff), Some(0x43));
+}
+#[test]
+fn test_unmapped_range_is_open_bus() {
+    let mapper = create_mapper(nrom_cartridge(1, 1, 0x00)).unwrap();
+    assert_eq!(mapper.cpu_peek(0x4020), None);
+    assert_eq!(mapper.cpu_peek(0x5fff), None);
+}
+#[test]
+fn test_chr_rom_is_read_only() {
+    let mut mapper = create_mapper(nrom_cartridge(1, 1, 0x00)).unwrap();
+    assert_eq!(mapper.ppu_read(0x0000), 0x00);
+    assert_eq!(mapper.ppu_read(0x1fff), 0x1f);
+    mapper.ppu_write(0x1fff, 0x42);
+    assert_eq!(mapper.ppu_peek(0x1fff), 0x1f);
+}
+#[test]
+fn test_chr_ram_is_writable() {
+    let mut mapper = create_mapper(nrom_cartridge(1, 0, 0x00)).unwrap();
+    mapper.ppu_write(0x0000, 0x42);
+    mapper.ppu_write(0x1fff, 0x43);
+    assert_eq!(mapper.ppu_read(0x0000), 0x42);
+    assert_eq!(mapper.ppu_read(0x1fff), 0x43);
+}
+#[test]
+fn test_mirroring_comes_from_header() {
+    let horizontal = create_mapper(nrom_cartridge(1, 1, 0x00)).unwrap();
+    let vertical = create_mapper(nrom_cartridge(1, 1, 0x01)).unwrap();
+    assert_eq!(horizontal.mirroring(), Mirroring::Horizontal);
+    assert_eq!(vertical.mirroring(), Mirroring::Vertical);
+    assert!(!vertical.irq_asserted());
+}
+#[test]
+fn test_unsupported_mapper_is_rejected() {
+    let mut cartridge = nrom_cartridge(1, 1, 0x00);
+    cartridge.mapper = 0x0fff;
+    let error = create_mapper(cartridge).err().unwrap();
+    assert_eq!(
+        error,
+        CartridgeError::UnsupportedMapper {
+            mapper: 0x0fff,
+            submapper: 0
+        }
+    );
+}
+#[test]
+fn test_cpu_runs_from_nrom() {
+    let mut cartridge = nrom_cartridge(1, 0, 0x00);
+    cartridge.prg_rom[0..6].copy_from_slice(&[
+        0xa9, 0x42, // LDA #$42
+        0x8d, 0x00, 0x60, // STA $6000
+        0x00,
+    ]);
+    cartridge.prg_rom[0x3ffc] = 0x00;
+    cartridge.prg_rom[0x3ffd] = 0x80;
+    let mut bus = NesBus::new();
+    bus.insert_cartridge(create_mapper(cartridge).unwrap());
+    let mut cpu = CPU::with_bus(Box::new(bus));
+    cpu.reset();
+    cpu.run().unwrap();
+    assert_eq!(cpu.memory_read_byte(0x6000), 0x42);
+}
+#[test]
+fn test_nametable_pages_follow_mirroring() {
+    let vertical = create_mapper(nrom_cartridge(1, 1, 0x01)).unwrap();
+    let horizontal = create_mapper(nrom_cartridge(1, 1, 0x00)).unwrap();
+    let pages = |mapper: &dyn Mapper| {
+        [0x2000, 0x2400, 0x2800, 0x2c00].map(|address| mapper.nametable_page(address))
+    };
+    assert_eq!(pages(vertical.as_ref()), [0, 1, 0, 1]);
+    assert_eq!(pages(horizontal.as_ref()), [0, 0, 1, 1]);
+    assert_eq!(vertical.nametable_peek(0x2000), None);
+}
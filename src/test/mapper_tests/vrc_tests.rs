@@ -0,0 +1,310 @@
+use super::{mapper_from, numbered_banks};
+use crate::cartridge::*;
+use crate::mapper::*;
+
+// NES 2.0 image with 16 PRG banks of 8 KiB and 32 CHR banks of 1 KiB, each filled with its number
+fn vrc_mapper(mapper: u8, submapper: u8) -> Box<dyn Mapper> {
+    mapper_from(
+        &[
+            8,
+            4,
+            mapper << 4,
+            (mapper & 0xf0) | 0x08,
+            submapper << 4,
+            0,
+            0x07,
+        ],
+        &numbered_banks(16, 0x2000),
+        &numbered_banks(32, 0x0400),
+    )
+}
+// the loudest output over a number of CPU cycles
+fn loudest_output(mapper: &mut Box<dyn Mapper>, cycles: usize) -> f32 {
+    let mut loudest: f32 = 0.0;
+    for _ in 0..cycles {
+        mapper.cpu_clock();
+        loudest = loudest.max(mapper.audio_output());
+    }
+    loudest
+}
+// the lowest and highest output over a number of CPU cycles
+fn output_range(mapper: &mut Box<dyn Mapper>, cycles: usize) -> (f32, f32) {
+    let mut range = (f32::MAX, f32::MIN);
+    for _ in 0..cycles {
+        mapper.cpu_clock();
+        let output = mapper.audio_output();
+        range = (range.0.min(output), range.1.max(output));
+    }
+    range
+}
+
+#[test]
+fn test_vrc4_prg_banks_and_swap_mode() {
+    let mut mapper = vrc_mapper(21, 1);
+    mapper.cpu_write(0x8000, 3);
+    mapper.cpu_write(0xa000, 5);
+    assert_eq!(mapper.cpu_peek(0x8000), Some(3));
+    assert_eq!(mapper.cpu_peek(0xa000), Some(5));
+    assert_eq!(mapper.cpu_peek(0xc000), Some(14));
+    assert_eq!(mapper.cpu_peek(0xe000), Some(15));
+    // VRC4a: $9004 is $9002
+    mapper.cpu_write(0x9004, 0b10);
+    assert_eq!(mapper.cpu_peek(0x8000), Some(14));
+    assert_eq!(mapper.cpu_peek(0xc000), Some(3));
+}
+#[test]
+fn test_vrc4_register_wiring_by_submapper() {
+    // the second CHR bank's low nibble is register 2 of $B000
+    let cases = [
+        (21, 1, 0xb004),
+        (21, 2, 0xb080),
+        (23, 1, 0xb002),
+        (23, 2, 0xb008),
+        (25, 1, 0xb001),
+        (25, 2, 0xb004),
+    ];
+    for (number, submapper, address) in cases {
+        let mut mapper = vrc_mapper(number, submapper);
+        mapper.cpu_write(address, 7);
+        assert_eq!(mapper.ppu_peek(0x0400), 7, "mapper {number}.{submapper}");
+        assert_eq!(mapper.ppu_peek(0x0000), 0, "mapper {number}.{submapper}");
+    }
+}
+#[test]
+fn test_vrc4_without_submapper_decodes_both_wirings() {
+    let mut mapper = vrc_mapper(21, 0);
+    mapper.cpu_write(0xb004, 5);
+    assert_eq!(mapper.ppu_peek(0x0400), 5);
+    mapper.cpu_write(0xb080, 6);
+    assert_eq!(mapper.ppu_peek(0x0400), 6);
+}
+#[test]
+fn test_vrc4_chr_bank_nibbles() {
+    let mut mapper = vrc_mapper(23, 1);
+    // CHR bank 7 ($E002/$E003) = $13
+    mapper.cpu_write(0xe002, 0x03);
+    mapper.cpu_write(0xe003, 0x01);
+    assert_eq!(mapper.ppu_peek(0x1c00), 0x13);
+    mapper.cpu_write(0xe003, 0x00);
+    assert_eq!(mapper.ppu_peek(0x1c00), 0x03);
+}
+#[test]
+fn test_vrc4_mirroring() {
+    let mut mapper = vrc_mapper(25, 1);
+    let modes = [
+        Mirroring::Vertical,
+        Mirroring::Horizontal,
+        Mirroring::SingleScreenLower,
+        Mirroring::SingleScreenUpper,
+    ];
+    for (value, mirroring) in modes.iter().enumerate() {
+        mapper.cpu_write(0x9000, value as u8);
+        assert_eq!(mapper.mirroring(), *mirroring);
+    }
+}
+#[test]
+fn test_vrc2a_chr_banks_ignore_low_bit() {
+    let mut mapper = vrc_mapper(22, 0);
+    mapper.cpu_write(0xb000, 0x0b);
+    assert_eq!(mapper.ppu_peek(0x0000), 5);
+    // VRC2 mirroring only has one bit
+    mapper.cpu_write(0x9000, 0b11);
+    assert_eq!(mapper.mirroring(), Mirroring::Horizontal);
+}
+#[test]
+fn test_vrc2_microwire_latch_without_prg_ram() {
+    let mut image = vec![b'N', b'E', b'S', 0x1a, 8, 4, 0x70, 0x18, 0x30];
+    image.resize(16, 0);
+    image.resize(16 + 0x20000 + 0x8000, 0);
+    let mut mapper = create_mapper(Cartridge::from_ines(&image).unwrap()).unwrap();
+    mapper.cpu_write(0x6000, 0xff);
+    assert_eq!(mapper.cpu_peek(0x6000), Some(0x01));
+    assert_eq!(mapper.cpu_peek(0x7000), None);
+}
+#[test]
+fn test_vrc4_irq_in_cycle_mode() {
+    let mut mapper = vrc_mapper(21, 1);
+    // latch $FC: 4 cycles to overflow
+    mapper.cpu_write(0xf000, 0x0c);
+    mapper.cpu_write(0xf002, 0x0f);
+    mapper.cpu_write(0xf004, 0b111);
+    for _ in 0..3 {
+        mapper.cpu_clock();
+    }
+    assert!(!mapper.irq_asserted());
+    mapper.cpu_clock();
+    assert!(mapper.irq_asserted());
+    // acknowledging copies the enable-after-ack bit, the counter was reloaded
+    mapper.cpu_write(0xf006, 0);
+    assert!(!mapper.irq_asserted());
+    for _ in 0..4 {
+        mapper.cpu_clock();
+    }
+    assert!(mapper.irq_asserted());
+}
+#[test]
+fn test_vrc_irq_in_scanline_mode() {
+    let mut mapper = vrc_mapper(24, 0);
+    mapper.cpu_write(0xf000, 0xfe);
+    mapper.cpu_write(0xf001, 0b010);
+    // two scanlines of 113 2/3 CPU cycles
+    for _ in 0..227 {
+        mapper.cpu_clock();
+    }
+    assert!(!mapper.irq_asserted());
+    mapper.cpu_clock();
+    assert!(mapper.irq_asserted());
+    // without enable-after-ack the counter stops
+    mapper.cpu_write(0xf002, 0);
+    for _ in 0..1000 {
+        mapper.cpu_clock();
+    }
+    assert!(!mapper.irq_asserted());
+}
+#[test]
+fn test_vrc6_banking() {
+    let mut mapper = vrc_mapper(24, 0);
+    mapper.cpu_write(0x8000, 2);
+    mapper.cpu_write(0xc000, 9);
+    assert_eq!(mapper.cpu_peek(0x8000), Some(4));
+    assert_eq!(mapper.cpu_peek(0xa000), Some(5));
+    assert_eq!(mapper.cpu_peek(0xc000), Some(9));
+    assert_eq!(mapper.cpu_peek(0xe000), Some(15));
+    mapper.cpu_write(0xd001, 11);
+    mapper.cpu_write(0xe003, 20);
+    assert_eq!(mapper.ppu_peek(0x0400), 11);
+    assert_eq!(mapper.ppu_peek(0x1c00), 20);
+    // $B003: horizontal mirroring, 2 KiB CHR banks
+    mapper.cpu_write(0xb003, 0b0101);
+    assert_eq!(mapper.mirroring(), Mirroring::Horizontal);
+    assert_eq!(mapper.ppu_peek(0x0800), 22);
+    assert_eq!(mapper.ppu_peek(0x0c00), 23);
+}
+#[test]
+fn test_vrc6b_swaps_a0_and_a1() {
+    let mut mapper = vrc_mapper(26, 0);
+    mapper.cpu_write(0xd002, 11);
+    assert_eq!(mapper.ppu_peek(0x0400), 11);
+    // $B001 decodes as the saw's $B002, the RAM enable stays at $B003
+    mapper.cpu_write(0xb001, 0x80);
+    mapper.cpu_write(0x6000, 0x42);
+    assert_eq!(mapper.cpu_peek(0x6000), None);
+    mapper.cpu_write(0xb003, 0x80);
+    mapper.cpu_write(0x6000, 0x42);
+    assert_eq!(mapper.cpu_peek(0x6000), Some(0x42));
+}
+#[test]
+fn test_vrc6_pulse_duty() {
+    let mut mapper = vrc_mapper(24, 0);
+    // duty 7/16 at volume 15, period 1
+    mapper.cpu_write(0x9000, 0b0111_1111);
+    mapper.cpu_write(0x9001, 0x01);
+    mapper.cpu_write(0x9002, 0x80);
+    let high_cycles = (0..32)
+        .filter(|_| {
+            mapper.cpu_clock();
+            mapper.audio_output() > 0.0
+        })
+        .count();
+    assert_eq!(high_cycles, 16);
+    // halted through $9003
+    mapper.cpu_write(0x9003, 0x01);
+    let output = mapper.audio_output();
+    assert_eq!(loudest_output(&mut mapper, 100), output);
+}
+#[test]
+fn test_vrc6_saw_resets_after_7_steps() {
+    let mut mapper = vrc_mapper(24, 0);
+    mapper.cpu_write(0xb000, 0x2a);
+    mapper.cpu_write(0xb002, 0x80);
+    let mut levels = Vec::new();
+    for _ in 0..14 {
+        mapper.cpu_clock();
+        levels.push(mapper.audio_output());
+    }
+    // $2A * 6 >> 3 is the peak, then the accumulator is cleared
+    let step = 0.00752;
+    assert!((levels[12] - 31.0 * step).abs() < 1e-6);
+    assert_eq!(levels[13], 0.0);
+}
+#[test]
+fn test_vrc7_banking() {
+    let mut mapper = vrc_mapper(85, 2);
+    mapper.cpu_write(0x8000, 3);
+    mapper.cpu_write(0x8010, 4);
+    mapper.cpu_write(0x9000, 5);
+    assert_eq!(mapper.cpu_peek(0x8000), Some(3));
+    assert_eq!(mapper.cpu_peek(0xa000), Some(4));
+    assert_eq!(mapper.cpu_peek(0xc000), Some(5));
+    assert_eq!(mapper.cpu_peek(0xe000), Some(15));
+    mapper.cpu_write(0xa010, 9);
+    mapper.cpu_write(0xd010, 10);
+    assert_eq!(mapper.ppu_peek(0x0400), 9);
+    assert_eq!(mapper.ppu_peek(0x1c00), 10);
+    mapper.cpu_write(0xe000, 0x81);
+    assert_eq!(mapper.mirroring(), Mirroring::Horizontal);
+    mapper.cpu_write(0x6000, 0x42);
+    assert_eq!(mapper.cpu_peek(0x6000), Some(0x42));
+}
+#[test]
+fn test_vrc7b_selects_with_a3() {
+    let mut mapper = vrc_mapper(85, 1);
+    mapper.cpu_write(0x8008, 4);
+    assert_eq!(mapper.cpu_peek(0xa000), Some(4));
+    mapper.cpu_write(0x8010, 6);
+    assert_eq!(mapper.cpu_peek(0x8000), Some(6));
+}
+#[test]
+fn test_vrc7_irq() {
+    let mut mapper = vrc_mapper(85, 2);
+    mapper.cpu_write(0xe010, 0xff);
+    mapper.cpu_write(0xf000, 0b110);
+    mapper.cpu_clock();
+    assert!(mapper.irq_asserted());
+    mapper.cpu_write(0xf010, 0);
+    assert!(!mapper.irq_asserted());
+}
+#[test]
+fn test_vrc7_fm_channel_sounds_while_keyed_on() {
+    let mut mapper = vrc_mapper(85, 2);
+    let mut write_audio = |register: u8, data: u8| {
+        mapper.cpu_write(0x9010, register);
+        mapper.cpu_write(0x9030, data);
+    };
+    // channel 0: instrument 3, full volume, ~440 Hz, keyed on
+    write_audio(0x30, 0x30);
+    write_audio(0x10, 0x20);
+    write_audio(0x20, 0x19);
+    let (lowest, highest) = output_range(&mut mapper, 36 * 200);
+    assert!(highest - lowest > 0.05);
+    // volume 15 is -45 dB
+    mapper.cpu_write(0x9010, 0x30);
+    mapper.cpu_write(0x9030, 0x3f);
+    // the level from before the write holds until the next sample
+    output_range(&mut mapper, 36);
+    let (lowest, highest) = output_range(&mut mapper, 36 * 200);
+    assert!(highest - lowest < 0.01);
+}
+#[test]
+fn test_vrc7_is_silent_without_key_on_or_in_reset() {
+    let mut mapper = vrc_mapper(85, 2);
+    assert_eq!(output_range(&mut mapper, 36 * 100), (0.5, 0.5));
+    mapper.cpu_write(0x9010, 0x20);
+    mapper.cpu_write(0x9030, 0x19);
+    mapper.cpu_write(0xe000, 0x40);
+    assert_eq!(output_range(&mut mapper, 36 * 100), (0.5, 0.5));
+}
+#[test]
+fn test_vrc7_output_stays_in_range() {
+    let mut mapper = vrc_mapper(85, 2);
+    // all 6 channels on instrument 1 at full volume, keyed on
+    for channel in 0..6 {
+        for (register, data) in [(0x30, 0x10), (0x10, 0x20), (0x20, 0x19)] {
+            mapper.cpu_write(0x9010, register + channel);
+            mapper.cpu_write(0x9030, data);
+        }
+    }
+    let (lowest, highest) = output_range(&mut mapper, 36 * 2000);
+    assert!(lowest < 0.5 && highest > 0.5);
+    assert!((0.0..=1.0).contains(&lowest) && (0.0..=1.0).contains(&highest));
+}
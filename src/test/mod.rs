@@ -1,11 +1,20 @@
 use super::*;
 #[cfg(test)]
+mod bus_tests;
+#[cfg(test)]
+mod cartridge_tests;
+#[cfg(test)]
 mod cpu_tests;
+#[cfg(test)]
+mod mapper_tests;
+#[cfg(test)]
+mod ppu_tests;
 
 #[test]
 fn test_5_ops_working_together() {
     let mut cpu = cpu::CPU::new();
-    cpu.load_and_run(vec![0xa9, 0xc0, 0xaa, 0xe8, 0x00]);
+    cpu.load_and_run(vec![0xa9, 0xc0, 0xaa, 0xe8, 0x00])
+        .unwrap();
 
     assert_eq!(cpu.register_x, 0xc1)
 }
@@ -13,14 +22,14 @@ fn test_5_ops_working_together() {
 #[test]
 fn test_relative_subtract_addressing_mode() {
     let mut cpu = cpu::CPU::new();
-    cpu.load_and_run(vec![0x90, 0x9a, 0x00]);
-    print!("{}", cpu.program_counter);
-    assert_eq!(cpu.program_counter, (0x8003 - 0b0001_1010));
+    cpu.load_and_run(vec![0x90, 0x9a, 0x00]).unwrap();
+    // $9a is -102, BRK at the branch target pushed its address + 2
+    assert_eq!(cpu.memory_read_2_byte(0x01fc), (0x8002 - 102 + 2));
 }
 #[test]
 fn test_relative_add_addressing_mode() {
     let mut cpu = cpu::CPU::new();
-    cpu.load_and_run(vec![0x90, 0b0001_1010, 0x00]);
-    print!("{}", cpu.program_counter);
-    assert_eq!(cpu.program_counter, (0x8003 + 0b0001_1010));
+    cpu.load_and_run(vec![0x90, 0b0001_1010, 0x00]).unwrap();
+    // BRK at the branch target pushed its address + 2
+    assert_eq!(cpu.memory_read_2_byte(0x01fc), (0x8002 + 0b0001_1010 + 2));
 }
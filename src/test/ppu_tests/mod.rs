@@ -0,0 +1 @@
+mod register_tests;
@@ -0,0 +1,179 @@
+use crate::mapper::*;
+use crate::ppu::*;
+use crate::test::mapper_tests::mapper_from;
+
+// NROM with 8 KiB CHR-RAM and vertical mirroring
+fn chr_ram_mapper() -> Box<dyn Mapper> {
+    mapper_from(&[1, 0, 0x01], &[0; 0x4000], &[])
+}
+fn set_address(ppu: &mut Ppu, address: u16) {
+    ppu.write_register(PPUADDR, (address >> 8) as u8, None);
+    ppu.write_register(PPUADDR, address as u8, None);
+}
+fn write_data(ppu: &mut Ppu, mapper: &mut Box<dyn Mapper>, address: u16, data: &[u8]) {
+    set_address(ppu, address);
+    for byte in data {
+        ppu.write_register(PPUDATA, *byte, Some(mapper));
+    }
+}
+
+#[test]
+fn test_status_read_clears_vblank_and_toggle() {
+    let mut ppu = Ppu::new();
+    ppu.start_vblank();
+    assert_eq!(ppu.peek_register(PPUSTATUS) & 0x80, 0x80);
+    // first PPUADDR write pending
+    ppu.write_register(PPUADDR, 0x21, None);
+    assert_eq!(ppu.read_register(PPUSTATUS, None) & 0x80, 0x80);
+    assert_eq!(ppu.read_register(PPUSTATUS, None) & 0x80, 0x00);
+    ppu.write_register(PPUADDR, 0x23, None);
+    ppu.write_register(PPUADDR, 0xc0, None);
+    assert_eq!(ppu.temp_vram_address(), 0x23c0);
+}
+#[test]
+fn test_status_low_bits_come_from_the_latch() {
+    let mut ppu = Ppu::new();
+    ppu.start_vblank();
+    ppu.write_register(PPUMASK, 0x1f, None);
+    assert_eq!(ppu.read_register(PPUSTATUS, None), 0x9f);
+}
+#[test]
+fn test_write_only_registers_read_the_decaying_latch() {
+    let mut ppu = Ppu::new();
+    ppu.write_register(PPUCTRL, 0x5a, None);
+    assert_eq!(ppu.read_register(PPUMASK, None), 0x5a);
+    assert_eq!(ppu.read_register(PPUSCROLL, None), 0x5a);
+    for _ in 0..1_000_000 / 255 {
+        ppu.tick(255);
+    }
+    assert_eq!(ppu.peek_register(PPUADDR), 0x5a);
+    // bits driven again by PPUSTATUS stay, the rest fade out
+    ppu.start_vblank();
+    ppu.read_register(PPUSTATUS, None);
+    for _ in 0..100_000 / 255 {
+        ppu.tick(255);
+    }
+    assert_eq!(ppu.peek_register(PPUCTRL), 0x80);
+}
+#[test]
+fn test_oam_data() {
+    let mut ppu = Ppu::new();
+    ppu.write_register(OAMADDR, 0xfd, None);
+    for _ in 0..4 {
+        ppu.write_register(OAMDATA, 0xff, None);
+    }
+    ppu.write_register(OAMADDR, 0xfd, None);
+    ppu.write_register(OAMDATA, 0x11, None);
+    ppu.write_register(OAMADDR, 0xfd, None);
+    // reads do not advance OAMADDR, writes wrap around
+    assert_eq!(ppu.read_register(OAMDATA, None), 0x11);
+    assert_eq!(ppu.read_register(OAMDATA, None), 0x11);
+    ppu.write_register(OAMADDR, 0x00, None);
+    assert_eq!(ppu.read_register(OAMDATA, None), 0xff);
+    // attribute bytes lack bits 2-4
+    ppu.write_register(OAMADDR, 0xfe, None);
+    assert_eq!(ppu.read_register(OAMDATA, None), 0xe3);
+}
+#[test]
+fn test_data_reads_are_buffered() {
+    let mut ppu = Ppu::new();
+    let mut mapper = chr_ram_mapper();
+    write_data(&mut ppu, &mut mapper, 0x0100, &[0x11, 0x22, 0x33]);
+    set_address(&mut ppu, 0x0100);
+    assert_eq!(ppu.read_register(PPUDATA, Some(&mut mapper)), 0x00);
+    assert_eq!(ppu.read_register(PPUDATA, Some(&mut mapper)), 0x11);
+    assert_eq!(ppu.peek_register(PPUDATA), 0x22);
+    assert_eq!(ppu.read_register(PPUDATA, Some(&mut mapper)), 0x22);
+    assert_eq!(mapper.ppu_peek(0x0102), 0x33);
+}
+#[test]
+fn test_data_increment_of_32() {
+    let mut ppu = Ppu::new();
+    let mut mapper = chr_ram_mapper();
+    ppu.write_register(PPUCTRL, 0x04, None);
+    write_data(&mut ppu, &mut mapper, 0x2000, &[0x11, 0x22]);
+    assert_eq!(mapper.nametable_peek(0x2000), None);
+    ppu.write_register(PPUCTRL, 0x00, None);
+    set_address(&mut ppu, 0x2020);
+    ppu.read_register(PPUDATA, Some(&mut mapper));
+    assert_eq!(ppu.read_register(PPUDATA, Some(&mut mapper)), 0x22);
+}
+#[test]
+fn test_nametables_follow_mirroring() {
+    let mut ppu = Ppu::new();
+    let mut mapper = chr_ram_mapper();
+    write_data(&mut ppu, &mut mapper, 0x2005, &[0x11]);
+    write_data(&mut ppu, &mut mapper, 0x2405, &[0x22]);
+    // vertical: $2800 mirrors $2000, $3000-$3EFF mirrors $2000-$2EFF
+    set_address(&mut ppu, 0x2805);
+    ppu.read_register(PPUDATA, Some(&mut mapper));
+    assert_eq!(ppu.read_register(PPUDATA, Some(&mut mapper)), 0x11);
+    set_address(&mut ppu, 0x3c05);
+    ppu.read_register(PPUDATA, Some(&mut mapper));
+    assert_eq!(ppu.read_register(PPUDATA, Some(&mut mapper)), 0x22);
+}
+#[test]
+fn test_palette_reads_bypass_the_buffer() {
+    let mut ppu = Ppu::new();
+    let mut mapper = chr_ram_mapper();
+    write_data(&mut ppu, &mut mapper, 0x2f00, &[0x55]);
+    write_data(&mut ppu, &mut mapper, 0x3f00, &[0x2a]);
+    set_address(&mut ppu, 0x3f00);
+    assert_eq!(ppu.read_register(PPUDATA, Some(&mut mapper)) & 0x3f, 0x2a);
+    // the buffer holds the nametable byte under the palette
+    set_address(&mut ppu, 0x0000);
+    assert_eq!(ppu.read_register(PPUDATA, Some(&mut mapper)), 0x55);
+}
+#[test]
+fn test_palette_reads_take_top_bits_from_the_latch() {
+    let mut ppu = Ppu::new();
+    let mut mapper = chr_ram_mapper();
+    write_data(&mut ppu, &mut mapper, 0x3f01, &[0xff]);
+    set_address(&mut ppu, 0x3f01);
+    // the last write drove $01 onto the bus
+    assert_eq!(ppu.read_register(PPUDATA, Some(&mut mapper)), 0x3f);
+    set_address(&mut ppu, 0x3f01);
+    ppu.write_register(OAMADDR, 0xff, None);
+    assert_eq!(ppu.read_register(PPUDATA, Some(&mut mapper)), 0xff);
+}
+#[test]
+fn test_palette_mirrors_and_greyscale() {
+    let mut ppu = Ppu::new();
+    let mut mapper = chr_ram_mapper();
+    write_data(&mut ppu, &mut mapper, 0x3f10, &[0x21]);
+    write_data(&mut ppu, &mut mapper, 0x3f04, &[0x12]);
+    set_address(&mut ppu, 0x3f00);
+    assert_eq!(ppu.read_register(PPUDATA, Some(&mut mapper)) & 0x3f, 0x21);
+    set_address(&mut ppu, 0x3f14);
+    assert_eq!(ppu.read_register(PPUDATA, Some(&mut mapper)) & 0x3f, 0x12);
+    // mirrored every 32 bytes up to $3FFF
+    set_address(&mut ppu, 0x3fe0);
+    assert_eq!(ppu.read_register(PPUDATA, Some(&mut mapper)) & 0x3f, 0x21);
+    ppu.write_register(PPUMASK, 0x01, None);
+    set_address(&mut ppu, 0x3f00);
+    assert_eq!(ppu.read_register(PPUDATA, Some(&mut mapper)) & 0x3f, 0x20);
+}
+#[test]
+fn test_scroll_and_ctrl_fill_the_temporary_address() {
+    let mut ppu = Ppu::new();
+    ppu.write_register(PPUCTRL, 0x03, None);
+    ppu.write_register(PPUSCROLL, 0x7d, None);
+    ppu.write_register(PPUSCROLL, 0x5e, None);
+    assert_eq!(ppu.fine_x_scroll(), 0x05);
+    // fine Y 6, coarse Y 11, nametable 3, coarse X 15
+    assert_eq!(ppu.temp_vram_address(), 0x6d6f);
+    // PPUSCROLL and PPUADDR share the toggle
+    ppu.write_register(PPUSCROLL, 0x00, None);
+    ppu.write_register(PPUADDR, 0x12, None);
+    assert_eq!(ppu.temp_vram_address(), 0x6d12);
+}
+#[test]
+fn test_nmi_follows_vblank_and_ctrl() {
+    let mut ppu = Ppu::new();
+    ppu.start_vblank();
+    assert!(!ppu.nmi_asserted());
+    ppu.write_register(PPUCTRL, 0x80, None);
+    assert!(ppu.nmi_asserted());
+    ppu.end_vblank();
+    assert!(!ppu.nmi_asserted());
+}
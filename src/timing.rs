@@ -0,0 +1,49 @@
+const NTSC_MASTER_CLOCK_HZ: u64 = 21_477_272;
+const PAL_MASTER_CLOCK_HZ: u64 = 26_601_712;
+const DOTS_PER_SCANLINE: u64 = 341;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Region {
+    // RP2A03 + RP2C02
+    Ntsc,
+    // RP2A07 + RP2C07
+    Pal,
+    // the game runs on either, we pick NTSC
+    MultiRegion,
+    // UA6538 famiclones: PAL frame with NTSC-like CPU to PPU ratio
+    Dendy,
+}
+
+// CPU and PPU clocks are both derived from one master clock by fixed dividers
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimingConfig {
+    pub region: Region,
+    pub master_clock_hz: u64,
+    pub cpu_divider: u64,
+    pub ppu_divider: u64,
+    pub scanlines_per_frame: u64,
+}
+
+impl TimingConfig {
+    pub fn for_region(region: Region) -> TimingConfig {
+        let (master_clock_hz, cpu_divider, ppu_divider, scanlines_per_frame) = match region {
+            Region::Ntsc | Region::MultiRegion => (NTSC_MASTER_CLOCK_HZ, 12, 4, 262),
+            Region::Pal => (PAL_MASTER_CLOCK_HZ, 16, 5, 312),
+            Region::Dendy => (PAL_MASTER_CLOCK_HZ, 15, 5, 312),
+        };
+        TimingConfig {
+            region,
+            master_clock_hz,
+            cpu_divider,
+            ppu_divider,
+            scanlines_per_frame,
+        }
+    }
+    pub fn cpu_clock_hz(&self) -> u64 {
+        self.master_clock_hz / self.cpu_divider
+    }
+    // rounded down, NTSC is really 29780.67 because of the skipped dot on odd frames
+    pub fn cpu_cycles_per_frame(&self) -> u64 {
+        DOTS_PER_SCANLINE * self.scanlines_per_frame * self.ppu_divider / self.cpu_divider
+    }
+}